@@ -0,0 +1,88 @@
+//! Compares the multiscalar multiplications on BLS12-381 G1 with the GLV decomposition of the
+//! scalars to the ones on the full length scalars. Run it with
+//! `cargo bench --features __private_bench --bench glv`.
+
+use blstrs::{G1Affine, G1Projective, Scalar as Fr};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ff::{Field, PrimeField};
+use group::{Curve, Group};
+use rand_core::SeedableRng;
+
+use bellperson::__private_bench::{
+    par_multiscalar_generic, par_multiscalar_glv, precompute_fixed_window, Getter, Glv, ScalarList,
+    WINDOW_SIZE,
+};
+
+fn random_input(num_points: usize) -> (Vec<G1Affine>, Vec<<Fr as PrimeField>::Repr>) {
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+    let points = (0..num_points)
+        .map(|_| G1Projective::random(&mut rng).to_affine())
+        .collect();
+    let scalars = (0..num_points)
+        .map(|_| Fr::random(&mut rng).to_repr())
+        .collect();
+    (points, scalars)
+}
+
+/// The fixed-window multiscalar multiplication with precomputed tables, used by the verifiers and
+/// the aggregation.
+fn bench_multiscalar(c: &mut Criterion) {
+    let glv = Glv::<G1Affine>::of().unwrap();
+    let mut group = c.benchmark_group("par_multiscalar");
+    for num_points in [1, 4, 16, 64, 256, 1024, 4096] {
+        let (points, scalars) = random_input(num_points);
+        let table = precompute_fixed_window(&points, WINDOW_SIZE);
+        let scalars = ScalarList::<_, &Getter<G1Affine>>::Slice(&scalars);
+
+        group.bench_with_input(BenchmarkId::new("generic", num_points), &(), |b, _| {
+            b.iter(|| par_multiscalar_generic(&scalars, &table, 256))
+        });
+        group.bench_with_input(BenchmarkId::new("glv", num_points), &(), |b, _| {
+            b.iter(|| par_multiscalar_glv(&glv, &scalars, &table))
+        });
+    }
+    group.finish();
+}
+
+/// The bucket method used by the prover on the CPU.
+#[cfg(feature = "prover")]
+fn bench_multiexp(c: &mut Criterion) {
+    use std::sync::Arc;
+
+    use bellperson::__private_bench::multiexp_cpu_glv;
+    use ec_gpu_gen::multiexp_cpu::{multiexp_cpu, FullDensity};
+    use ec_gpu_gen::threadpool::Worker;
+
+    let pool = Worker::new();
+    let glv = Glv::<G1Affine>::of().unwrap();
+    let mut group = c.benchmark_group("multiexp_cpu");
+    group.sample_size(10);
+    for log_num_points in [6, 10, 14, 16] {
+        let num_points = 1 << log_num_points;
+        let (points, scalars) = random_input(num_points);
+        let (points, scalars) = (Arc::new(points), Arc::new(scalars));
+
+        group.bench_with_input(BenchmarkId::new("generic", num_points), &(), |b, _| {
+            b.iter(|| {
+                multiexp_cpu::<_, _, G1Affine, _>(
+                    &pool,
+                    (points.clone(), 0),
+                    FullDensity,
+                    scalars.clone(),
+                )
+                .wait()
+                .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("glv", num_points), &(), |b, _| {
+            b.iter(|| multiexp_cpu_glv(&glv, &points, &scalars))
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "prover")]
+criterion_group!(benches, bench_multiscalar, bench_multiexp);
+#[cfg(not(feature = "prover"))]
+criterion_group!(benches, bench_multiscalar);
+criterion_main!(benches);
@@ -2,14 +2,23 @@
 
 pub mod test;
 
+pub mod blake2b;
 pub mod blake2s;
 pub use bellpepper_core::boolean;
+pub mod jubjub;
+pub mod keccak;
 pub mod lookup;
+pub mod merkle;
 pub mod multieq;
 pub mod multipack;
 pub use bellpepper_core::num;
+pub mod pedersen_hash;
+pub mod poseidon;
+pub mod range;
 pub mod sha256;
+pub mod sha512;
 pub mod uint32;
+pub mod uint64;
 
 use bellpepper_core::SynthesisError;
 
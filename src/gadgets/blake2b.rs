@@ -0,0 +1,382 @@
+//! The [BLAKE2b] hash function with key, salt and personalization support.
+//!
+//! The functions follow the [`blake2s`](super::blake2s) gadget, with 64-bit
+//! words, 128-byte blocks and 12 rounds. The input and output bits are in
+//! little-endian order within each byte.
+//!
+//! [BLAKE2b]: https://tools.ietf.org/html/rfc7693
+
+#![allow(clippy::many_single_char_names)]
+
+use ff::PrimeField;
+
+use super::{blake2s::SIGMA, boolean::Boolean, multieq::MultiEq, uint64::UInt64};
+use bellpepper_core::{ConstraintSystem, SynthesisError};
+
+const R1: usize = 32;
+const R2: usize = 24;
+const R3: usize = 16;
+const R4: usize = 63;
+
+#[allow(clippy::unreadable_literal)]
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The mixing function G, see section 3.1 of RFC 7693.
+#[allow(clippy::too_many_arguments)]
+fn mixing_g<Scalar: PrimeField, CS: ConstraintSystem<Scalar>, M>(
+    mut cs: M,
+    v: &mut [UInt64],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    x: &UInt64,
+    y: &UInt64,
+) -> Result<(), SynthesisError>
+where
+    M: ConstraintSystem<Scalar, Root = MultiEq<Scalar, CS>>,
+{
+    v[a] = UInt64::addmany(
+        cs.namespace(|| "mixing step 1"),
+        &[v[a].clone(), v[b].clone(), x.clone()],
+    )?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 2"), &v[a])?.rotr(R1);
+    v[c] = UInt64::addmany(
+        cs.namespace(|| "mixing step 3"),
+        &[v[c].clone(), v[d].clone()],
+    )?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 4"), &v[c])?.rotr(R2);
+    v[a] = UInt64::addmany(
+        cs.namespace(|| "mixing step 5"),
+        &[v[a].clone(), v[b].clone(), y.clone()],
+    )?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 6"), &v[a])?.rotr(R3);
+    v[c] = UInt64::addmany(
+        cs.namespace(|| "mixing step 7"),
+        &[v[c].clone(), v[d].clone()],
+    )?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 8"), &v[c])?.rotr(R4);
+
+    Ok(())
+}
+
+/// The compression function F, see section 3.2 of RFC 7693.
+fn blake2b_compression<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    h: &mut [UInt64],
+    m: &[UInt64],
+    t: u128,
+    f: bool,
+) -> Result<(), SynthesisError> {
+    assert_eq!(h.len(), 8);
+    assert_eq!(m.len(), 16);
+
+    let mut v = Vec::with_capacity(16);
+    v.extend_from_slice(h);
+    v.extend(IV.iter().map(|iv| UInt64::constant(*iv)));
+
+    v[12] = v[12].xor(cs.namespace(|| "first xor"), &UInt64::constant(t as u64))?;
+    v[13] = v[13].xor(
+        cs.namespace(|| "second xor"),
+        &UInt64::constant((t >> 64) as u64),
+    )?;
+
+    if f {
+        v[14] = v[14].xor(
+            cs.namespace(|| "third xor"),
+            &UInt64::constant(u64::max_value()),
+        )?;
+    }
+
+    {
+        let mut cs = MultiEq::new(&mut cs);
+
+        for i in 0..12 {
+            let mut cs = cs.namespace(|| format!("round {}", i));
+
+            let s = SIGMA[i % 10];
+
+            for (j, &(a, b, c, d)) in [
+                (0, 4, 8, 12),
+                (1, 5, 9, 13),
+                (2, 6, 10, 14),
+                (3, 7, 11, 15),
+                (0, 5, 10, 15),
+                (1, 6, 11, 12),
+                (2, 7, 8, 13),
+                (3, 4, 9, 14),
+            ]
+            .iter()
+            .enumerate()
+            {
+                mixing_g(
+                    cs.namespace(|| format!("mixing invocation {}", j + 1)),
+                    &mut v,
+                    a,
+                    b,
+                    c,
+                    d,
+                    &m[s[2 * j]],
+                    &m[s[2 * j + 1]],
+                )?;
+            }
+        }
+    }
+
+    for i in 0..8 {
+        let mut cs = cs.namespace(|| format!("h[{i}] ^ v[{i}] ^ v[{i} + 8]", i = i));
+
+        h[i] = h[i].xor(cs.namespace(|| "first xor"), &v[i])?;
+        h[i] = h[i].xor(cs.namespace(|| "second xor"), &v[i + 8])?;
+    }
+
+    Ok(())
+}
+
+/// BLAKE2b with a 64-byte output and a 16-byte `personalization`.
+pub fn blake2b<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
+    cs: CS,
+    input: &[Boolean],
+    personalization: &[u8],
+) -> Result<Vec<Boolean>, SynthesisError> {
+    assert_eq!(personalization.len(), 16);
+
+    blake2b_with_params(cs, input, &[], 64, &[], personalization)
+}
+
+/// BLAKE2b with an output of `output_len` bytes, from 1 to 64, and an optional
+/// `key` of up to 64 bytes. The `salt` and the `personalization` have up to 16
+/// bytes, and are padded with zeros like in `blake2b_simd`. The key is part of
+/// the circuit, but its length is not hidden.
+pub fn blake2b_with_params<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    input: &[Boolean],
+    key: &[Boolean],
+    output_len: usize,
+    salt: &[u8],
+    personalization: &[u8],
+) -> Result<Vec<Boolean>, SynthesisError> {
+    use byteorder::{ByteOrder, LittleEndian};
+
+    assert!((1..=64).contains(&output_len));
+    assert!(key.len() % 8 == 0 && key.len() <= 512);
+    assert!(salt.len() <= 16);
+    assert!(personalization.len() <= 16);
+    assert!(input.len() % 8 == 0);
+
+    let mut salt_bytes = [0u8; 16];
+    salt_bytes[..salt.len()].copy_from_slice(salt);
+    let mut personalization_bytes = [0u8; 16];
+    personalization_bytes[..personalization.len()].copy_from_slice(personalization);
+
+    let key_len = (key.len() / 8) as u64;
+    let mut h = vec![
+        UInt64::constant(IV[0] ^ 0x0101_0000 ^ (key_len << 8) ^ output_len as u64),
+        UInt64::constant(IV[1]),
+        UInt64::constant(IV[2]),
+        UInt64::constant(IV[3]),
+        // Salt is stored here
+        UInt64::constant(IV[4] ^ LittleEndian::read_u64(&salt_bytes[0..8])),
+        UInt64::constant(IV[5] ^ LittleEndian::read_u64(&salt_bytes[8..16])),
+        // Personalization is stored here
+        UInt64::constant(IV[6] ^ LittleEndian::read_u64(&personalization_bytes[0..8])),
+        UInt64::constant(IV[7] ^ LittleEndian::read_u64(&personalization_bytes[8..16])),
+    ];
+
+    // The key, padded to a full block, comes before the input.
+    let mut data = Vec::with_capacity(1024 + input.len());
+    if !key.is_empty() {
+        data.extend_from_slice(key);
+        data.resize(1024, Boolean::constant(false));
+    }
+    data.extend_from_slice(input);
+
+    let mut blocks: Vec<Vec<UInt64>> = vec![];
+
+    for block in data.chunks(1024) {
+        let mut this_block = Vec::with_capacity(16);
+        for word in block.chunks(64) {
+            let mut tmp = word.to_vec();
+            while tmp.len() < 64 {
+                tmp.push(Boolean::constant(false));
+            }
+            this_block.push(UInt64::from_bits(&tmp));
+        }
+        while this_block.len() < 16 {
+            this_block.push(UInt64::constant(0));
+        }
+        blocks.push(this_block);
+    }
+
+    if blocks.is_empty() {
+        blocks.push((0..16).map(|_| UInt64::constant(0)).collect());
+    }
+
+    for (i, block) in blocks[0..blocks.len() - 1].iter().enumerate() {
+        let cs = cs.namespace(|| format!("block {}", i));
+
+        blake2b_compression(cs, &mut h, block, ((i as u128) + 1) * 128, false)?;
+    }
+
+    {
+        let cs = cs.namespace(|| "final block");
+
+        blake2b_compression(
+            cs,
+            &mut h,
+            &blocks[blocks.len() - 1],
+            (data.len() / 8) as u128,
+            true,
+        )?;
+    }
+
+    let mut output: Vec<Boolean> = h.into_iter().flat_map(|b| b.into_bits()).collect();
+    output.truncate(output_len * 8);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use blake2b_simd::Params as Blake2bParams;
+    use blstrs::Scalar as Fr;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::{blake2b, blake2b_with_params};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::ConstraintSystem;
+
+    fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+        bytes
+            .iter()
+            .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+            .collect()
+    }
+
+    fn alloc_bits(cs: &mut TestConstraintSystem<Fr>, name: &str, bytes: &[u8]) -> Vec<Boolean> {
+        bytes_to_bits(bytes)
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), Some(b))
+                    .unwrap()
+                    .into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_blank_hash() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let out = blake2b(&mut cs, &[], b"1234567890123456").unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+
+        let expected = Blake2bParams::new().personal(b"1234567890123456").hash(&[]);
+        let out: Vec<bool> = out.iter().map(|b| b.get_value().unwrap()).collect();
+        assert_eq!(out, bytes_to_bits(expected.as_bytes()));
+    }
+
+    #[test]
+    fn test_blake2b_constraints() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let input_bits = alloc_bits(&mut cs, "input", &[0xff; 128]);
+        blake2b(&mut cs, &input_bits, b"1234567890123456").unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 50880);
+    }
+
+    #[test]
+    fn test_blake2b_constant_constraints() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let input_bits: Vec<_> = (0..1024)
+            .map(|_| Boolean::constant(rng.next_u32() % 2 != 0))
+            .collect();
+        blake2b(&mut cs, &input_bits, b"1234567890123456").unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_blake2b() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for input_len in (0..16).chain([127, 128, 129, 256, 300]) {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.next_u32() as u8).collect();
+            let hash_result = Blake2bParams::new()
+                .personal(b"1234567890123456")
+                .hash(&data);
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let input_bits = alloc_bits(&mut cs, "input", &data);
+            let r = blake2b(&mut cs, &input_bits, b"1234567890123456").unwrap();
+            assert!(cs.is_satisfied());
+
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(r, bytes_to_bits(hash_result.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_blake2b_with_params() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for (input_len, key_len, output_len, salt_len, personalization_len) in [
+            (0, 0, 1, 0, 0),
+            (0, 1, 64, 16, 16),
+            (3, 64, 32, 5, 0),
+            (128, 32, 20, 0, 9),
+            (129, 7, 64, 16, 16),
+        ] {
+            let mut random_bytes =
+                |len: usize| -> Vec<u8> { (0..len).map(|_| rng.next_u32() as u8).collect() };
+            let data = random_bytes(input_len);
+            let key = random_bytes(key_len);
+            let salt = random_bytes(salt_len);
+            let personalization = random_bytes(personalization_len);
+
+            let hash_result = Blake2bParams::new()
+                .hash_length(output_len)
+                .key(&key)
+                .salt(&salt)
+                .personal(&personalization)
+                .hash(&data);
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let input_bits = alloc_bits(&mut cs, "input", &data);
+            let key_bits = alloc_bits(&mut cs, "key", &key);
+            let r = blake2b_with_params(
+                &mut cs,
+                &input_bits,
+                &key_bits,
+                output_len,
+                &salt,
+                &personalization,
+            )
+            .unwrap();
+            assert!(cs.is_satisfied());
+
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(r, bytes_to_bits(hash_result.as_bytes()));
+        }
+    }
+}
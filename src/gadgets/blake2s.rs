@@ -47,7 +47,7 @@ const R4: usize = 7;
         ----------+-------------------------------------------------+
 */
 
-const SIGMA: [[usize; 16]; 10] = [
+pub(super) const SIGMA: [[usize; 16]; 10] = [
     [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
     [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
     [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
@@ -341,31 +341,67 @@ fn blake2s_compression<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
         END FUNCTION.
 */
 
+/// BLAKE2s with a 32-byte output and an 8-byte `personalization`.
 pub fn blake2s<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
+    cs: CS,
+    input: &[Boolean],
+    personalization: &[u8],
+) -> Result<Vec<Boolean>, SynthesisError> {
+    assert_eq!(personalization.len(), 8);
+
+    blake2s_with_params(cs, input, &[], 32, &[], personalization)
+}
+
+/// BLAKE2s with an output of `output_len` bytes, from 1 to 32, and an optional
+/// `key` of up to 32 bytes. The `salt` and the `personalization` have up to 8
+/// bytes, and are padded with zeros like in `blake2s_simd`. The key is part of
+/// the circuit, but its length is not hidden.
+pub fn blake2s_with_params<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
     mut cs: CS,
     input: &[Boolean],
+    key: &[Boolean],
+    output_len: usize,
+    salt: &[u8],
     personalization: &[u8],
 ) -> Result<Vec<Boolean>, SynthesisError> {
     use byteorder::{ByteOrder, LittleEndian};
 
-    assert_eq!(personalization.len(), 8);
+    assert!((1..=32).contains(&output_len));
+    assert!(key.len() % 8 == 0 && key.len() <= 256);
+    assert!(salt.len() <= 8);
+    assert!(personalization.len() <= 8);
     assert!(input.len() % 8 == 0);
 
+    let mut salt_bytes = [0u8; 8];
+    salt_bytes[..salt.len()].copy_from_slice(salt);
+    let mut personalization_bytes = [0u8; 8];
+    personalization_bytes[..personalization.len()].copy_from_slice(personalization);
+
+    let key_len = (key.len() / 8) as u32;
     let mut h = vec![
-        UInt32::constant(0x6A09_E667 ^ 0x0101_0000 ^ 32),
+        UInt32::constant(0x6A09_E667 ^ 0x0101_0000 ^ (key_len << 8) ^ output_len as u32),
         UInt32::constant(0xBB67_AE85),
         UInt32::constant(0x3C6E_F372),
         UInt32::constant(0xA54F_F53A),
-        UInt32::constant(0x510E_527F),
-        UInt32::constant(0x9B05_688C),
+        // Salt is stored here
+        UInt32::constant(0x510E_527F ^ LittleEndian::read_u32(&salt_bytes[0..4])),
+        UInt32::constant(0x9B05_688C ^ LittleEndian::read_u32(&salt_bytes[4..8])),
         // Personalization is stored here
-        UInt32::constant(0x1F83_D9AB ^ LittleEndian::read_u32(&personalization[0..4])),
-        UInt32::constant(0x5BE0_CD19 ^ LittleEndian::read_u32(&personalization[4..8])),
+        UInt32::constant(0x1F83_D9AB ^ LittleEndian::read_u32(&personalization_bytes[0..4])),
+        UInt32::constant(0x5BE0_CD19 ^ LittleEndian::read_u32(&personalization_bytes[4..8])),
     ];
 
+    // The key, padded to a full block, comes before the input.
+    let mut data = Vec::with_capacity(512 + input.len());
+    if !key.is_empty() {
+        data.extend_from_slice(key);
+        data.resize(512, Boolean::constant(false));
+    }
+    data.extend_from_slice(input);
+
     let mut blocks: Vec<Vec<UInt32>> = vec![];
 
-    for block in input.chunks(512) {
+    for block in data.chunks(512) {
         let mut this_block = Vec::with_capacity(16);
         for word in block.chunks(32) {
             let mut tmp = word.to_vec();
@@ -397,12 +433,14 @@ pub fn blake2s<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
             cs,
             &mut h,
             &blocks[blocks.len() - 1],
-            (input.len() / 8) as u64,
+            (data.len() / 8) as u64,
             true,
         )?;
     }
 
-    Ok(h.into_iter().flat_map(|b| b.into_bits()).collect())
+    let mut output: Vec<Boolean> = h.into_iter().flat_map(|b| b.into_bits()).collect();
+    output.truncate(output_len * 8);
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -412,7 +450,7 @@ mod test {
     use rand_core::{RngCore, SeedableRng};
     use rand_xorshift::XorShiftRng;
 
-    use super::blake2s;
+    use super::{blake2s, blake2s_with_params};
     use crate::gadgets::boolean::{AllocatedBit, Boolean};
     use crate::gadgets::test::TestConstraintSystem;
     use crate::ConstraintSystem;
@@ -553,6 +591,72 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_blake2s_with_params() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for (input_len, key_len, output_len, salt_len, personalization_len) in [
+            (0, 0, 1, 0, 0),
+            (0, 1, 32, 8, 8),
+            (3, 32, 20, 5, 0),
+            (64, 16, 16, 0, 3),
+            (65, 7, 32, 8, 8),
+            (200, 0, 31, 1, 8),
+        ] {
+            let mut random_bytes =
+                |len: usize| -> Vec<u8> { (0..len).map(|_| rng.next_u32() as u8).collect() };
+            let data = random_bytes(input_len);
+            let key = random_bytes(key_len);
+            let salt = random_bytes(salt_len);
+            let personalization = random_bytes(personalization_len);
+
+            let hash_result = Blake2sParams::new()
+                .hash_length(output_len)
+                .key(&key)
+                .salt(&salt)
+                .personal(&personalization)
+                .hash(&data);
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let mut alloc_bits = |name: &str, bytes: &[u8]| -> Vec<Boolean> {
+                bytes
+                    .iter()
+                    .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+                    .enumerate()
+                    .map(|(i, b)| {
+                        AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), Some(b))
+                            .unwrap()
+                            .into()
+                    })
+                    .collect()
+            };
+            let input_bits = alloc_bits("input", &data);
+            let key_bits = alloc_bits("key", &key);
+
+            let r = blake2s_with_params(
+                &mut cs,
+                &input_bits,
+                &key_bits,
+                output_len,
+                &salt,
+                &personalization,
+            )
+            .unwrap();
+            assert!(cs.is_satisfied());
+
+            let expected: Vec<bool> = hash_result
+                .as_bytes()
+                .iter()
+                .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+                .collect();
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(r, expected);
+        }
+    }
+
     #[test]
     fn test_blake2s_256_vars() {
         let data: Vec<u8> = hex!("be9f9c485e670acce8b1516a378176161b20583637b6f1c536fbc1158a0a3296831df2920e57a442d5738f4be4dd6be89dd7913fc8b4d1c0a815646a4d674b77f7caf313bd880bf759fcac27037c48c2b2a20acd2fd5248e3be426c84a341c0a3c63eaf36e0d537d10b8db5c6e4c801832c41eb1a3ed602177acded8b4b803bd34339d99a18b71df399641cc8dfae2ad193fcd74b5913e704551777160d14c78f2e8d5c32716a8599c1080cb89a40ccd6ba596694a8b4a065d9f2d0667ef423ed2e418093caff884540858b4f4b62acd47edcea880523e1b1cda8eb225c128c2e9e83f14f6e7448c5733a195cac7d79a53dde5083172462c45b2f799e42af1c9").to_vec();
@@ -0,0 +1,897 @@
+//! The [Jubjub] curve, natively and as a circuit.
+//!
+//! Jubjub is the twisted Edwards curve $-u^2 + v^2 = 1 + d u^2 v^2$ with
+//! $d = -10240/10241$, defined over the scalar field of BLS12-381, so that its
+//! points are pairs of field elements in the circuit. Its group has order
+//! $8 r_J$ for a prime $r_J$ of 252 bits. The addition law is complete, so that
+//! points are added without special cases, including the identity $(0, 1)$.
+//!
+//! The curve is birationally equivalent to the Montgomery curve
+//! $y^2 = x^3 + A x^2 + x$ with $A = 40962$. Montgomery additions are cheaper in
+//! the circuit but incomplete, and are only used where the points are known to
+//! be distinct and not the identity, as in the Pedersen hash.
+//!
+//! Scalars are given as little-endian bits and are not reduced, so that
+//! multiplying by any number of bits is well defined.
+//!
+//! [Jubjub]: https://zips.z.cash/protocol/protocol.pdf#jubjub
+
+use blstrs::Scalar;
+use ff::{Field, PrimeField};
+
+use super::boolean::Boolean;
+use super::lookup::lookup3_xy;
+use super::num::{AllocatedNum, Num};
+use super::Assignment;
+use bellpepper_core::{ConstraintSystem, SynthesisError};
+
+/// The first block hashed by `group_hash`, as in Zcash.
+const GH_FIRST_BLOCK: &[u8; 64] =
+    b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0";
+
+/// The coefficient $d$ of the twisted Edwards equation.
+fn edwards_d() -> Scalar {
+    -Scalar::from(10240) * Scalar::from(10241).invert().unwrap()
+}
+
+/// The coefficient $A$ of the Montgomery equation.
+fn montgomery_a() -> Scalar {
+    Scalar::from(40962)
+}
+
+/// The scaling factor $\sqrt{-(A + 2)}$ between the Montgomery and the twisted
+/// Edwards coordinates.
+fn montgomery_scale() -> Scalar {
+    (-Scalar::from(40964)).sqrt().unwrap()
+}
+
+/// A point of Jubjub in affine twisted Edwards coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point {
+    u: Scalar,
+    v: Scalar,
+}
+
+impl Point {
+    /// The neutral element $(0, 1)$.
+    pub fn identity() -> Self {
+        Point {
+            u: Scalar::ZERO,
+            v: Scalar::ONE,
+        }
+    }
+
+    /// The point with the given coordinates, if it is on the curve.
+    pub fn from_coordinates(u: Scalar, v: Scalar) -> Option<Self> {
+        let u2 = u.square();
+        let v2 = v.square();
+        if v2 - u2 == Scalar::ONE + edwards_d() * u2 * v2 {
+            Some(Point { u, v })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_u(&self) -> Scalar {
+        self.u
+    }
+
+    pub fn get_v(&self) -> Scalar {
+        self.v
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Decodes a point from the little-endian bytes of $v$, whose top bit is
+    /// replaced by the parity of $u$, as in Zcash. Non-canonical encodings are
+    /// rejected.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let sign = bytes[31] >> 7 == 1;
+        let mut repr = *bytes;
+        repr[31] &= 0x7f;
+        let v: Scalar = Option::from(Scalar::from_bytes_le(&repr))?;
+
+        // u^2 = (v^2 - 1) / (d v^2 + 1)
+        let v2 = v.square();
+        let u2 =
+            (v2 - Scalar::ONE) * Option::<Scalar>::from((edwards_d() * v2 + Scalar::ONE).invert())?;
+        let u: Scalar = Option::from(u2.sqrt())?;
+        if u.is_zero_vartime() && sign {
+            return None;
+        }
+        let u = if bool::from(u.is_odd()) == sign {
+            u
+        } else {
+            -u
+        };
+
+        Some(Point { u, v })
+    }
+
+    /// Encodes the point, like `from_bytes`.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = self.v.to_bytes_le();
+        if bool::from(self.u.is_odd()) {
+            bytes[31] |= 0x80;
+        }
+        bytes
+    }
+
+    /// Hashes `tag` to a point of prime order with BLAKE2s and the given 8-byte
+    /// `personalization`, as in Zcash. Returns `None` if the digest does not
+    /// encode a point, or if the point is of small order.
+    pub fn group_hash(tag: &[u8], personalization: &[u8]) -> Option<Self> {
+        assert_eq!(personalization.len(), 8);
+
+        let h = blake2s_simd::Params::new()
+            .hash_length(32)
+            .personal(personalization)
+            .to_state()
+            .update(GH_FIRST_BLOCK)
+            .update(tag)
+            .finalize();
+
+        let p = Point::from_bytes(h.as_array())?.mul_by_cofactor();
+        if p.is_identity() {
+            None
+        } else {
+            Some(p)
+        }
+    }
+
+    /// Returns the first point given by `group_hash` for `tag` followed by a
+    /// counter byte, as in Zcash.
+    pub fn find_group_hash(tag: &[u8], personalization: &[u8]) -> Self {
+        let mut tag = tag.to_vec();
+        tag.push(0);
+        loop {
+            if let Some(p) = Point::group_hash(&tag, personalization) {
+                return p;
+            }
+            let counter = tag.last_mut().unwrap();
+            *counter = counter.checked_add(1).expect("no point found");
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        *self + *self
+    }
+
+    pub fn mul_by_cofactor(&self) -> Self {
+        self.double().double().double()
+    }
+
+    /// Multiplies the point by the scalar given by its little-endian bits.
+    pub fn mul_bits(&self, bits: &[bool]) -> Self {
+        bits.iter().rev().fold(Self::identity(), |acc, bit| {
+            let acc = acc.double();
+            if *bit {
+                acc + *self
+            } else {
+                acc
+            }
+        })
+    }
+
+    /// The Montgomery coordinates of the point, which is neither the identity
+    /// nor the point $(0, -1)$ of order 2.
+    pub fn to_montgomery(&self) -> Option<(Scalar, Scalar)> {
+        if self.u.is_zero_vartime() {
+            return None;
+        }
+
+        // x = (1 + v) / (1 - v), y = scale * x / u
+        let x = (Scalar::ONE + self.v) * (Scalar::ONE - self.v).invert().unwrap();
+        let y = montgomery_scale() * x * self.u.invert().unwrap();
+        Some((x, y))
+    }
+}
+
+impl std::ops::Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        // u3 = (u1 v2 + v1 u2) / (1 + d u1 u2 v1 v2)
+        // v3 = (v1 v2 + u1 u2) / (1 - d u1 u2 v1 v2)
+        let t = edwards_d() * self.u * other.u * self.v * other.v;
+        let u = (self.u * other.v + self.v * other.u) * (Scalar::ONE + t).invert().unwrap();
+        let v = (self.v * other.v + self.u * other.u) * (Scalar::ONE - t).invert().unwrap();
+        Point { u, v }
+    }
+}
+
+impl std::ops::Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point {
+            u: -self.u,
+            v: self.v,
+        }
+    }
+}
+
+/// Precomputes the table of `fixed_base_multiplication` for `base` and scalars
+/// of up to `3 * num_windows` bits. The window `i` holds the multiples of
+/// `8^i * base` by 0 to 7.
+pub fn fixed_base_table(base: &Point, num_windows: usize) -> Vec<Vec<(Scalar, Scalar)>> {
+    let mut g = *base;
+    (0..num_windows)
+        .map(|_| {
+            let mut window = Vec::with_capacity(8);
+            let mut p = Point::identity();
+            for _ in 0..8 {
+                window.push((p.u, p.v));
+                p = p + g;
+            }
+            g = p;
+            window
+        })
+        .collect()
+}
+
+/// Multiplies a fixed base by the scalar given by its little-endian bits, using
+/// a table from `fixed_base_table` with enough windows. Each window of 3 bits
+/// costs a table lookup and an addition.
+pub fn fixed_base_multiplication<CS>(
+    mut cs: CS,
+    table: &[Vec<(Scalar, Scalar)>],
+    by: &[Boolean],
+) -> Result<EdwardsPoint, SynthesisError>
+where
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(!by.is_empty() && by.len() <= 3 * table.len());
+
+    let mut result: Option<EdwardsPoint> = None;
+    for (i, (chunk, window)) in by.chunks(3).zip(table).enumerate() {
+        let bits: Vec<Boolean> = (0..3)
+            .map(|j| chunk.get(j).cloned().unwrap_or(Boolean::Constant(false)))
+            .collect();
+        let (u, v) = lookup3_xy(cs.namespace(|| format!("window {}", i)), &bits, window)?;
+        let p = EdwardsPoint { u, v };
+
+        result = Some(match result {
+            None => p,
+            Some(result) => result.add(cs.namespace(|| format!("addition {}", i)), &p)?,
+        });
+    }
+
+    Ok(result.unwrap())
+}
+
+/// A point of Jubjub in the circuit, in twisted Edwards coordinates.
+#[derive(Clone)]
+pub struct EdwardsPoint {
+    u: AllocatedNum<Scalar>,
+    v: AllocatedNum<Scalar>,
+}
+
+impl EdwardsPoint {
+    pub fn get_u(&self) -> &AllocatedNum<Scalar> {
+        &self.u
+    }
+
+    pub fn get_v(&self) -> &AllocatedNum<Scalar> {
+        &self.v
+    }
+
+    pub fn get_value(&self) -> Option<Point> {
+        Some(Point {
+            u: self.u.get_value()?,
+            v: self.v.get_value()?,
+        })
+    }
+
+    /// Allocates a point, and enforces that it is on the curve.
+    pub fn witness<CS>(mut cs: CS, p: Option<Point>) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let u = AllocatedNum::alloc(cs.namespace(|| "u"), || Ok(p.get()?.u))?;
+        let v = AllocatedNum::alloc(cs.namespace(|| "v"), || Ok(p.get()?.v))?;
+
+        Self::interpret(cs.namespace(|| "point"), &u, &v)
+    }
+
+    /// Interprets `u` and `v` as a point, and enforces that it is on the curve.
+    /// This costs 4 constraints.
+    pub fn interpret<CS>(
+        mut cs: CS,
+        u: &AllocatedNum<Scalar>,
+        v: &AllocatedNum<Scalar>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let u2 = u.square(cs.namespace(|| "u^2"))?;
+        let v2 = v.square(cs.namespace(|| "v^2"))?;
+        let u2v2 = u2.mul(cs.namespace(|| "u^2 v^2"), &v2)?;
+
+        // (v^2 - u^2) * 1 = 1 + d u^2 v^2
+        cs.enforce(
+            || "on curve",
+            |lc| lc + v2.get_variable() - u2.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + CS::one() + (edwards_d(), u2v2.get_variable()),
+        );
+
+        Ok(EdwardsPoint {
+            u: u.clone(),
+            v: v.clone(),
+        })
+    }
+
+    /// Exposes both coordinates as public inputs.
+    pub fn inputize<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        self.u.inputize(cs.namespace(|| "u"))?;
+        self.v.inputize(cs.namespace(|| "v"))
+    }
+
+    /// The little-endian bits of the encoding of the point, like
+    /// `Point::to_bytes`.
+    pub fn repr<CS>(&self, mut cs: CS) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let u = self.u.to_bits_le_strict(cs.namespace(|| "u bits"))?;
+        let mut bits = self.v.to_bits_le_strict(cs.namespace(|| "v bits"))?;
+        bits.push(u[0].clone());
+        Ok(bits)
+    }
+
+    /// Enforces that the point is not of small order, i.e. that multiplying it
+    /// by the cofactor does not give the identity.
+    pub fn assert_not_small_order<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let p = self.double(cs.namespace(|| "first doubling"))?;
+        let p = p.double(cs.namespace(|| "second doubling"))?;
+        let p = p.double(cs.namespace(|| "third doubling"))?;
+
+        // The only points with u = 0 are the identity and (0, -1), which is
+        // not a multiple of 8.
+        p.u.assert_nonzero(cs.namespace(|| "u != 0"))
+    }
+
+    /// Returns the point if `condition` is true, and the identity otherwise.
+    /// This costs 2 constraints.
+    pub fn conditionally_select<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let selected = condition.get_value();
+        let u = AllocatedNum::alloc(cs.namespace(|| "u"), || {
+            if *selected.get()? {
+                Ok(*self.u.get_value().get()?)
+            } else {
+                Ok(Scalar::ZERO)
+            }
+        })?;
+        let v = AllocatedNum::alloc(cs.namespace(|| "v"), || {
+            if *selected.get()? {
+                Ok(*self.v.get_value().get()?)
+            } else {
+                Ok(Scalar::ONE)
+            }
+        })?;
+
+        // u * condition = u'
+        cs.enforce(
+            || "u selection",
+            |lc| lc + self.u.get_variable(),
+            |_| condition.lc(CS::one(), Scalar::ONE),
+            |lc| lc + u.get_variable(),
+        );
+        // (v - 1) * condition = v' - 1
+        cs.enforce(
+            || "v selection",
+            |lc| lc + self.v.get_variable() - CS::one(),
+            |_| condition.lc(CS::one(), Scalar::ONE),
+            |lc| lc + v.get_variable() - CS::one(),
+        );
+
+        Ok(EdwardsPoint { u, v })
+    }
+
+    /// Adds two points. This costs 6 constraints.
+    pub fn add<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let d = edwards_d();
+
+        // U = (u1 + v1) * (u2 + v2)
+        let uu = AllocatedNum::alloc(cs.namespace(|| "U"), || {
+            let (u1, v1) = (*self.u.get_value().get()?, *self.v.get_value().get()?);
+            let (u2, v2) = (*other.u.get_value().get()?, *other.v.get_value().get()?);
+            Ok((u1 + v1) * (u2 + v2))
+        })?;
+        cs.enforce(
+            || "U computation",
+            |lc| lc + self.u.get_variable() + self.v.get_variable(),
+            |lc| lc + other.u.get_variable() + other.v.get_variable(),
+            |lc| lc + uu.get_variable(),
+        );
+
+        // A = v2 * u1, B = u2 * v1, C = d * A * B
+        let a = other.v.mul(cs.namespace(|| "A"), &self.u)?;
+        let b = other.u.mul(cs.namespace(|| "B"), &self.v)?;
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            Ok(d * a.get_value().get()? * b.get_value().get()?)
+        })?;
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (d, a.get_variable()),
+            |lc| lc + b.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // u3 = (A + B) / (1 + C)
+        let u = AllocatedNum::alloc(cs.namespace(|| "u3"), || {
+            let t = Option::<Scalar>::from((Scalar::ONE + c.get_value().get()?).invert())
+                .ok_or(SynthesisError::DivisionByZero)?;
+            Ok((*a.get_value().get()? + b.get_value().get()?) * t)
+        })?;
+        cs.enforce(
+            || "u3 computation",
+            |lc| lc + CS::one() + c.get_variable(),
+            |lc| lc + u.get_variable(),
+            |lc| lc + a.get_variable() + b.get_variable(),
+        );
+
+        // v3 = (U - A - B) / (1 - C)
+        let v = AllocatedNum::alloc(cs.namespace(|| "v3"), || {
+            let t = Option::<Scalar>::from((Scalar::ONE - c.get_value().get()?).invert())
+                .ok_or(SynthesisError::DivisionByZero)?;
+            Ok((*uu.get_value().get()? - a.get_value().get()? - b.get_value().get()?) * t)
+        })?;
+        cs.enforce(
+            || "v3 computation",
+            |lc| lc + CS::one() - c.get_variable(),
+            |lc| lc + v.get_variable(),
+            |lc| lc + uu.get_variable() - a.get_variable() - b.get_variable(),
+        );
+
+        Ok(EdwardsPoint { u, v })
+    }
+
+    /// Doubles the point. This costs 5 constraints.
+    pub fn double<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let d = edwards_d();
+
+        // T = (u + v)^2
+        let t = AllocatedNum::alloc(cs.namespace(|| "T"), || {
+            Ok((*self.u.get_value().get()? + self.v.get_value().get()?).square())
+        })?;
+        cs.enforce(
+            || "T computation",
+            |lc| lc + self.u.get_variable() + self.v.get_variable(),
+            |lc| lc + self.u.get_variable() + self.v.get_variable(),
+            |lc| lc + t.get_variable(),
+        );
+
+        // A = u * v, C = d * A^2
+        let a = self.u.mul(cs.namespace(|| "A"), &self.v)?;
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            Ok(d * a.get_value().get()?.square())
+        })?;
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (d, a.get_variable()),
+            |lc| lc + a.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // u3 = 2A / (1 + C)
+        let u = AllocatedNum::alloc(cs.namespace(|| "u3"), || {
+            let t = Option::<Scalar>::from((Scalar::ONE + c.get_value().get()?).invert())
+                .ok_or(SynthesisError::DivisionByZero)?;
+            Ok(a.get_value().get()?.double() * t)
+        })?;
+        cs.enforce(
+            || "u3 computation",
+            |lc| lc + CS::one() + c.get_variable(),
+            |lc| lc + u.get_variable(),
+            |lc| lc + a.get_variable() + a.get_variable(),
+        );
+
+        // v3 = (T - 2A) / (1 - C)
+        let v = AllocatedNum::alloc(cs.namespace(|| "v3"), || {
+            let t_inv = Option::<Scalar>::from((Scalar::ONE - c.get_value().get()?).invert())
+                .ok_or(SynthesisError::DivisionByZero)?;
+            Ok((*t.get_value().get()? - a.get_value().get()?.double()) * t_inv)
+        })?;
+        cs.enforce(
+            || "v3 computation",
+            |lc| lc + CS::one() - c.get_variable(),
+            |lc| lc + v.get_variable(),
+            |lc| lc + t.get_variable() - a.get_variable() - a.get_variable(),
+        );
+
+        Ok(EdwardsPoint { u, v })
+    }
+
+    /// Multiplies the point by the scalar given by its little-endian bits, with
+    /// a doubling, a selection and an addition per bit.
+    pub fn mul<CS>(&self, mut cs: CS, by: &[Boolean]) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        assert!(!by.is_empty());
+
+        let mut base = self.clone();
+        let mut result: Option<EdwardsPoint> = None;
+        for (i, bit) in by.iter().enumerate() {
+            if i > 0 {
+                base = base.double(cs.namespace(|| format!("doubling {}", i)))?;
+            }
+            let p = base.conditionally_select(cs.namespace(|| format!("selection {}", i)), bit)?;
+            result = Some(match result {
+                None => p,
+                Some(result) => result.add(cs.namespace(|| format!("addition {}", i)), &p)?,
+            });
+        }
+
+        Ok(result.unwrap())
+    }
+}
+
+/// A point of the Montgomery form of Jubjub in the circuit, which is neither
+/// the identity nor of order 2.
+#[derive(Clone)]
+pub struct MontgomeryPoint {
+    x: Num<Scalar>,
+    y: Num<Scalar>,
+}
+
+impl MontgomeryPoint {
+    /// Interprets `x` and `y` as a point, without checking that it is on the
+    /// curve.
+    pub fn interpret_unchecked(x: Num<Scalar>, y: Num<Scalar>) -> Self {
+        MontgomeryPoint { x, y }
+    }
+
+    /// Converts the point to twisted Edwards coordinates. This costs 2
+    /// constraints.
+    pub fn into_edwards<CS>(self, mut cs: CS) -> Result<EdwardsPoint, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let scale = montgomery_scale();
+
+        // u = scale * x / y
+        let u = AllocatedNum::alloc(cs.namespace(|| "u"), || {
+            let y_inv = Option::<Scalar>::from(self.y.get_value().get()?.invert())
+                .ok_or(SynthesisError::DivisionByZero)?;
+            Ok(scale * self.x.get_value().get()? * y_inv)
+        })?;
+        cs.enforce(
+            || "u computation",
+            |_| self.y.lc(Scalar::ONE),
+            |lc| lc + u.get_variable(),
+            |_| self.x.lc(scale),
+        );
+
+        // v = (x - 1) / (x + 1)
+        let v = AllocatedNum::alloc(cs.namespace(|| "v"), || {
+            let x = *self.x.get_value().get()?;
+            let t = Option::<Scalar>::from((x + Scalar::ONE).invert())
+                .ok_or(SynthesisError::DivisionByZero)?;
+            Ok((x - Scalar::ONE) * t)
+        })?;
+        cs.enforce(
+            || "v computation",
+            |lc| lc + &self.x.lc(Scalar::ONE) + CS::one(),
+            |lc| lc + v.get_variable(),
+            |lc| lc + &self.x.lc(Scalar::ONE) - CS::one(),
+        );
+
+        Ok(EdwardsPoint { u, v })
+    }
+
+    /// Adds two points with distinct x coordinates. This costs 3 constraints.
+    pub fn add<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // lambda = (y2 - y1) / (x2 - x1)
+        let lambda = AllocatedNum::alloc(cs.namespace(|| "lambda"), || {
+            let dx = *other.x.get_value().get()? - self.x.get_value().get()?;
+            let dx_inv =
+                Option::<Scalar>::from(dx.invert()).ok_or(SynthesisError::DivisionByZero)?;
+            Ok((*other.y.get_value().get()? - self.y.get_value().get()?) * dx_inv)
+        })?;
+        cs.enforce(
+            || "lambda computation",
+            |lc| lc + &other.x.lc(Scalar::ONE) - &self.x.lc(Scalar::ONE),
+            |lc| lc + lambda.get_variable(),
+            |lc| lc + &other.y.lc(Scalar::ONE) - &self.y.lc(Scalar::ONE),
+        );
+
+        // x3 = lambda^2 - A - x1 - x2
+        let x = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            Ok(lambda.get_value().get()?.square()
+                - montgomery_a()
+                - self.x.get_value().get()?
+                - other.x.get_value().get()?)
+        })?;
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + lambda.get_variable(),
+            |lc| lc + lambda.get_variable(),
+            |lc| {
+                lc + (montgomery_a(), CS::one())
+                    + &self.x.lc(Scalar::ONE)
+                    + &other.x.lc(Scalar::ONE)
+                    + x.get_variable()
+            },
+        );
+
+        // y3 = lambda * (x1 - x3) - y1
+        let y = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            Ok(
+                *lambda.get_value().get()? * (*self.x.get_value().get()? - x.get_value().get()?)
+                    - self.y.get_value().get()?,
+            )
+        })?;
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + &self.x.lc(Scalar::ONE) - x.get_variable(),
+            |lc| lc + lambda.get_variable(),
+            |lc| lc + y.get_variable() + &self.y.lc(Scalar::ONE),
+        );
+
+        Ok(MontgomeryPoint {
+            x: x.into(),
+            y: y.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    fn random_point(rng: &mut XorShiftRng) -> Point {
+        let mut tag = [0u8; 16];
+        rng.fill_bytes(&mut tag);
+        Point::find_group_hash(&tag, b"jubjub_t")
+    }
+
+    fn random_bits(rng: &mut XorShiftRng, n: usize) -> Vec<bool> {
+        (0..n).map(|_| rng.next_u32() % 2 == 1).collect()
+    }
+
+    fn alloc_bits(cs: &mut TestConstraintSystem<Scalar>, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(
+            edwards_d().to_bytes_le(),
+            hex!("b13e34d6d65f0601269d57376d7f2d29d47fbde60792fdf5482bfa4be718932a")
+        );
+        assert_eq!(
+            montgomery_scale().square(),
+            -(montgomery_a() + Scalar::from(2))
+        );
+    }
+
+    #[test]
+    fn test_native_arithmetic() {
+        let mut rng = rng();
+        let identity = Point::identity();
+        let order_two = Point::from_coordinates(Scalar::ZERO, -Scalar::ONE).unwrap();
+        assert_eq!(order_two.double(), identity);
+        assert_eq!(Point::from_coordinates(Scalar::ONE, Scalar::ONE), None);
+
+        for _ in 0..10 {
+            let p = random_point(&mut rng);
+            let q = random_point(&mut rng);
+            assert!(Point::from_coordinates(p.get_u(), p.get_v()).is_some());
+            assert_eq!(p + identity, p);
+            assert_eq!(p + -p, identity);
+            assert_eq!(p + q, q + p);
+            assert_eq!((p + q).double(), p.double() + q.double());
+            assert_eq!(Point::from_bytes(&p.to_bytes()), Some(p));
+            assert_eq!(Point::from_bytes(&(-p).to_bytes()), Some(-p));
+            assert_eq!(
+                p.mul_bits(&[true, true, false, true]),
+                p.mul_by_cofactor() + p.double() + p
+            );
+        }
+    }
+
+    #[test]
+    fn test_interpret() {
+        let mut rng = rng();
+        let p = random_point(&mut rng);
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        EdwardsPoint::witness(cs.namespace(|| "p"), Some(p)).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 4);
+
+        cs.set("p/u/num", p.get_u() + Scalar::ONE);
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_edwards_addition() {
+        let mut rng = rng();
+        let identity = Point::identity();
+        for _ in 0..10 {
+            let p = random_point(&mut rng);
+            let q = random_point(&mut rng);
+            for (a, b) in [(p, q), (p, p), (p, -p), (p, identity), (identity, identity)] {
+                let mut cs = TestConstraintSystem::<Scalar>::new();
+                let a_var = EdwardsPoint::witness(cs.namespace(|| "a"), Some(a)).unwrap();
+                let b_var = EdwardsPoint::witness(cs.namespace(|| "b"), Some(b)).unwrap();
+                let sum = a_var.add(cs.namespace(|| "a + b"), &b_var).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints(), 8 + 6);
+                assert_eq!(sum.get_value(), Some(a + b));
+
+                cs.set("a + b/u3/num", sum.get_v().get_value().unwrap());
+                assert_eq!(cs.which_is_unsatisfied(), Some("a + b/u3 computation"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_edwards_doubling() {
+        let mut rng = rng();
+        for p in [random_point(&mut rng), Point::identity()] {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let p_var = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p)).unwrap();
+            let double = p_var.double(cs.namespace(|| "2p")).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 4 + 5);
+            assert_eq!(double.get_value(), Some(p.double()));
+        }
+    }
+
+    #[test]
+    fn test_conditionally_select() {
+        let mut rng = rng();
+        let p = random_point(&mut rng);
+        for condition in [false, true] {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let p_var = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p)).unwrap();
+            let bit = alloc_bits(&mut cs, &[condition]);
+            let selected = p_var
+                .conditionally_select(cs.namespace(|| "select"), &bit[0])
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            let expected = if condition { p } else { Point::identity() };
+            assert_eq!(selected.get_value(), Some(expected));
+
+            cs.set("select/v/num", -Scalar::ONE);
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_edwards_multiplication() {
+        let mut rng = rng();
+        for num_bits in [1, 10, 252] {
+            let p = random_point(&mut rng);
+            let bits = random_bits(&mut rng, num_bits);
+
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let p_var = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p)).unwrap();
+            let bits_var = alloc_bits(&mut cs, &bits);
+            let product = p_var.mul(cs.namespace(|| "mul"), &bits_var).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(product.get_value(), Some(p.mul_bits(&bits)));
+            assert_eq!(
+                cs.num_constraints(),
+                4 + num_bits + (num_bits - 1) * (5 + 2 + 6) + 2
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixed_base_multiplication() {
+        let mut rng = rng();
+        let base = random_point(&mut rng);
+        let table = fixed_base_table(&base, 84);
+        for num_bits in [1, 3, 100, 252] {
+            let bits = random_bits(&mut rng, num_bits);
+
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let bits_var = alloc_bits(&mut cs, &bits);
+            let product =
+                fixed_base_multiplication(cs.namespace(|| "mul"), &table, &bits_var).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(product.get_value(), Some(base.mul_bits(&bits)));
+        }
+    }
+
+    #[test]
+    fn test_montgomery() {
+        let mut rng = rng();
+        for _ in 0..10 {
+            let p = random_point(&mut rng);
+            let q = random_point(&mut rng);
+            let (px, py) = p.to_montgomery().unwrap();
+            let (qx, qy) = q.to_montgomery().unwrap();
+
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let alloc = |cs: &mut TestConstraintSystem<Scalar>, name: &str, value: Scalar| {
+                Num::from(AllocatedNum::alloc(cs.namespace(|| name), || Ok(value)).unwrap())
+            };
+            let p_var = MontgomeryPoint::interpret_unchecked(
+                alloc(&mut cs, "px", px),
+                alloc(&mut cs, "py", py),
+            );
+            let q_var = MontgomeryPoint::interpret_unchecked(
+                alloc(&mut cs, "qx", qx),
+                alloc(&mut cs, "qy", qy),
+            );
+            let sum = p_var.add(cs.namespace(|| "p + q"), &q_var).unwrap();
+            let sum = sum.into_edwards(cs.namespace(|| "edwards")).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 3 + 2);
+            assert_eq!(sum.get_value(), Some(p + q));
+        }
+    }
+
+    #[test]
+    fn test_assert_not_small_order() {
+        let mut rng = rng();
+        let p = random_point(&mut rng);
+        let order_two = Point::from_coordinates(Scalar::ZERO, -Scalar::ONE).unwrap();
+
+        for (point, small) in [(p, false), (p + order_two, false), (order_two, true)] {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let p_var = EdwardsPoint::witness(cs.namespace(|| "p"), Some(point)).unwrap();
+            let result = p_var.assert_not_small_order(cs.namespace(|| "check"));
+            if small {
+                assert!(matches!(result, Err(SynthesisError::DivisionByZero)));
+            } else {
+                result.unwrap();
+                assert!(cs.is_satisfied());
+            }
+        }
+    }
+}
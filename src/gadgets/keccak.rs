@@ -0,0 +1,353 @@
+//! Circuits for the [Keccak-f\[1600\]] permutation and the hash functions of the
+//! [SHA-3] standard built on it: Keccak-256 as used by Ethereum, SHA3-256, and the
+//! SHAKE128 and SHAKE256 extendable-output functions.
+//!
+//! Keccak operates on little-endian lanes, so like for [`blake2s`](super::blake2s)
+//! the input and output bits are in little-endian order within each byte.
+//!
+//! [Keccak-f\[1600\]]: https://keccak.team/keccak_specs_summary.html
+//! [SHA-3]: https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+
+#![allow(clippy::many_single_char_names)]
+
+use ff::PrimeField;
+
+use super::boolean::Boolean;
+use super::multieq::MultiEq;
+use super::uint64::UInt64;
+use bellpepper_core::{ConstraintSystem, SynthesisError};
+
+#[allow(clippy::unreadable_literal)]
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation offsets of the ρ step, for the lane at `x + 5 * y`.
+const ROTATIONS: [usize; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// Domain separation suffixes, including the first bit of the padding.
+const KECCAK_SUFFIX: u8 = 0x01;
+const SHA3_SUFFIX: u8 = 0x06;
+const SHAKE_SUFFIX: u8 = 0x1f;
+
+/// Rates of the sponge, in bits.
+const RATE_256: usize = 1088;
+const RATE_128: usize = 1344;
+
+/// The Keccak-f\[1600\] permutation of the 25 lanes of `state`, the lane
+/// `state[x + 5 * y]` being at position `(x, y)`.
+pub fn keccak_f1600<Scalar, CS>(cs: CS, state: &[UInt64]) -> Result<Vec<UInt64>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert_eq!(state.len(), 25);
+
+    let mut cs = MultiEq::new(cs);
+    let mut a = state.to_vec();
+
+    for (round, rc) in ROUND_CONSTANTS.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("round {}", round));
+
+        // θ: the parity of the five lanes of each column is computed at once
+        let c = (0..5)
+            .map(|x| {
+                let column: Vec<UInt64> = (0..5).map(|y| a[x + 5 * y].clone()).collect();
+                UInt64::xormany(cs.namespace(|| format!("theta column {}", x)), &column)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let d = (0..5)
+            .map(|x| {
+                c[(x + 4) % 5].xor(
+                    cs.namespace(|| format!("theta d {}", x)),
+                    &c[(x + 1) % 5].rotl(1),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, lane) in a.iter_mut().enumerate() {
+            *lane = lane.xor(cs.namespace(|| format!("theta lane {}", i)), &d[i % 5])?;
+        }
+
+        // ρ and π only move bits around
+        let mut b = vec![UInt64::constant(0); 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotl(ROTATIONS[x + 5 * y]);
+            }
+        }
+
+        // χ
+        for x in 0..5 {
+            for y in 0..5 {
+                let cs = &mut cs.namespace(|| format!("chi lane {}", x + 5 * y));
+                let t = b[(x + 1) % 5 + 5 * y]
+                    .not()
+                    .and(cs.namespace(|| "and"), &b[(x + 2) % 5 + 5 * y])?;
+                a[x + 5 * y] = b[x + 5 * y].xor(cs.namespace(|| "xor"), &t)?;
+            }
+        }
+
+        // ι is free, as the round constant is known
+        a[0] = a[0].xor(cs.namespace(|| "iota"), &UInt64::constant(*rc))?;
+    }
+
+    Ok(a)
+}
+
+/// The Keccak sponge with a capacity of `1600 - rate` bits, absorbing `input`
+/// followed by the domain separation `suffix` and the padding, and squeezing
+/// `output_len` bits.
+fn keccak_sponge<Scalar, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    rate: usize,
+    suffix: u8,
+    output_len: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(input.len() % 8 == 0);
+
+    // Append the suffix, which starts the pad10*1 padding, then zeros, and set
+    // the last bit of the block, which is never part of the suffix.
+    let mut padded = input.to_vec();
+    padded.extend((0..8).map(|i| Boolean::constant((suffix >> i) & 1 == 1)));
+    while padded.len() % rate != 0 {
+        padded.push(Boolean::constant(false));
+    }
+    let last = padded.len() - 1;
+    padded[last] = Boolean::constant(true);
+
+    let mut state = vec![UInt64::constant(0); 25];
+    for (i, block) in padded.chunks(rate).enumerate() {
+        let cs = &mut cs.namespace(|| format!("absorb block {}", i));
+        for (j, lane) in block.chunks(64).enumerate() {
+            state[j] = state[j].xor(
+                cs.namespace(|| format!("lane {}", j)),
+                &UInt64::from_bits(lane),
+            )?;
+        }
+        state = keccak_f1600(cs.namespace(|| "permutation"), &state)?;
+    }
+
+    let mut output = Vec::with_capacity(output_len);
+    let mut i = 0;
+    loop {
+        for lane in &state[..rate / 64] {
+            output.extend(lane.clone().into_bits());
+        }
+        if output.len() >= output_len {
+            break;
+        }
+        state = keccak_f1600(cs.namespace(|| format!("squeeze block {}", i)), &state)?;
+        i += 1;
+    }
+    output.truncate(output_len);
+
+    Ok(output)
+}
+
+/// The Keccak-256 hash function, as used by Ethereum, which differs from
+/// SHA3-256 by its padding.
+pub fn keccak256<Scalar, CS>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    keccak_sponge(cs, input, RATE_256, KECCAK_SUFFIX, 256)
+}
+
+/// The SHA3-256 hash function.
+pub fn sha3_256<Scalar, CS>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    keccak_sponge(cs, input, RATE_256, SHA3_SUFFIX, 256)
+}
+
+/// The SHAKE128 extendable-output function, returning `output_len` bits.
+pub fn shake128<Scalar, CS>(
+    cs: CS,
+    input: &[Boolean],
+    output_len: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    keccak_sponge(cs, input, RATE_128, SHAKE_SUFFIX, output_len)
+}
+
+/// The SHAKE256 extendable-output function, returning `output_len` bits.
+pub fn shake256<Scalar, CS>(
+    cs: CS,
+    input: &[Boolean],
+    output_len: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    keccak_sponge(cs, input, RATE_256, SHAKE_SUFFIX, output_len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use sha3::digest::{ExtendableOutput, Update, XofReader};
+    use sha3::{Digest, Keccak256, Sha3_256, Shake128, Shake256};
+
+    fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+        bytes
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+            .collect()
+    }
+
+    /// Checks `hash` against the reference implementation `expected` on random
+    /// inputs of each of the `input_lens`, in bytes.
+    fn check_against_sha3<F, H>(hash: F, expected: H, input_lens: impl Iterator<Item = usize>)
+    where
+        F: Fn(&mut TestConstraintSystem<Fr>, &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>,
+        H: Fn(&[u8]) -> Vec<u8>,
+    {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for input_len in input_lens {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.next_u32() as u8).collect();
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let input_bits: Vec<Boolean> = bytes_to_bits(&data)
+                .into_iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b))
+                        .unwrap()
+                        .into()
+                })
+                .collect();
+
+            let r = hash(&mut cs, &input_bits).unwrap();
+            assert!(cs.is_satisfied());
+
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+            assert_eq!(
+                r,
+                bytes_to_bits(&expected(&data)),
+                "input length {}",
+                input_len
+            );
+        }
+    }
+
+    #[test]
+    fn test_blank_hash() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let out = keccak256(&mut cs, &[]).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+        let out: Vec<bool> = out.iter().map(|b| b.get_value().unwrap()).collect();
+        let expected = hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+        assert_eq!(out, bytes_to_bits(&expected));
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let out = sha3_256(&mut cs, &[]).unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+        let out: Vec<bool> = out.iter().map(|b| b.get_value().unwrap()).collect();
+        let expected = hex!("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a");
+        assert_eq!(out, bytes_to_bits(&expected));
+    }
+
+    #[test]
+    fn test_keccak_f1600_constraints() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let state: Vec<UInt64> = (0..25)
+            .map(|i| UInt64::alloc(cs.namespace(|| format!("lane {}", i)), Some(i)).unwrap())
+            .collect();
+        keccak_f1600(cs.namespace(|| "permutation"), &state).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - 25 * 64, 146012);
+    }
+
+    #[test]
+    fn test_keccak256() {
+        check_against_sha3(
+            |cs, input| keccak256(cs, input),
+            |data| Keccak256::digest(data).to_vec(),
+            (0..=2 * 136 + 1).step_by(17).chain([135, 136, 137]),
+        );
+    }
+
+    #[test]
+    fn test_sha3_256() {
+        check_against_sha3(
+            |cs, input| sha3_256(cs, input),
+            |data| Sha3_256::digest(data).to_vec(),
+            [0, 1, 64, 135, 136, 137, 300].into_iter(),
+        );
+    }
+
+    #[test]
+    fn test_shake() {
+        for output_len in [8, 256, 1344, 3000] {
+            check_against_sha3(
+                |cs, input| shake128(cs, input, output_len),
+                |data| {
+                    let mut out = vec![0u8; output_len / 8];
+                    let mut hasher = Shake128::default();
+                    hasher.update(data);
+                    hasher.finalize_xof().read(&mut out);
+                    out
+                },
+                [0, 167, 168, 169].into_iter(),
+            );
+            check_against_sha3(
+                |cs, input| shake256(cs, input, output_len),
+                |data| {
+                    let mut out = vec![0u8; output_len / 8];
+                    let mut hasher = Shake256::default();
+                    hasher.update(data);
+                    hasher.finalize_xof().read(&mut out);
+                    out
+                },
+                [0, 135, 136, 137].into_iter(),
+            );
+        }
+    }
+}
@@ -0,0 +1,737 @@
+//! Merkle trees of any arity, natively and as a circuit.
+//!
+//! A tree is defined by a `MerkleHasher`, which hashes the digests of the
+//! children of a node into the digest of the node. It is implemented for the
+//! SHA-256 and BLAKE2s gadgets, whose digests are bit strings, and for the
+//! Poseidon permutation, whose digests are field elements. Any other algebraic
+//! hash can implement it in the same way as Poseidon.
+//!
+//! The leaves are digests given by the caller, and are not hashed again: leaves
+//! and inner nodes are only domain separated if the leaves are computed with a
+//! different hash. The tree is complete, so that it has `arity^height` leaves.
+//!
+//! The circuit proves that a private leaf is in the tree, at a private position,
+//! and exposes the root as public inputs with `multipack`.
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use ff::{PrimeField, PrimeFieldBits};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::multipack;
+use super::num::AllocatedNum;
+use super::poseidon::{self, PoseidonParams};
+use super::{blake2s, range, sha256};
+use bellpepper_core::{Circuit, ConstraintSystem, SynthesisError};
+
+/// A hash function building the nodes of a Merkle tree from their children.
+pub trait MerkleHasher<Scalar: PrimeField> {
+    /// A digest outside of the circuit.
+    type Digest: Clone + PartialEq + Debug;
+
+    /// A digest in the circuit.
+    type Var: Clone;
+
+    /// Hashes the digests of the children of a node.
+    fn hash(&self, children: &[Self::Digest]) -> Self::Digest;
+
+    /// Hashes the digests of the children of a node in the circuit.
+    fn hash_circuit<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        children: &[Self::Var],
+    ) -> Result<Self::Var, SynthesisError>;
+
+    /// Allocates a digest as a private witness.
+    fn alloc<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        digest: Option<&Self::Digest>,
+    ) -> Result<Self::Var, SynthesisError>;
+
+    /// Returns `a` if `condition` is true, and `b` otherwise.
+    fn select<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        condition: &Boolean,
+        a: &Self::Var,
+        b: &Self::Var,
+    ) -> Result<Self::Var, SynthesisError>;
+
+    /// The bits of a digest, as they are packed into public inputs.
+    fn to_bits(&self, digest: &Self::Digest) -> Vec<bool>;
+
+    /// The bits of a digest in the circuit, in the same order as `to_bits`.
+    fn to_bits_circuit<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        var: &Self::Var,
+    ) -> Result<Vec<Boolean>, SynthesisError>;
+}
+
+fn alloc_bits<Scalar, CS>(
+    mut cs: CS,
+    bits: Option<Vec<bool>>,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    (0..256)
+        .map(|i| {
+            AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                bits.as_ref().map(|bits| bits[i]),
+            )
+            .map(Boolean::from)
+        })
+        .collect()
+}
+
+fn select_bits<Scalar, CS>(
+    mut cs: CS,
+    condition: &Boolean,
+    a: &[Boolean],
+    b: &[Boolean],
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    a.iter()
+        .zip(b)
+        .enumerate()
+        .map(|(i, (a, b))| {
+            Boolean::sha256_ch(cs.namespace(|| format!("bit {}", i)), condition, a, b)
+        })
+        .collect()
+}
+
+/// SHA-256 of the concatenated digests of the children. The bits of the digests
+/// are big-endian, like in the `sha256` gadget.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl<Scalar: PrimeField> MerkleHasher<Scalar> for Sha256Hasher {
+    type Digest = [u8; 32];
+    type Var = Vec<Boolean>;
+
+    fn hash(&self, children: &[Self::Digest]) -> Self::Digest {
+        use sha2::{Digest, Sha256};
+
+        let mut h = Sha256::new();
+        for child in children {
+            h.update(child);
+        }
+        h.finalize().into()
+    }
+
+    fn hash_circuit<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        children: &[Self::Var],
+    ) -> Result<Self::Var, SynthesisError> {
+        sha256::sha256(cs, &children.concat())
+    }
+
+    fn alloc<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        digest: Option<&Self::Digest>,
+    ) -> Result<Self::Var, SynthesisError> {
+        alloc_bits(cs, digest.map(|d| multipack::bytes_to_bits(d)))
+    }
+
+    fn select<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        condition: &Boolean,
+        a: &Self::Var,
+        b: &Self::Var,
+    ) -> Result<Self::Var, SynthesisError> {
+        select_bits(cs, condition, a, b)
+    }
+
+    fn to_bits(&self, digest: &Self::Digest) -> Vec<bool> {
+        multipack::bytes_to_bits(digest)
+    }
+
+    fn to_bits_circuit<CS: ConstraintSystem<Scalar>>(
+        &self,
+        _cs: CS,
+        var: &Self::Var,
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        Ok(var.clone())
+    }
+}
+
+/// BLAKE2s with a 32-byte output of the concatenated digests of the children.
+/// The bits of the digests are little-endian in each byte, like in the `blake2s`
+/// gadget.
+#[derive(Clone, Copy, Debug)]
+pub struct Blake2sHasher {
+    personalization: [u8; 8],
+}
+
+impl Blake2sHasher {
+    /// A hasher with an 8-byte `personalization`.
+    pub fn new(personalization: &[u8]) -> Self {
+        let mut p = [0u8; 8];
+        p.copy_from_slice(personalization);
+        Blake2sHasher { personalization: p }
+    }
+}
+
+impl<Scalar: PrimeField> MerkleHasher<Scalar> for Blake2sHasher {
+    type Digest = [u8; 32];
+    type Var = Vec<Boolean>;
+
+    fn hash(&self, children: &[Self::Digest]) -> Self::Digest {
+        let mut h = blake2s_simd::Params::new()
+            .hash_length(32)
+            .personal(&self.personalization)
+            .to_state();
+        for child in children {
+            h.update(child);
+        }
+        *h.finalize().as_array()
+    }
+
+    fn hash_circuit<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        children: &[Self::Var],
+    ) -> Result<Self::Var, SynthesisError> {
+        blake2s::blake2s(cs, &children.concat(), &self.personalization)
+    }
+
+    fn alloc<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        digest: Option<&Self::Digest>,
+    ) -> Result<Self::Var, SynthesisError> {
+        alloc_bits(cs, digest.map(|d| multipack::bytes_to_bits_le(d)))
+    }
+
+    fn select<CS: ConstraintSystem<Scalar>>(
+        &self,
+        cs: CS,
+        condition: &Boolean,
+        a: &Self::Var,
+        b: &Self::Var,
+    ) -> Result<Self::Var, SynthesisError> {
+        select_bits(cs, condition, a, b)
+    }
+
+    fn to_bits(&self, digest: &Self::Digest) -> Vec<bool> {
+        multipack::bytes_to_bits_le(digest)
+    }
+
+    fn to_bits_circuit<CS: ConstraintSystem<Scalar>>(
+        &self,
+        _cs: CS,
+        var: &Self::Var,
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        Ok(var.clone())
+    }
+}
+
+/// Poseidon with the children as inputs, using `poseidon::hash`. The arity of
+/// the parameters should be the arity of the tree, so that each node takes a
+/// single permutation. Digests are exposed with their little-endian bits.
+impl<F: PrimeFieldBits> MerkleHasher<F> for PoseidonParams<F> {
+    type Digest = F;
+    type Var = AllocatedNum<F>;
+
+    fn hash(&self, children: &[Self::Digest]) -> Self::Digest {
+        poseidon::hash(self, children)
+    }
+
+    fn hash_circuit<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        children: &[Self::Var],
+    ) -> Result<Self::Var, SynthesisError> {
+        poseidon::hash_circuit(cs, self, children)
+    }
+
+    fn alloc<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        digest: Option<&Self::Digest>,
+    ) -> Result<Self::Var, SynthesisError> {
+        AllocatedNum::alloc(cs, || {
+            digest.copied().ok_or(SynthesisError::AssignmentMissing)
+        })
+    }
+
+    fn select<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        condition: &Boolean,
+        a: &Self::Var,
+        b: &Self::Var,
+    ) -> Result<Self::Var, SynthesisError> {
+        range::select(cs, condition, a, b)
+    }
+
+    fn to_bits(&self, digest: &Self::Digest) -> Vec<bool> {
+        digest
+            .to_le_bits()
+            .iter()
+            .by_vals()
+            .take(F::NUM_BITS as usize)
+            .collect()
+    }
+
+    fn to_bits_circuit<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        var: &Self::Var,
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        var.to_bits_le_strict(cs)
+    }
+}
+
+/// A complete Merkle tree, with all its nodes.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<Scalar: PrimeField, H: MerkleHasher<Scalar>> {
+    hasher: H,
+    arity: usize,
+    /// The layers of the tree, from the leaves to the root.
+    layers: Vec<Vec<H::Digest>>,
+    _scalar: PhantomData<Scalar>,
+}
+
+impl<Scalar: PrimeField, H: MerkleHasher<Scalar>> MerkleTree<Scalar, H> {
+    /// Builds the tree of the given arity over `leaves`, whose number must be a
+    /// power of the arity.
+    pub fn new(hasher: H, arity: usize, leaves: Vec<H::Digest>) -> Self {
+        assert!(arity >= 2, "the arity of a Merkle tree is at least 2");
+
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers.last().unwrap();
+            assert!(
+                layer.len() % arity == 0,
+                "the number of leaves is not a power of the arity"
+            );
+            let next = layer.chunks(arity).map(|c| hasher.hash(c)).collect();
+            layers.push(next);
+        }
+        assert_eq!(layers[0].len(), arity.pow(layers.len() as u32 - 1));
+
+        MerkleTree {
+            hasher,
+            arity,
+            layers,
+            _scalar: PhantomData,
+        }
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// The number of layers above the leaves.
+    pub fn height(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn leaves(&self) -> &[H::Digest] {
+        &self.layers[0]
+    }
+
+    pub fn root(&self) -> &H::Digest {
+        &self.layers[self.height()][0]
+    }
+
+    /// The authentication path of the leaf at `index`.
+    pub fn prove(&self, index: usize) -> MerkleProof<H::Digest> {
+        assert!(index < self.leaves().len());
+
+        let siblings = self.layers[..self.height()]
+            .iter()
+            .enumerate()
+            .map(|(level, layer)| {
+                let position = index / self.arity.pow(level as u32);
+                let start = position - position % self.arity;
+                (start..start + self.arity)
+                    .filter(|&i| i != position)
+                    .map(|i| layer[i].clone())
+                    .collect()
+            })
+            .collect();
+
+        MerkleProof { index, siblings }
+    }
+}
+
+/// The authentication path of a leaf: the siblings of the leaf and of each of
+/// its ancestors below the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof<D> {
+    index: usize,
+    /// The `arity - 1` siblings at each level, from the leaves up, in order.
+    siblings: Vec<Vec<D>>,
+}
+
+impl<D: Clone + PartialEq> MerkleProof<D> {
+    pub fn new(index: usize, siblings: Vec<Vec<D>>) -> Self {
+        MerkleProof { index, siblings }
+    }
+
+    /// The position of the leaf.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn siblings(&self) -> &[Vec<D>] {
+        &self.siblings
+    }
+
+    /// The root of the tree containing `leaf` at this path.
+    pub fn root<Scalar, H>(&self, hasher: &H, leaf: &D) -> D
+    where
+        Scalar: PrimeField,
+        H: MerkleHasher<Scalar, Digest = D>,
+    {
+        let mut node = leaf.clone();
+        let mut index = self.index;
+        for siblings in &self.siblings {
+            let arity = siblings.len() + 1;
+            let mut children = siblings.clone();
+            children.insert(index % arity, node);
+            node = hasher.hash(&children);
+            index /= arity;
+        }
+        node
+    }
+
+    /// Whether `leaf` is in the tree of the given `root` at this path.
+    pub fn verify<Scalar, H>(&self, hasher: &H, leaf: &D, root: &D) -> bool
+    where
+        Scalar: PrimeField,
+        H: MerkleHasher<Scalar, Digest = D>,
+    {
+        self.root(hasher, leaf) == *root
+    }
+}
+
+/// Computes in the circuit the root of the tree of the given arity and height
+/// which contains `leaf` at the path given by `proof`. The position of the leaf
+/// is a private witness.
+pub fn compute_root<Scalar, H, CS>(
+    mut cs: CS,
+    hasher: &H,
+    arity: usize,
+    height: usize,
+    leaf: &H::Var,
+    proof: Option<&MerkleProof<H::Digest>>,
+) -> Result<H::Var, SynthesisError>
+where
+    Scalar: PrimeField,
+    H: MerkleHasher<Scalar>,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(arity >= 2);
+    if let Some(proof) = proof {
+        assert_eq!(proof.siblings.len(), height);
+        assert!(proof.siblings.iter().all(|s| s.len() == arity - 1));
+    }
+
+    let mut node = leaf.clone();
+    for level in 0..height {
+        let mut cs = cs.namespace(|| format!("level {}", level));
+        let position = proof.map(|p| (p.index / arity.pow(level as u32)) % arity);
+
+        // at_least[j] is whether the position is at least j + 1, so that the
+        // bits are non-increasing.
+        let mut at_least: Vec<Boolean> = Vec::with_capacity(arity - 1);
+        for j in 1..arity {
+            let bit = AllocatedBit::alloc(
+                cs.namespace(|| format!("position bit {}", j)),
+                position.map(|p| p >= j),
+            )?;
+            if let Some(Boolean::Is(prev)) = at_least.last() {
+                // bit * (1 - prev) = 0
+                cs.enforce(
+                    || format!("position bit {} ordering", j),
+                    |lc| lc + bit.get_variable(),
+                    |lc| lc + CS::one() - prev.get_variable(),
+                    |lc| lc,
+                );
+            }
+            at_least.push(Boolean::from(bit));
+        }
+
+        let siblings = (0..arity - 1)
+            .map(|j| {
+                hasher.alloc(
+                    cs.namespace(|| format!("sibling {}", j)),
+                    proof.map(|p| &p.siblings[level][j]),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The child j is the node if the position is j, the sibling j if the
+        // position is larger, and the sibling j - 1 if it is smaller.
+        let children = (0..arity)
+            .map(|j| {
+                let mut cs = cs.namespace(|| format!("child {}", j));
+                let child = if j == 0 {
+                    node.clone()
+                } else {
+                    hasher.select(
+                        cs.namespace(|| "below"),
+                        &at_least[j - 1],
+                        &node,
+                        &siblings[j - 1],
+                    )?
+                };
+                if j == arity - 1 {
+                    Ok(child)
+                } else {
+                    hasher.select(cs.namespace(|| "above"), &at_least[j], &siblings[j], &child)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        node = hasher.hash_circuit(cs.namespace(|| "hash"), &children)?;
+    }
+
+    Ok(node)
+}
+
+/// The public inputs of a `MerklePathCircuit` for the given root.
+pub fn public_inputs<Scalar, H>(hasher: &H, root: &H::Digest) -> Vec<Scalar>
+where
+    Scalar: PrimeField,
+    H: MerkleHasher<Scalar>,
+{
+    multipack::compute_multipacking(&hasher.to_bits(root))
+}
+
+/// A circuit proving the knowledge of a leaf and of its authentication path in
+/// the tree whose root is exposed as public inputs.
+#[derive(Clone, Debug)]
+pub struct MerklePathCircuit<Scalar: PrimeField, H: MerkleHasher<Scalar>> {
+    hasher: H,
+    arity: usize,
+    height: usize,
+    leaf: Option<H::Digest>,
+    proof: Option<MerkleProof<H::Digest>>,
+    _scalar: PhantomData<Scalar>,
+}
+
+impl<Scalar: PrimeField, H: MerkleHasher<Scalar>> MerklePathCircuit<Scalar, H> {
+    /// The circuit for trees of the given arity and height, with the leaf and its
+    /// path as witness. Both are `None` when generating parameters.
+    pub fn new(
+        hasher: H,
+        arity: usize,
+        height: usize,
+        leaf: Option<H::Digest>,
+        proof: Option<MerkleProof<H::Digest>>,
+    ) -> Self {
+        MerklePathCircuit {
+            hasher,
+            arity,
+            height,
+            leaf,
+            proof,
+            _scalar: PhantomData,
+        }
+    }
+}
+
+impl<Scalar: PrimeField, H: MerkleHasher<Scalar>> Circuit<Scalar> for MerklePathCircuit<Scalar, H> {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let leaf = self
+            .hasher
+            .alloc(cs.namespace(|| "leaf"), self.leaf.as_ref())?;
+        let root = compute_root(
+            cs.namespace(|| "path"),
+            &self.hasher,
+            self.arity,
+            self.height,
+            &leaf,
+            self.proof.as_ref(),
+        )?;
+        let bits = self
+            .hasher
+            .to_bits_circuit(cs.namespace(|| "root bits"), &root)?;
+        multipack::pack_into_inputs(cs.namespace(|| "root"), &bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::util_cs::metric_cs::MetricCS;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    fn random_digests(rng: &mut XorShiftRng, n: usize) -> Vec<[u8; 32]> {
+        (0..n)
+            .map(|_| {
+                let mut d = [0u8; 32];
+                rng.fill_bytes(&mut d);
+                d
+            })
+            .collect()
+    }
+
+    /// Synthesizes the circuit for each leaf of `tree` and checks it against the
+    /// root, and against a wrong root.
+    fn check_circuit<H: MerkleHasher<Fr> + Clone>(
+        tree: &MerkleTree<Fr, H>,
+        wrong_root: &H::Digest,
+    ) {
+        let hasher = tree.hasher();
+        let inputs = public_inputs(hasher, tree.root());
+        let wrong_inputs = public_inputs(hasher, wrong_root);
+        assert_ne!(inputs, wrong_inputs);
+
+        for (index, leaf) in tree.leaves().iter().enumerate() {
+            let proof = tree.prove(index);
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            MerklePathCircuit::new(
+                hasher.clone(),
+                tree.arity(),
+                tree.height(),
+                Some(leaf.clone()),
+                Some(proof),
+            )
+            .synthesize(&mut cs)
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(cs.verify(&inputs));
+            assert!(!cs.verify(&wrong_inputs));
+        }
+    }
+
+    #[test]
+    fn test_native_tree() {
+        let mut rng = rng();
+        let params = PoseidonParams::<Fr>::new(3);
+
+        let leaves: Vec<Fr> = (0..27).map(|_| Fr::random(&mut rng)).collect();
+        let tree = MerkleTree::new(params.clone(), 3, leaves.clone());
+        assert_eq!(tree.height(), 3);
+
+        let left = poseidon::hash(&params, &leaves[..3]);
+        let middle = poseidon::hash(&params, &leaves[3..6]);
+        let right = poseidon::hash(&params, &leaves[6..9]);
+        assert_eq!(
+            tree.layers[2][0],
+            poseidon::hash(&params, &[left, middle, right])
+        );
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = tree.prove(index);
+            assert_eq!(proof.index(), index);
+            assert!(proof.verify(&params, leaf, tree.root()));
+            assert!(!proof.verify(&params, &(*leaf + Fr::ONE), tree.root()));
+
+            let moved = MerkleProof::new((index + 1) % 27, proof.siblings().to_vec());
+            assert!(!moved.verify(&params, leaf, tree.root()));
+        }
+
+        let single = MerkleTree::new(params.clone(), 3, vec![leaves[0]]);
+        assert_eq!(single.height(), 0);
+        assert!(single.prove(0).verify(&params, &leaves[0], &leaves[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "not a power of the arity")]
+    fn test_native_tree_incomplete() {
+        let mut rng = rng();
+        let leaves = random_digests(&mut rng, 6);
+        MerkleTree::<Fr, _>::new(Sha256Hasher, 2, leaves);
+    }
+
+    #[test]
+    fn test_sha256_path() {
+        let mut rng = rng();
+        let tree = MerkleTree::<Fr, _>::new(Sha256Hasher, 2, random_digests(&mut rng, 8));
+        check_circuit(&tree, &random_digests(&mut rng, 1)[0]);
+    }
+
+    #[test]
+    fn test_blake2s_path() {
+        let mut rng = rng();
+        let hasher = Blake2sHasher::new(b"12345678");
+        let tree = MerkleTree::<Fr, _>::new(hasher, 4, random_digests(&mut rng, 16));
+        check_circuit(&tree, &random_digests(&mut rng, 1)[0]);
+
+        let expected = blake2s_simd::Params::new()
+            .personal(b"12345678")
+            .hash(&tree.leaves()[..4].concat());
+        assert_eq!(tree.layers[1][0], *expected.as_array());
+    }
+
+    #[test]
+    fn test_poseidon_path() {
+        let mut rng = rng();
+        for arity in [2, 3, 5] {
+            let params = PoseidonParams::<Fr>::new(arity);
+            let leaves = (0..arity * arity).map(|_| Fr::random(&mut rng)).collect();
+            let tree = MerkleTree::new(params, arity, leaves);
+            check_circuit(&tree, &Fr::random(&mut rng));
+        }
+    }
+
+    #[test]
+    fn test_poseidon_path_constraints() {
+        let params = PoseidonParams::<Fr>::new(4);
+        let mut cs = MetricCS::<Fr>::new();
+        MerklePathCircuit::<Fr, _>::new(params, 4, 5, None, None)
+            .synthesize(&mut cs)
+            .unwrap();
+
+        // Each level has 3 position bits with 2 ordering constraints, 6 selections
+        // and a Poseidon hash, and the root is decomposed into bits and packed.
+        assert_eq!(cs.num_inputs(), 3);
+        assert_eq!(cs.num_constraints(), 5 * (5 + 6 + 301) + 390);
+    }
+
+    #[test]
+    fn test_position_bits() {
+        let mut rng = rng();
+        let params = PoseidonParams::<Fr>::new(4);
+        let leaves: Vec<Fr> = (0..16).map(|_| Fr::random(&mut rng)).collect();
+        let tree = MerkleTree::new(params.clone(), 4, leaves.clone());
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        MerklePathCircuit::new(params, 4, 2, Some(leaves[5]), Some(tree.prove(5)))
+            .synthesize(&mut cs)
+            .unwrap();
+        assert!(cs.is_satisfied());
+
+        // The position 1 at the leaves has the bits 1, 0, 0, which can't be
+        // reordered.
+        cs.set("path/level 0/position bit 1/boolean", Fr::ZERO);
+        cs.set("path/level 0/position bit 2/boolean", Fr::ONE);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("path/level 0/position bit 2 ordering")
+        );
+    }
+}
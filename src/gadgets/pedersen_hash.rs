@@ -0,0 +1,288 @@
+//! The [Pedersen hash] of Zcash Sapling on Jubjub, natively and as a circuit.
+//!
+//! The input is split into segments of up to 189 bits, each hashed with its
+//! own generator $G$. A segment is split into chunks of 3 bits $(a, b, c)$,
+//! each encoding $(1 + a + 2b) \cdot (-1)^c$, and the chunk $i$ contributes
+//! its encoding times $16^i G$. Sapling prefixes the input with a 6-bit
+//! personalization, which is left to the caller here.
+//!
+//! In the circuit, each chunk costs a lookup in a table of Montgomery points.
+//! The chunks of a segment are multiples of $G$ by distinct nonzero scalars
+//! smaller than $r_J / 2$, so that they are summed with incomplete Montgomery
+//! additions. Each segment is then converted to twisted Edwards coordinates, and
+//! the segments are summed with complete additions.
+//!
+//! [Pedersen hash]: https://zips.z.cash/protocol/protocol.pdf#concretepedersenhash
+
+use blstrs::Scalar;
+
+use super::boolean::Boolean;
+use super::jubjub::{EdwardsPoint, MontgomeryPoint, Point};
+use super::lookup::lookup3_xy_with_conditional_negation;
+use bellpepper_core::{ConstraintSystem, SynthesisError};
+
+/// The number of chunks of 3 bits hashed with each generator.
+pub const CHUNKS_PER_GENERATOR: usize = 63;
+
+/// The personalization of the group hash giving the generators.
+const GENERATORS_PERSONALIZATION: &[u8; 8] = b"Zcash_PH";
+
+/// The generators of a Pedersen hash, with the tables used in the circuit.
+#[derive(Clone, Debug)]
+pub struct PedersenParams {
+    generators: Vec<Point>,
+    /// The Montgomery coordinates of the multiples of $16^i G$ by 1 to 4, for
+    /// each chunk $i$ of each generator $G$.
+    tables: Vec<Vec<Vec<(Scalar, Scalar)>>>,
+}
+
+impl PedersenParams {
+    /// The first `num_generators` generators of Zcash Sapling, which uses 6 of
+    /// them.
+    pub fn new(num_generators: usize) -> Self {
+        let generators = (0..num_generators as u32)
+            .map(|i| Point::find_group_hash(&i.to_le_bytes(), GENERATORS_PERSONALIZATION))
+            .collect();
+        Self::with_generators(generators)
+    }
+
+    /// Parameters with the given generators, which must be of prime order and
+    /// have no known discrete logarithm relation.
+    pub fn with_generators(generators: Vec<Point>) -> Self {
+        let tables = generators
+            .iter()
+            .map(|g| {
+                let mut g = *g;
+                (0..CHUNKS_PER_GENERATOR)
+                    .map(|_| {
+                        let mut p = g;
+                        let window = (0..4)
+                            .map(|_| {
+                                let coords = p.to_montgomery().expect("generator of small order");
+                                p = p + g;
+                                coords
+                            })
+                            .collect();
+                        g = g.double().double().double().double();
+                        window
+                    })
+                    .collect()
+            })
+            .collect();
+
+        PedersenParams { generators, tables }
+    }
+
+    pub fn generators(&self) -> &[Point] {
+        &self.generators
+    }
+
+    /// The maximum number of bits of the input.
+    pub fn max_bits(&self) -> usize {
+        self.generators.len() * 3 * CHUNKS_PER_GENERATOR
+    }
+}
+
+/// Hashes `bits`, which are not empty, to a point.
+pub fn hash(params: &PedersenParams, bits: &[bool]) -> Point {
+    assert!(!bits.is_empty() && bits.len() <= params.max_bits());
+
+    let mut result = Point::identity();
+    for (segment, g) in bits
+        .chunks(3 * CHUNKS_PER_GENERATOR)
+        .zip(&params.generators)
+    {
+        let mut g = *g;
+        for chunk in segment.chunks(3) {
+            let bit = |i: usize| chunk.get(i).copied().unwrap_or(false);
+            let mut p = g;
+            if bit(0) {
+                p = p + g;
+            }
+            if bit(1) {
+                p = p + g.double();
+            }
+            if bit(2) {
+                p = -p;
+            }
+            result = result + p;
+            g = g.double().double().double().double();
+        }
+    }
+    result
+}
+
+/// Hashes `bits` in the circuit, like `hash`.
+pub fn hash_circuit<CS>(
+    mut cs: CS,
+    params: &PedersenParams,
+    bits: &[Boolean],
+) -> Result<EdwardsPoint, SynthesisError>
+where
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(!bits.is_empty() && bits.len() <= params.max_bits());
+
+    let mut result: Option<EdwardsPoint> = None;
+    let segments = bits.chunks(3 * CHUNKS_PER_GENERATOR).zip(&params.tables);
+    for (i, (segment, table)) in segments.enumerate() {
+        let mut segment_result: Option<MontgomeryPoint> = None;
+        for (j, (chunk, window)) in segment.chunks(3).zip(table).enumerate() {
+            let chunk: Vec<Boolean> = (0..3)
+                .map(|k| chunk.get(k).cloned().unwrap_or(Boolean::Constant(false)))
+                .collect();
+            let (x, y) = lookup3_xy_with_conditional_negation(
+                cs.namespace(|| format!("segment {} lookup {}", i, j)),
+                &chunk,
+                window,
+            )?;
+            let p = MontgomeryPoint::interpret_unchecked(x, y);
+
+            segment_result = Some(match segment_result {
+                None => p,
+                Some(acc) => p.add(
+                    cs.namespace(|| format!("segment {} addition {}", i, j)),
+                    &acc,
+                )?,
+            });
+        }
+
+        let p = segment_result
+            .unwrap()
+            .into_edwards(cs.namespace(|| format!("segment {} conversion", i)))?;
+        result = Some(match result {
+            None => p,
+            Some(acc) => p.add(cs.namespace(|| format!("segment {} addition", i)), &acc)?,
+        });
+    }
+
+    Ok(result.unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    /// Test vectors of Zcash, with the personalization in the input.
+    const TEST_VECTORS: &[(&str, [u8; 32], [u8; 32])] = &[
+        (
+            "111111",
+            hex!("06b1187c11ca4fb4383b2e0d0dbbde3ad3617338b5029187ec65a5eaed5e4d0b"),
+            hex!("3ce70f536652f0dea496393a1e55c4e08b9d55508e16d11e5db40d4810cbc982"),
+        ),
+        (
+            "1111110",
+            hex!("2fc3bc454c337f71d4f04f86304262fcbfc9ecd808716b92fc42cbe6827f7f1a"),
+            hex!("46d0d25bf1a654eedc6a9b1e5af398925113959feac31b7a2c036ff9b9ec0638"),
+        ),
+        (
+            "1111111",
+            hex!("4f8ce0e0a9e674b3ab9606a7d7aefba386e81583d81918127814cde41d209d97"),
+            hex!("312b5ab93b14c9b9af334fe1fe3c50fffb53fbd074fa40ca600febde7c97e346"),
+        ),
+        (
+            "111111100",
+            hex!("4f8ce0e0a9e674b3ab9606a7d7aefba386e81583d81918127814cde41d209d97"),
+            hex!("312b5ab93b14c9b9af334fe1fe3c50fffb53fbd074fa40ca600febde7c97e346"),
+        ),
+        (
+            concat!(
+                "111111101000110100010001000000110000011111000011100011101100111110010101",
+                "000010110110011111010001010100001100000000111001011000011001001001000000",
+                "1011011111011110110001100111100001011111000011",
+            ),
+            hex!("601247c7e640992d193dfb51df6ed93446687a7f2bcd0e4a598e6feb1ef20c40"),
+            hex!("371931733b73e7b95c2cad55a6cebd15c83619f697c64283e54e5ef61442a743"),
+        ),
+        (
+            concat!(
+                "111111110010010010100000011001011110101100111000011001001110010011101110",
+                "110100000111011111001111110001101100101011101000001001110001011100011011",
+                "100000110100000111101011000100001101010111110001010111001001100011001101",
+                "011001001111011110011100010100010010001000011000000001011101100010001101",
+                "100100001010010100101011011001011111000010001000000001010111100101111010",
+                "111011010111101001100010011011100000111001010100010011101111100100000100",
+                "001011010101011000110010111001000100011111010000111011111110111101000010",
+                "100100011001000110010011010100010110100011101001100100010000001011011010",
+                "100010101011100010001000010110011010110111101010011110001011111101010011",
+                "111101011110110101010010001010110001111101010010001011010000010010100100",
+                "011010111100110100100000010000000111",
+            ),
+            hex!("314192ecb1f2d8806a8108704c875a25d9fb7e444f9f373919adedebe8f2ae27"),
+            hex!("6b12b32f1372ad574799dee9eb591d961b704bf611f55fcc71f7e82cd3330b74"),
+        ),
+        (
+            "0000001",
+            hex!("1f1086b287636a20063c9614db2de66bb7d49242e88060956a5e5845057f6f5d"),
+            hex!("6b1b395421dde74d53341caa9e01f39d7a3138efb9b57fc0381f98f4868df622"),
+        ),
+    ];
+
+    fn from_hex_be(mut bytes: [u8; 32]) -> Scalar {
+        bytes.reverse();
+        Scalar::from_bytes_le(&bytes).unwrap()
+    }
+
+    fn alloc_bits(cs: &mut TestConstraintSystem<Scalar>, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// The number of constraints of `hash_circuit` for allocated bits.
+    fn num_constraints(num_bits: usize) -> usize {
+        let chunks = (num_bits + 2) / 3;
+        let segments = (chunks + CHUNKS_PER_GENERATOR - 1) / CHUNKS_PER_GENERATOR;
+
+        // A lookup costs 2 constraints, or 1 if the second bit is the constant
+        // padding.
+        let lookups = 2 * chunks - (num_bits % 3 == 1) as usize;
+        lookups + (chunks - segments) * 3 + segments * 2 + (segments - 1) * 6
+    }
+
+    #[test]
+    fn test_vectors() {
+        let params = PedersenParams::new(6);
+        for (bits, u, v) in TEST_VECTORS {
+            let bits: Vec<bool> = bits.bytes().map(|b| b == b'1').collect();
+            let expected = Point::from_coordinates(from_hex_be(*u), from_hex_be(*v)).unwrap();
+            assert_eq!(hash(&params, &bits), expected);
+
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let bits_var = alloc_bits(&mut cs, &bits);
+            let p = hash_circuit(cs.namespace(|| "hash"), &params, &bits_var).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(p.get_value(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_hash_circuit() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = PedersenParams::new(6);
+
+        for num_bits in [1, 2, 3, 188, 189, 190, 4 * 189 + 1, 6 * 189] {
+            let bits: Vec<bool> = (0..num_bits).map(|_| rng.next_u32() % 2 == 1).collect();
+
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let bits_var = alloc_bits(&mut cs, &bits);
+            let p = hash_circuit(cs.namespace(|| "hash"), &params, &bits_var).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(p.get_value(), Some(hash(&params, &bits)));
+            assert_eq!(cs.num_constraints(), num_bits + num_constraints(num_bits));
+        }
+    }
+}
@@ -0,0 +1,620 @@
+//! The [Poseidon] hash function, natively and as a circuit.
+//!
+//! The permutation uses the S-box $x^5$, which requires $\gcd(5, p - 1) = 1$ as
+//! for the scalar field of BLS12-381. The state holds one capacity element,
+//! followed by `arity` rate elements. The round constants and the MDS matrix are
+//! generated with the Grain LFSR of the reference implementation, the MDS matrix
+//! being a Cauchy matrix. Unlike the reference implementation, the MDS matrix is
+//! not checked against infinitely long invariant subspace trails.
+//!
+//! Both implementations expose the same duplex sponge, so that absorbing and
+//! squeezing the same elements gives the same outputs natively and in the
+//! circuit. Only the S-boxes cost constraints: the round constants and the MDS
+//! matrix are applied to linear combinations.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458.pdf
+
+use ff::PrimeField;
+
+use super::num::AllocatedNum;
+use super::Assignment;
+use bellpepper_core::{ConstraintSystem, LinearCombination, SynthesisError};
+
+/// Number of full rounds, half of them before the partial rounds.
+const FULL_ROUNDS: usize = 8;
+
+/// Number of partial rounds for 128 bits of security with the S-box $x^5$ on a
+/// 255-bit field, as given by the Poseidon authors, for widths 2 to 17.
+const PARTIAL_ROUNDS: [usize; 16] = [
+    56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68,
+];
+
+/// The parameters of a Poseidon permutation.
+#[derive(Clone, Debug)]
+pub struct PoseidonParams<F: PrimeField> {
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    /// The `width` constants of each round, in order.
+    round_constants: Vec<F>,
+    mds: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> PoseidonParams<F> {
+    /// Parameters absorbing `arity` elements per permutation, with 128 bits of
+    /// security. The arity ranges from 1 to 16.
+    pub fn new(arity: usize) -> Self {
+        assert!(
+            (1..=PARTIAL_ROUNDS.len()).contains(&arity),
+            "unsupported Poseidon arity {}",
+            arity
+        );
+        Self::with_rounds(arity + 1, FULL_ROUNDS, PARTIAL_ROUNDS[arity - 1])
+    }
+
+    /// Parameters with the given width and numbers of rounds. The number of full
+    /// rounds must be even.
+    pub fn with_rounds(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(width >= 2);
+        assert!(full_rounds % 2 == 0);
+
+        let num_bits = F::NUM_BITS as usize;
+        let mut grain = Grain::new(num_bits, width, full_rounds, partial_rounds);
+
+        // Round constants are sampled until they are canonical.
+        let round_constants = (0..(full_rounds + partial_rounds) * width)
+            .map(|_| loop {
+                if let Some(c) = grain.next_canonical(num_bits) {
+                    break c;
+                }
+            })
+            .collect();
+
+        // The elements defining the Cauchy matrix are reduced, and sampled again
+        // until they are distinct and no sum $x_i + y_j$ is zero.
+        let mds = loop {
+            let elements: Vec<F> = (0..2 * width)
+                .map(|_| grain.next_reduced(num_bits))
+                .collect();
+            let distinct = elements
+                .iter()
+                .enumerate()
+                .all(|(i, e)| !elements[..i].contains(e));
+            if !distinct {
+                continue;
+            }
+            let (xs, ys) = elements.split_at(width);
+            let mds: Option<Vec<Vec<F>>> = xs
+                .iter()
+                .map(|x| ys.iter().map(|y| Option::from((*x + y).invert())).collect())
+                .collect();
+            if let Some(mds) = mds {
+                break mds;
+            }
+        };
+
+        PoseidonParams {
+            width,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// The number of elements of the state.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of elements absorbed or squeezed per permutation.
+    pub fn arity(&self) -> usize {
+        self.width - 1
+    }
+
+    fn is_full_round(&self, round: usize) -> bool {
+        let half = self.full_rounds / 2;
+        round < half || round >= half + self.partial_rounds
+    }
+}
+
+/// The Grain LFSR used by the reference implementation to generate the
+/// parameters.
+struct Grain {
+    state: [bool; 80],
+}
+
+impl Grain {
+    fn new(num_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        // A prime field (1) and the S-box $x^\alpha$ (0), followed by the sizes.
+        let fields = [
+            (1, 2),
+            (0, 4),
+            (num_bits, 12),
+            (width, 12),
+            (full_rounds, 10),
+            (partial_rounds, 10),
+            ((1 << 30) - 1, 30),
+        ];
+        let mut state = [false; 80];
+        let mut i = 0;
+        for (value, len) in fields {
+            for j in (0..len).rev() {
+                state[i] = (value >> j) & 1 == 1;
+                i += 1;
+            }
+        }
+
+        let mut grain = Grain { state };
+        for _ in 0..160 {
+            grain.clock();
+        }
+        grain
+    }
+
+    fn clock(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    /// Bits are output in pairs, the second one being kept if the first one is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.clock();
+            let bit = self.clock();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Returns the next `num_bits` bits, most significant first, reduced in `F`.
+    fn next_reduced<F: PrimeField>(&mut self, num_bits: usize) -> F {
+        (0..num_bits).fold(F::ZERO, |acc, _| {
+            let acc = acc.double();
+            if self.next_bit() {
+                acc + F::ONE
+            } else {
+                acc
+            }
+        })
+    }
+
+    /// Returns the next `num_bits` bits, most significant first, if they are the
+    /// canonical little-endian representation of an element of `F`.
+    fn next_canonical<F: PrimeField>(&mut self, num_bits: usize) -> Option<F> {
+        let mut repr = F::Repr::default();
+        let bytes = repr.as_mut();
+        for i in (0..num_bits).rev() {
+            if self.next_bit() {
+                bytes[i / 8] |= 1 << (i % 8);
+            }
+        }
+        F::from_repr(repr).into()
+    }
+}
+
+fn sbox<F: PrimeField>(x: F) -> F {
+    x.square().square() * x
+}
+
+/// The Poseidon permutation of `state`.
+pub fn permute<F: PrimeField>(params: &PoseidonParams<F>, state: &mut [F]) {
+    assert_eq!(state.len(), params.width);
+
+    for (round, constants) in params.round_constants.chunks(params.width).enumerate() {
+        for (s, c) in state.iter_mut().zip(constants) {
+            *s += c;
+        }
+        if params.is_full_round(round) {
+            for s in state.iter_mut() {
+                *s = sbox(*s);
+            }
+        } else {
+            state[0] = sbox(state[0]);
+        }
+
+        let mixed: Vec<F> = params
+            .mds
+            .iter()
+            .map(|row| row.iter().zip(state.iter()).map(|(m, s)| *m * s).sum())
+            .collect();
+        state.copy_from_slice(&mixed);
+    }
+}
+
+/// Hashes `inputs` with a sponge whose capacity element is initialized to the
+/// number of inputs. Hashing `arity` inputs takes a single permutation.
+pub fn hash<F: PrimeField>(params: &PoseidonParams<F>, inputs: &[F]) -> F {
+    let mut sponge = Sponge::new(params, F::from(inputs.len() as u64));
+    sponge.absorb(inputs);
+    sponge.squeeze()
+}
+
+/// A duplex sponge over the Poseidon permutation. Absorbed elements are added to
+/// the rate elements, and the state is permuted whenever the rate is exhausted,
+/// or before squeezing after absorbing.
+#[derive(Clone, Debug)]
+pub struct Sponge<'a, F: PrimeField> {
+    params: &'a PoseidonParams<F>,
+    state: Vec<F>,
+    /// Position in the rate of the next element absorbed or squeezed.
+    pos: usize,
+    squeezing: bool,
+}
+
+impl<'a, F: PrimeField> Sponge<'a, F> {
+    /// Creates a sponge with the capacity element set to `domain`.
+    pub fn new(params: &'a PoseidonParams<F>, domain: F) -> Self {
+        let mut state = vec![F::ZERO; params.width];
+        state[0] = domain;
+        Sponge {
+            params,
+            state,
+            pos: 0,
+            squeezing: false,
+        }
+    }
+
+    pub fn absorb(&mut self, inputs: &[F]) {
+        if self.squeezing {
+            self.squeezing = false;
+            self.pos = 0;
+        }
+        for input in inputs {
+            if self.pos == self.params.arity() {
+                permute(self.params, &mut self.state);
+                self.pos = 0;
+            }
+            self.state[1 + self.pos] += input;
+            self.pos += 1;
+        }
+    }
+
+    pub fn squeeze(&mut self) -> F {
+        if !self.squeezing || self.pos == self.params.arity() {
+            permute(self.params, &mut self.state);
+            self.squeezing = true;
+            self.pos = 0;
+        }
+        self.pos += 1;
+        self.state[self.pos]
+    }
+}
+
+/// An element of the state in the circuit, which is a linear combination of
+/// variables plus a constant until it goes through an S-box.
+#[derive(Clone)]
+struct Elt<F: PrimeField> {
+    lc: LinearCombination<F>,
+    constant: F,
+    value: Option<F>,
+}
+
+impl<F: PrimeField> Elt<F> {
+    fn constant(c: F) -> Self {
+        Elt {
+            lc: LinearCombination::zero(),
+            constant: c,
+            value: Some(c),
+        }
+    }
+
+    fn add_scaled(self, other: &Self, coeff: F) -> Self {
+        Elt {
+            lc: self.lc + (coeff, &other.lc),
+            constant: self.constant + other.constant * coeff,
+            value: self.value.zip(other.value).map(|(a, b)| a + b * coeff),
+        }
+    }
+
+    fn add_constant(self, c: F) -> Self {
+        Elt {
+            lc: self.lc,
+            constant: self.constant + c,
+            value: self.value.map(|v| v + c),
+        }
+    }
+
+    fn lc<CS: ConstraintSystem<F>>(&self) -> LinearCombination<F> {
+        self.lc.clone() + (self.constant, CS::one())
+    }
+}
+
+impl<F: PrimeField> From<&AllocatedNum<F>> for Elt<F> {
+    fn from(num: &AllocatedNum<F>) -> Self {
+        Elt {
+            lc: LinearCombination::zero() + num.get_variable(),
+            constant: F::ZERO,
+            value: num.get_value(),
+        }
+    }
+}
+
+fn sbox_circuit<F, CS>(mut cs: CS, x: &Elt<F>) -> Result<Elt<F>, SynthesisError>
+where
+    F: PrimeField,
+    CS: ConstraintSystem<F>,
+{
+    let x_lc = x.lc::<CS>();
+    let x2 = AllocatedNum::alloc(cs.namespace(|| "x^2"), || Ok(x.value.get()?.square()))?;
+    cs.enforce(
+        || "x^2 = x * x",
+        |lc| lc + &x_lc,
+        |lc| lc + &x_lc,
+        |lc| lc + x2.get_variable(),
+    );
+    let x4 = x2.square(cs.namespace(|| "x^4"))?;
+    let x5 = AllocatedNum::alloc(cs.namespace(|| "x^5"), || {
+        Ok(*x4.get_value().get()? * x.value.get()?)
+    })?;
+    cs.enforce(
+        || "x^5 = x^4 * x",
+        |lc| lc + x4.get_variable(),
+        |lc| lc + &x_lc,
+        |lc| lc + x5.get_variable(),
+    );
+    Ok(Elt::from(&x5))
+}
+
+fn permute_circuit<F, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<F>,
+    state: &mut [Elt<F>],
+) -> Result<(), SynthesisError>
+where
+    F: PrimeField,
+    CS: ConstraintSystem<F>,
+{
+    for (round, constants) in params.round_constants.chunks(params.width).enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        for (s, c) in state.iter_mut().zip(constants) {
+            *s = s.clone().add_constant(*c);
+        }
+        if params.is_full_round(round) {
+            for (i, s) in state.iter_mut().enumerate() {
+                *s = sbox_circuit(cs.namespace(|| format!("sbox {}", i)), s)?;
+            }
+        } else {
+            state[0] = sbox_circuit(cs.namespace(|| "sbox 0"), &state[0])?;
+        }
+
+        let mixed: Vec<Elt<F>> = params
+            .mds
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(state.iter())
+                    .fold(Elt::constant(F::ZERO), |acc, (m, s)| acc.add_scaled(s, *m))
+            })
+            .collect();
+        state.clone_from_slice(&mixed);
+    }
+    Ok(())
+}
+
+/// Hashes `inputs` in the circuit, like `hash`.
+pub fn hash_circuit<F, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<F>,
+    inputs: &[AllocatedNum<F>],
+) -> Result<AllocatedNum<F>, SynthesisError>
+where
+    F: PrimeField,
+    CS: ConstraintSystem<F>,
+{
+    let mut sponge = SpongeCircuit::new(params, F::from(inputs.len() as u64));
+    sponge.absorb(cs.namespace(|| "absorb"), inputs)?;
+    sponge.squeeze(cs.namespace(|| "squeeze"))
+}
+
+/// The circuit counterpart of `Sponge`.
+#[derive(Clone)]
+pub struct SpongeCircuit<'a, F: PrimeField> {
+    params: &'a PoseidonParams<F>,
+    state: Vec<Elt<F>>,
+    pos: usize,
+    squeezing: bool,
+    /// Number of permutations so far, to name their namespaces.
+    permutations: usize,
+}
+
+impl<'a, F: PrimeField> SpongeCircuit<'a, F> {
+    /// Creates a sponge with the capacity element set to `domain`.
+    pub fn new(params: &'a PoseidonParams<F>, domain: F) -> Self {
+        let mut state = vec![Elt::constant(F::ZERO); params.width];
+        state[0] = Elt::constant(domain);
+        SpongeCircuit {
+            params,
+            state,
+            pos: 0,
+            squeezing: false,
+            permutations: 0,
+        }
+    }
+
+    fn permute<CS: ConstraintSystem<F>>(&mut self, mut cs: CS) -> Result<(), SynthesisError> {
+        permute_circuit(
+            cs.namespace(|| format!("permutation {}", self.permutations)),
+            self.params,
+            &mut self.state,
+        )?;
+        self.permutations += 1;
+        self.pos = 0;
+        Ok(())
+    }
+
+    pub fn absorb<CS: ConstraintSystem<F>>(
+        &mut self,
+        mut cs: CS,
+        inputs: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        if self.squeezing {
+            self.squeezing = false;
+            self.pos = 0;
+        }
+        for input in inputs {
+            if self.pos == self.params.arity() {
+                self.permute(&mut cs)?;
+            }
+            let s = &mut self.state[1 + self.pos];
+            *s = s.clone().add_scaled(&Elt::from(input), F::ONE);
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    pub fn squeeze<CS: ConstraintSystem<F>>(
+        &mut self,
+        mut cs: CS,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+        if !self.squeezing || self.pos == self.params.arity() {
+            self.permute(&mut cs)?;
+            self.squeezing = true;
+        }
+        self.pos += 1;
+
+        let elt = &self.state[self.pos];
+        let out = AllocatedNum::alloc(cs.namespace(|| "output"), || Ok(*elt.value.get()?))?;
+        cs.enforce(
+            || "output is the state element",
+            |lc| lc + &elt.lc::<CS>(),
+            |lc| lc + CS::one(),
+            |lc| lc + out.get_variable(),
+        );
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn from_hex(bytes: [u8; 32]) -> Fr {
+        let mut repr = bytes;
+        repr.reverse();
+        Fr::from_repr(repr).unwrap()
+    }
+
+    fn alloc_inputs(cs: &mut TestConstraintSystem<Fr>, inputs: &[Fr]) -> Vec<AllocatedNum<Fr>> {
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*x)))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_reference_vector() {
+        // poseidonperm_x5_255_3 of the reference implementation
+        let params = PoseidonParams::<Fr>::new(2);
+        let mut state = [Fr::ZERO, Fr::ONE, Fr::from(2)];
+        permute(&params, &mut state);
+        assert_eq!(
+            state,
+            [
+                from_hex(hex!(
+                    "28ce19420fc246a05553ad1e8c98f5c9d67166be2c18e9e4cb4b4e317dd2a78a"
+                )),
+                from_hex(hex!(
+                    "51f3e312c95343a896cfd8945ea82ba956c1118ce9b9859b6ea56637b4b1ddc4"
+                )),
+                from_hex(hex!(
+                    "3b2b69139b235626a0bfb56c9527ae66a7bf486ad8c11c14d1da0c69bbe0f79a"
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hash_circuit() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for arity in [1, 2, 4, 8, 11, 16] {
+            let params = PoseidonParams::<Fr>::new(arity);
+            for num_inputs in [0, 1, arity, arity + 1, 3 * arity] {
+                let inputs: Vec<Fr> = (0..num_inputs).map(|_| Fr::random(&mut rng)).collect();
+                let expected = hash(&params, &inputs);
+
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let nums = alloc_inputs(&mut cs, &inputs);
+                let out = hash_circuit(cs.namespace(|| "hash"), &params, &nums).unwrap();
+                assert!(cs.is_satisfied());
+                assert_eq!(out.get_value(), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_constraints() {
+        let params = PoseidonParams::<Fr>::new(2);
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let nums = alloc_inputs(&mut cs, &[Fr::ONE, Fr::from(2)]);
+        let out = hash_circuit(cs.namespace(|| "hash"), &params, &nums).unwrap();
+        assert!(cs.is_satisfied());
+        // three constraints per S-box and one for the output
+        assert_eq!(cs.num_constraints(), 3 * (8 * 3 + 57) + 1);
+
+        // a wrong output is rejected
+        cs.set(
+            "hash/squeeze/output/num",
+            out.get_value().unwrap() + Fr::ONE,
+        );
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_sponge_circuit() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = PoseidonParams::<Fr>::new(4);
+        let domain = Fr::random(&mut rng);
+
+        let mut sponge = Sponge::new(&params, domain);
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let mut sponge_circuit = SpongeCircuit::new(&params, domain);
+
+        // interleave absorbing and squeezing, across the rate
+        for (step, (num_absorbed, num_squeezed)) in
+            [(3, 1), (6, 5), (0, 2), (4, 4)].into_iter().enumerate()
+        {
+            let mut cs = cs.namespace(|| format!("step {}", step));
+            let inputs: Vec<Fr> = (0..num_absorbed).map(|_| Fr::random(&mut rng)).collect();
+            let nums = inputs
+                .iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*x))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+            sponge.absorb(&inputs);
+            sponge_circuit
+                .absorb(cs.namespace(|| "absorb"), &nums)
+                .unwrap();
+            for i in 0..num_squeezed {
+                let out = sponge_circuit
+                    .squeeze(cs.namespace(|| format!("squeeze {}", i)))
+                    .unwrap();
+                assert_eq!(out.get_value(), Some(sponge.squeeze()));
+            }
+        }
+        assert!(cs.is_satisfied());
+    }
+}
@@ -0,0 +1,436 @@
+//! Range checks and comparisons of field elements.
+//!
+//! The comparisons are only meaningful for values bounded by a power of two
+//! given by the caller, which must be enforced separately, e.g. with
+//! `range_check_bits`, unless it follows from the rest of the circuit. The bounds
+//! must be smaller than the capacity of the field, so that no check wraps
+//! around its modulus.
+
+use ff::PrimeFieldBits;
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::num::AllocatedNum;
+use super::Assignment;
+use bellpepper_core::{ConstraintSystem, LinearCombination, SynthesisError};
+
+/// Decomposes the linear combination `lc`, of value `value`, into `num_bits`
+/// little-endian bits, which enforces that it is smaller than `2^num_bits`.
+fn decompose<Scalar, CS>(
+    mut cs: CS,
+    lc: LinearCombination<Scalar>,
+    value: Option<Scalar>,
+    num_bits: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(num_bits < Scalar::CAPACITY as usize);
+
+    let values: Vec<Option<bool>> = match value {
+        Some(v) => v
+            .to_le_bits()
+            .iter()
+            .by_vals()
+            .take(num_bits)
+            .map(Some)
+            .collect(),
+        None => vec![None; num_bits],
+    };
+
+    let mut bits = Vec::with_capacity(num_bits);
+    let mut sum = LinearCombination::zero();
+    let mut coeff = Scalar::ONE;
+    for (i, value) in values.into_iter().enumerate() {
+        let bit = AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), value)?;
+        sum = sum + (coeff, bit.get_variable());
+        bits.push(Boolean::from(bit));
+        coeff = coeff.double();
+    }
+
+    cs.enforce(|| "decomposition", |_| sum, |lc| lc + CS::one(), |_| lc);
+
+    Ok(bits)
+}
+
+/// Enforces `num < 2^num_bits`, returning the little-endian bits of `num`. It
+/// takes `num_bits + 1` constraints.
+pub fn range_check_bits<Scalar, CS>(
+    cs: CS,
+    num: &AllocatedNum<Scalar>,
+    num_bits: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    decompose(
+        cs,
+        LinearCombination::zero() + num.get_variable(),
+        num.get_value(),
+        num_bits,
+    )
+}
+
+/// Enforces `num < 2^(limb_bits * num_limbs)` by decomposing it into `num_limbs`
+/// little-endian limbs of `limb_bits` bits, which are returned.
+///
+/// Each limb is allocated and decomposed into its own `limb_bits` bits, and
+/// `num` is enforced to be the sum of the limbs, which takes
+/// `num_limbs * (limb_bits + 1) + 1` constraints. That is one constraint per
+/// limb more than `range_check_bits` on the whole value, in exchange for the
+/// limbs as numbers, e.g. to index lookup tables.
+pub fn range_check_limbs<Scalar, CS>(
+    mut cs: CS,
+    num: &AllocatedNum<Scalar>,
+    limb_bits: usize,
+    num_limbs: usize,
+) -> Result<Vec<AllocatedNum<Scalar>>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(limb_bits > 0);
+    assert!(limb_bits * num_limbs < Scalar::CAPACITY as usize);
+
+    let bits: Option<Vec<bool>> = num
+        .get_value()
+        .map(|v| v.to_le_bits().iter().by_vals().collect());
+
+    let mut limbs = Vec::with_capacity(num_limbs);
+    let mut sum = LinearCombination::zero();
+    let mut coeff = Scalar::ONE;
+    for i in 0..num_limbs {
+        let mut cs = cs.namespace(|| format!("limb {}", i));
+
+        let limb = AllocatedNum::alloc(cs.namespace(|| "limb"), || {
+            let bits = &bits.get()?[i * limb_bits..(i + 1) * limb_bits];
+            Ok(bits.iter().rev().fold(Scalar::ZERO, |acc, &b| {
+                if b {
+                    acc.double() + Scalar::ONE
+                } else {
+                    acc.double()
+                }
+            }))
+        })?;
+        range_check_bits(cs.namespace(|| "bits"), &limb, limb_bits)?;
+
+        sum = sum + (coeff, limb.get_variable());
+        limbs.push(limb);
+        for _ in 0..limb_bits {
+            coeff = coeff.double();
+        }
+    }
+
+    cs.enforce(
+        || "decomposition",
+        |_| sum,
+        |lc| lc + CS::one(),
+        |lc| lc + num.get_variable(),
+    );
+
+    Ok(limbs)
+}
+
+/// Returns whether `a < b`, for `a` and `b` smaller than `2^num_bits`. It takes
+/// `num_bits + 2` constraints.
+pub fn less_than<Scalar, CS>(
+    cs: CS,
+    a: &AllocatedNum<Scalar>,
+    b: &AllocatedNum<Scalar>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    // $a - b + 2^n$ is in $[0, 2^{n+1})$, and its top bit is set iff $a \geq b$.
+    let shift = Scalar::from(2).pow_vartime([num_bits as u64]);
+    let lc = LinearCombination::zero() + a.get_variable() - b.get_variable() + (shift, CS::one());
+    let value = a.get_value().zip(b.get_value()).map(|(a, b)| a - b + shift);
+
+    let bits = decompose(cs, lc, value, num_bits + 1)?;
+    Ok(bits[num_bits].not())
+}
+
+/// Returns whether `a <= b`, for `a` and `b` smaller than `2^num_bits`.
+pub fn less_or_equal<Scalar, CS>(
+    cs: CS,
+    a: &AllocatedNum<Scalar>,
+    b: &AllocatedNum<Scalar>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    Ok(less_than(cs, b, a, num_bits)?.not())
+}
+
+/// Returns `a` if `condition` is true, and `b` otherwise.
+pub(super) fn select<Scalar, CS>(
+    mut cs: CS,
+    condition: &Boolean,
+    a: &AllocatedNum<Scalar>,
+    b: &AllocatedNum<Scalar>,
+) -> Result<AllocatedNum<Scalar>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    let out = AllocatedNum::alloc(cs.namespace(|| "select"), || {
+        if *condition.get_value().get()? {
+            Ok(*a.get_value().get()?)
+        } else {
+            Ok(*b.get_value().get()?)
+        }
+    })?;
+
+    // out - b = condition * (a - b)
+    cs.enforce(
+        || "selection",
+        |lc| lc + a.get_variable() - b.get_variable(),
+        |_| condition.lc(CS::one(), Scalar::ONE),
+        |lc| lc + out.get_variable() - b.get_variable(),
+    );
+
+    Ok(out)
+}
+
+/// Returns the minimum of `a` and `b`, which are smaller than `2^num_bits`.
+pub fn min<Scalar, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<Scalar>,
+    b: &AllocatedNum<Scalar>,
+    num_bits: usize,
+) -> Result<AllocatedNum<Scalar>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    let lt = less_than(cs.namespace(|| "a < b"), a, b, num_bits)?;
+    select(cs.namespace(|| "min"), &lt, a, b)
+}
+
+/// Returns the maximum of `a` and `b`, which are smaller than `2^num_bits`.
+pub fn max<Scalar, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<Scalar>,
+    b: &AllocatedNum<Scalar>,
+    num_bits: usize,
+) -> Result<AllocatedNum<Scalar>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    let lt = less_than(cs.namespace(|| "a < b"), a, b, num_bits)?;
+    select(cs.namespace(|| "max"), &lt, b, a)
+}
+
+/// Enforces `min <= num <= max`.
+///
+/// With `k` the number of bits of `max - min`, both `num - min` and
+/// `max - num` are decomposed into `k` bits, the latter being skipped if
+/// `max - min = 2^k - 1`.
+pub fn assert_in_range<Scalar, CS>(
+    mut cs: CS,
+    num: &AllocatedNum<Scalar>,
+    min: u64,
+    max: u64,
+) -> Result<(), SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(min <= max);
+    let range = max - min;
+    let num_bits = (u64::BITS - range.leading_zeros()) as usize;
+
+    let (min, max) = (Scalar::from(min), Scalar::from(max));
+    decompose(
+        cs.namespace(|| "num - min"),
+        LinearCombination::zero() + num.get_variable() - (min, CS::one()),
+        num.get_value().map(|v| v - min),
+        num_bits,
+    )?;
+    if range.checked_add(1).map_or(true, |r| !r.is_power_of_two()) {
+        decompose(
+            cs.namespace(|| "max - num"),
+            LinearCombination::zero() + (max, CS::one()) - num.get_variable(),
+            num.get_value().map(|v| max - v),
+            num_bits,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::util_cs::metric_cs::MetricCS;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn alloc<CS: ConstraintSystem<Fr>>(cs: &mut CS, name: &str, value: u64) -> AllocatedNum<Fr> {
+        AllocatedNum::alloc(cs.namespace(|| name), || Ok(Fr::from(value))).unwrap()
+    }
+
+    #[test]
+    fn test_range_check_bits() {
+        for (value, num_bits, ok) in [(0, 0, true), (0, 8, true), (255, 8, true), (256, 8, false)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let num = alloc(&mut cs, "num", value);
+            let bits = range_check_bits(cs.namespace(|| "range"), &num, num_bits).unwrap();
+            assert_eq!(cs.is_satisfied(), ok);
+            if ok {
+                let bits: u64 = bits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| (b.get_value().unwrap() as u64) << i)
+                    .sum();
+                assert_eq!(bits, value);
+            }
+        }
+
+        // a value that wraps around the modulus is rejected
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(-Fr::ONE)).unwrap();
+        range_check_bits(cs.namespace(|| "range"), &num, 64).unwrap();
+        assert!(!cs.is_satisfied());
+
+        let mut cs = MetricCS::<Fr>::new();
+        let num = alloc(&mut cs, "num", 5);
+        range_check_bits(cs.namespace(|| "range"), &num, 64).unwrap();
+        assert_eq!(cs.num_constraints(), 65);
+    }
+
+    #[test]
+    fn test_range_check_limbs() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for limb_bits in [1, 2, 3, 4, 8, 16] {
+            let num_limbs = 16 / limb_bits;
+            let total_bits = limb_bits * num_limbs;
+            let value = rng.next_u64() & ((1 << total_bits) - 1);
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let num = alloc(&mut cs, "num", value);
+            let limbs =
+                range_check_limbs(cs.namespace(|| "range"), &num, limb_bits, num_limbs).unwrap();
+            assert!(cs.is_satisfied());
+
+            let mask = (1 << limb_bits) - 1;
+            for (i, limb) in limbs.iter().enumerate() {
+                let expected = (value >> (i * limb_bits)) & mask;
+                assert_eq!(limb.get_value(), Some(Fr::from(expected)));
+            }
+
+            // a limb out of range is rejected, even if the sum is correct
+            if num_limbs > 1 {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let num = alloc(&mut cs, "num", 1 << limb_bits);
+                range_check_limbs(cs.namespace(|| "range"), &num, limb_bits, num_limbs).unwrap();
+                assert!(cs.is_satisfied());
+                cs.set("range/limb 0/limb/num", Fr::from(1 << limb_bits));
+                cs.set("range/limb 1/limb/num", Fr::ZERO);
+                cs.set("range/limb 1/bits/bit 0/boolean", Fr::ZERO);
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("range/limb 0/bits/decomposition")
+                );
+            }
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let num = alloc(&mut cs, "num", 1 << total_bits);
+            range_check_limbs(cs.namespace(|| "range"), &num, limb_bits, num_limbs).unwrap();
+            assert!(!cs.is_satisfied());
+
+            let mut cs = MetricCS::<Fr>::new();
+            let num = alloc(&mut cs, "num", value);
+            range_check_limbs(cs.namespace(|| "range"), &num, limb_bits, num_limbs).unwrap();
+            assert_eq!(cs.num_constraints(), num_limbs * (limb_bits + 1) + 1);
+        }
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let cases = (0..50)
+            .map(|_| (rng.next_u32() as u64, rng.next_u32() as u64))
+            .chain([(0, 0), (5, 5), (0, u32::MAX as u64), (u32::MAX as u64, 0)]);
+        for (a_value, b_value) in cases {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = alloc(&mut cs, "a", a_value);
+            let b = alloc(&mut cs, "b", b_value);
+
+            let lt = less_than(cs.namespace(|| "lt"), &a, &b, 32).unwrap();
+            let le = less_or_equal(cs.namespace(|| "le"), &a, &b, 32).unwrap();
+            let min = min(cs.namespace(|| "min"), &a, &b, 32).unwrap();
+            let max = max(cs.namespace(|| "max"), &a, &b, 32).unwrap();
+            assert!(cs.is_satisfied());
+
+            assert_eq!(lt.get_value(), Some(a_value < b_value));
+            assert_eq!(le.get_value(), Some(a_value <= b_value));
+            assert_eq!(min.get_value(), Some(Fr::from(a_value.min(b_value))));
+            assert_eq!(max.get_value(), Some(Fr::from(a_value.max(b_value))));
+
+            // flipping the result is rejected
+            let path = "lt/bit 32/boolean";
+            let flipped = if cs.get(path).is_zero().into() {
+                Fr::ONE
+            } else {
+                Fr::ZERO
+            };
+            cs.set(path, flipped);
+            assert!(!cs.is_satisfied());
+        }
+
+        let mut cs = MetricCS::<Fr>::new();
+        let a = alloc(&mut cs, "a", 1);
+        let b = alloc(&mut cs, "b", 2);
+        less_than(cs.namespace(|| "lt"), &a, &b, 32).unwrap();
+        assert_eq!(cs.num_constraints(), 34);
+        min(cs.namespace(|| "min"), &a, &b, 32).unwrap();
+        assert_eq!(cs.num_constraints(), 34 + 35);
+    }
+
+    #[test]
+    fn test_assert_in_range() {
+        for (min, max) in [(0u64, 0u64), (0, 255), (10, 20), (100, 355), (7, u64::MAX)] {
+            let candidates = [
+                min.checked_sub(1),
+                Some(min),
+                Some(min + (max - min) / 2),
+                Some(max),
+                max.checked_add(1),
+            ];
+            for value in candidates.into_iter().flatten() {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let num = alloc(&mut cs, "num", value);
+                assert_in_range(cs.namespace(|| "range"), &num, min, max).unwrap();
+                assert_eq!(cs.is_satisfied(), min <= value && value <= max);
+            }
+        }
+
+        // a single decomposition when the range is a power of two
+        let mut cs = MetricCS::<Fr>::new();
+        let num = alloc(&mut cs, "num", 100);
+        assert_in_range(cs.namespace(|| "range"), &num, 100, 355).unwrap();
+        assert_eq!(cs.num_constraints(), 9);
+
+        let mut cs = MetricCS::<Fr>::new();
+        let num = alloc(&mut cs, "num", 15);
+        assert_in_range(cs.namespace(|| "range"), &num, 10, 20).unwrap();
+        assert_eq!(cs.num_constraints(), 10);
+    }
+}
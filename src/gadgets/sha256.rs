@@ -7,10 +7,11 @@
 
 use ff::PrimeField;
 
-use super::boolean::Boolean;
+use super::boolean::{AllocatedBit, Boolean};
 use super::multieq::MultiEq;
+use super::num::AllocatedNum;
 use super::uint32::UInt32;
-use bellpepper_core::{ConstraintSystem, SynthesisError};
+use bellpepper_core::{ConstraintSystem, LinearCombination, SynthesisError};
 
 #[allow(clippy::unreadable_literal)]
 const ROUND_CONSTANTS: [u32; 64] = [
@@ -76,6 +77,194 @@ where
     Ok(cur.into_iter().flat_map(|e| e.into_bits_be()).collect())
 }
 
+/// SHA-256 of the first `length` bytes of `input`, where `length` is a witness
+/// ranging from zero to `input.len() / 8`. The bits of `input` past `length` are
+/// ignored.
+///
+/// The padding is computed in the circuit from the bits $m_i = [i < length]$,
+/// which are enforced to be non-increasing and to sum to `length`. This also
+/// enforces the range of `length`. All the blocks a message of `input.len() / 8`
+/// bytes needs are compressed, and the state after the final block of the
+/// actual message is selected.
+pub fn sha256_var<Scalar, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    length: &AllocatedNum<Scalar>,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    Scalar: PrimeField,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(input.len() % 8 == 0);
+    let max_len = input.len() / 8;
+
+    let len_value = match length.get_value() {
+        Some(v) => Some(
+            (0..=max_len)
+                .find(|&n| Scalar::from(n as u64) == v)
+                .ok_or(SynthesisError::Unsatisfiable)?,
+        ),
+        None => None,
+    };
+
+    let mut m = Vec::with_capacity(max_len);
+    for i in 0..max_len {
+        m.push(Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| format!("m {}", i)),
+            len_value.map(|l| i < l),
+        )?));
+    }
+    for i in 1..max_len {
+        // m[i] implies m[i - 1]
+        cs.enforce(
+            || format!("m {} implies m {}", i, i - 1),
+            |_| m[i].lc(CS::one(), Scalar::ONE),
+            |lc| lc + CS::one() - &m[i - 1].lc(CS::one(), Scalar::ONE),
+            |lc| lc,
+        );
+    }
+    cs.enforce(
+        || "length is the sum of m",
+        |lc| {
+            m.iter()
+                .fold(lc, |lc, b| lc + &b.lc(CS::one(), Scalar::ONE))
+        },
+        |lc| lc + CS::one(),
+        |lc| lc + length.get_variable(),
+    );
+    // $m_i$ for any `i`, including out of the range of the input.
+    let m_at = |i: isize| -> Boolean {
+        if i < 0 {
+            Boolean::constant(true)
+        } else {
+            m.get(i as usize)
+                .cloned()
+                .unwrap_or(Boolean::constant(false))
+        }
+    };
+
+    // The bits of `length`, enough to hold `max_len`.
+    let num_len_bits = (usize::BITS - max_len.leading_zeros()) as usize;
+    let mut len_bits = Vec::with_capacity(num_len_bits);
+    let mut coeff = Scalar::ONE;
+    let mut len_lc = LinearCombination::zero();
+    for i in 0..num_len_bits {
+        let b = AllocatedBit::alloc(
+            cs.namespace(|| format!("length bit {}", i)),
+            len_value.map(|l| (l >> i) & 1 == 1),
+        )?;
+        len_lc = len_lc + (coeff, b.get_variable());
+        len_bits.push(Boolean::from(b));
+        coeff = coeff.double();
+    }
+    cs.enforce(
+        || "length decomposition",
+        |_| len_lc,
+        |lc| lc + CS::one(),
+        |lc| lc + length.get_variable(),
+    );
+
+    // The final block is the one holding the byte at `length + 8`.
+    let num_blocks = (max_len + 8) / 64 + 1;
+    let mut padded = Vec::with_capacity(num_blocks * 512);
+    for i in 0..num_blocks * 64 {
+        let mut cs = cs.namespace(|| format!("padded byte {}", i));
+        let (data_bits, m_i) = match input.get(i * 8..(i + 1) * 8) {
+            Some(bits) => (bits, m_at(i as isize)),
+            None => (&[][..], Boolean::constant(false)),
+        };
+
+        for j in 0..8 {
+            let bit = if j == 0 {
+                // The data bit if $i < length$, one if $i = length$, and zero otherwise.
+                let data = data_bits
+                    .first()
+                    .cloned()
+                    .unwrap_or(Boolean::constant(false));
+                let t = Boolean::and(cs.namespace(|| "m and not data"), &m_i, &data.not())?;
+                Boolean::and(
+                    cs.namespace(|| "first bit"),
+                    &m_at(i as isize - 1),
+                    &t.not(),
+                )?
+            } else {
+                match data_bits.get(j) {
+                    Some(data) => Boolean::and(cs.namespace(|| format!("bit {}", j)), data, &m_i)?,
+                    None => Boolean::constant(false),
+                }
+            };
+            padded.push(bit);
+        }
+    }
+
+    // Each block may be the final one, which holds the length in bits as a 64-bit
+    // big-endian integer in its last 8 bytes.
+    let mut is_final = Vec::with_capacity(num_blocks);
+    for b in 0..num_blocks {
+        let mut cs = cs.namespace(|| format!("block {} length", b));
+        let start = 64 * b as isize;
+        let s = Boolean::and(
+            cs.namespace(|| "is final"),
+            &m_at(start - 9),
+            &m_at(start + 55).not(),
+        )?;
+
+        for (j, len_bit) in len_bits.iter().enumerate() {
+            // bit `j + 3` of the length in bits
+            let pos = (b + 1) * 512 - 1 - (j + 3);
+            let bit = Boolean::and(cs.namespace(|| format!("bit {}", j)), &s, len_bit)?;
+            padded[pos] = Boolean::xor(
+                cs.namespace(|| format!("bit {} with data", j)),
+                &padded[pos],
+                &bit,
+            )?;
+        }
+        is_final.push(s);
+    }
+
+    let mut states = Vec::with_capacity(num_blocks);
+    let mut cur = get_sha256_iv();
+    for (i, block) in padded.chunks(512).enumerate() {
+        cur = sha256_compression_function(cs.namespace(|| format!("block {}", i)), block, &cur)?;
+        states.push(
+            cur.iter()
+                .cloned()
+                .flat_map(|e| e.into_bits_be())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    if num_blocks == 1 {
+        return Ok(states.pop().unwrap());
+    }
+
+    // As exactly one block is final, each output bit is the sum of the bits of the
+    // states masked by the flags.
+    (0..256)
+        .map(|i| {
+            let mut cs = cs.namespace(|| format!("output bit {}", i));
+            let mut sum = LinearCombination::zero();
+            let mut value = Some(false);
+            for (b, (state, s)) in states.iter().zip(is_final.iter()).enumerate() {
+                let masked = Boolean::and(cs.namespace(|| format!("block {}", b)), s, &state[i])?;
+                value = match (value, masked.get_value()) {
+                    (Some(v), Some(m)) => Some(v | m),
+                    _ => None,
+                };
+                sum = sum + &masked.lc(CS::one(), Scalar::ONE);
+            }
+            let out = AllocatedBit::alloc(cs.namespace(|| "bit"), value)?;
+            cs.enforce(
+                || "selection",
+                |_| sum,
+                |lc| lc + CS::one(),
+                |lc| lc + out.get_variable(),
+            );
+            Ok(Boolean::from(out))
+        })
+        .collect()
+}
+
 fn get_sha256_iv() -> Vec<UInt32> {
     IV.iter().map(|&v| UInt32::constant(v)).collect()
 }
@@ -414,4 +603,75 @@ mod test {
             }
         }
     }
+
+    fn alloc_input(cs: &mut TestConstraintSystem<Fr>, data: &[u8]) -> Vec<Boolean> {
+        data.iter()
+            .enumerate()
+            .flat_map(|(byte_i, byte)| (0..8).rev().map(move |bit_i| (byte_i, bit_i, *byte)))
+            .map(|(byte_i, bit_i, byte)| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)),
+                    Some((byte >> bit_i) & 1u8 == 1u8),
+                )
+                .unwrap()
+                .into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sha256_var() {
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for max_len in [0, 1, 55, 56, 64, 130] {
+            let data: Vec<u8> = (0..max_len).map(|_| rng.next_u32() as u8).collect();
+            let lengths = (0..=max_len).filter(|l| l % 7 == 0 || max_len - l < 3 || l % 64 >= 54);
+            for len in lengths {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let input_bits = alloc_input(&mut cs, &data);
+                let length =
+                    AllocatedNum::alloc(cs.namespace(|| "length"), || Ok(Fr::from(len as u64)))
+                        .unwrap();
+
+                let r = sha256_var(cs.namespace(|| "sha256"), &input_bits, &length).unwrap();
+                assert!(cs.is_satisfied());
+
+                let expected = Sha256::digest(&data[..len]);
+                let mut s = expected
+                    .iter()
+                    .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8));
+                for b in r {
+                    assert_eq!(b.get_value().unwrap(), s.next().unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sha256_var_length() {
+        let data = [0u8; 100];
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let input_bits = alloc_input(&mut cs, &data);
+        let length = AllocatedNum::alloc(cs.namespace(|| "length"), || Ok(Fr::from(101))).unwrap();
+        assert!(matches!(
+            sha256_var(cs.namespace(|| "sha256"), &input_bits, &length),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let input_bits = alloc_input(&mut cs, &data);
+        let length = AllocatedNum::alloc(cs.namespace(|| "length"), || Ok(Fr::from(60))).unwrap();
+        sha256_var(cs.namespace(|| "sha256"), &input_bits, &length).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 54457);
+
+        // the length must match the padding
+        cs.set("length/num", Fr::from(61));
+        assert!(!cs.is_satisfied());
+    }
 }
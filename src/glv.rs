@@ -0,0 +1,155 @@
+//! The GLV decomposition of scalar multiplications on the G1 group of BLS12-381.
+//!
+//! G1 has the efficiently computable endomorphism $\phi(x, y) = (\beta x, y)$, where $\beta$ is a
+//! cube root of unity in $F_p$. It acts on the group as the multiplication by
+//! $\lambda = z^2 - 1$, where $z$ is the BLS parameter of the curve. As $r = \lambda^2 + \lambda + 1$,
+//! any scalar $k < r$ can be written as $k = k_1 + k_2 \lambda$ with $k_1, k_2$ of at most 128 bits,
+//! so that $kP = k_1 P + k_2 \phi(P)$. A multiscalar multiplication of $n$ points with full length
+//! scalars becomes one of $2n$ points with half length scalars, which halves the number of
+//! windows, and so the number of doublings and bucket reductions.
+
+use std::any::Any;
+
+use blstrs::{Fp, G1Affine};
+use group::prime::PrimeCurveAffine;
+
+/// Number of bits of the scalars after the decomposition.
+pub const GLV_BITS: usize = 128;
+
+/// The absolute value of the BLS parameter $z = -\mathtt{0xd201000000010000}$.
+const Z: u64 = 0xd201_0000_0001_0000;
+
+/// The eigenvalue $\lambda = z^2 - 1$ of the endomorphism.
+pub const LAMBDA: u128 = (Z as u128) * (Z as u128) - 1;
+
+/// The cube root of unity $\beta$ such that $\phi(P) = \lambda P$, in Montgomery form.
+const BETA: [u64; 6] = [
+    0xcd03_c9e4_8671_f071,
+    0x5dab_2246_1fcd_a5d2,
+    0x5870_42af_d385_1b95,
+    0x8eb6_0ebe_01ba_cb9e,
+    0x03f9_7d6e_83d0_50d2,
+    0x18f0_2065_5463_8741,
+];
+
+/// Returns $\phi(P) = \lambda P$. The identity is mapped to itself.
+pub fn endomorphism(p: &G1Affine) -> G1Affine {
+    G1Affine::from_raw_unchecked(p.x() * Fp::from_raw_unchecked(BETA), p.y(), false)
+}
+
+/// Splits the scalar `k`, given as its canonical little-endian representation, into
+/// $(k_1, k_2)$ such that $k = k_1 + k_2 \lambda$ and $k_1 < \lambda$.
+pub fn decompose(k: &[u8]) -> (u128, u128) {
+    debug_assert_eq!(k.len(), 32);
+    let mut limbs = [0u64; 4];
+    for (limb, bytes) in limbs.iter_mut().zip(k.chunks(8)) {
+        *limb = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    // As $\lambda + 1 = z^2$, $k = a z^2 + b = a \lambda + (a + b)$ where $a$ and $b$ are
+    // obtained with two divisions by $z$, which fits into a limb.
+    let r1 = div_rem(&mut limbs, Z);
+    let r2 = div_rem(&mut limbs, Z);
+    let a = u128::from(limbs[0]) | (u128::from(limbs[1]) << 64);
+    let b = u128::from(r2) * u128::from(Z) + u128::from(r1);
+
+    // $a + b$ may exceed $\lambda$, or even 128 bits, in which case `carry` holds the top bit.
+    let (mut k1, mut carry) = a.overflowing_add(b);
+    let mut k2 = a;
+    while carry || k1 >= LAMBDA {
+        let (diff, borrow) = k1.overflowing_sub(LAMBDA);
+        k1 = diff;
+        carry &= !borrow;
+        k2 += 1;
+    }
+    (k1, k2)
+}
+
+/// Divides `limbs` in place by `d`, returning the remainder.
+fn div_rem(limbs: &mut [u64; 4], d: u64) -> u64 {
+    let mut rem = 0u128;
+    for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 64) | u128::from(*limb);
+        *limb = (cur / u128::from(d)) as u64;
+        rem = cur % u128::from(d);
+    }
+    rem as u64
+}
+
+/// The endomorphism of a group and the matching decomposition of the scalars. The multiscalar
+/// multiplications are generic over any group and use the GLV decomposition if `Glv::of` returns
+/// it for their group.
+#[derive(Clone, Copy)]
+pub struct Glv<G> {
+    /// Returns $\phi(P) = \lambda P$.
+    pub endomorphism: fn(&G) -> G,
+    /// Splits the scalar `k` into $(k_1, k_2)$ such that $k = k_1 + k_2 \lambda$, with both
+    /// halves of at most `GLV_BITS` bits.
+    pub decompose: fn(&[u8]) -> (u128, u128),
+}
+
+impl<G: PrimeCurveAffine> Glv<G> {
+    /// Returns the GLV decomposition of the group `G` if it has an endomorphism, which is the case
+    /// of BLS12-381 G1, and `None` otherwise.
+    pub fn of() -> Option<Self> {
+        let g1 = Glv::<G1Affine> {
+            endomorphism,
+            decompose,
+        };
+        (&g1 as &dyn Any).downcast_ref::<Self>().copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use blstrs::{G1Projective, G2Affine, Scalar as Fr};
+    use ff::{Field, PrimeField};
+    use group::{prime::PrimeCurveAffine, Curve, Group};
+    use rand_core::SeedableRng;
+
+    fn scalar(v: u128) -> Fr {
+        Fr::from_u128(v)
+    }
+
+    #[test]
+    fn test_endomorphism() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        for _ in 0..10 {
+            let p = G1Projective::random(&mut rng).to_affine();
+            assert_eq!(endomorphism(&p), (p * scalar(LAMBDA)).to_affine());
+        }
+        assert_eq!(endomorphism(&G1Affine::identity()), G1Affine::identity());
+    }
+
+    #[test]
+    fn test_decompose() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let lambda = scalar(LAMBDA);
+        let edge = [
+            Fr::ZERO,
+            Fr::ONE,
+            lambda,
+            lambda - Fr::ONE,
+            -Fr::ONE,
+            -lambda,
+        ];
+        let random = (0..1000).map(|_| Fr::random(&mut rng));
+        for k in edge.into_iter().chain(random) {
+            let (k1, k2) = decompose(k.to_repr().as_ref());
+            assert!(k1 < LAMBDA);
+            assert_eq!(scalar(k1) + scalar(k2) * lambda, k);
+        }
+    }
+
+    #[test]
+    fn test_glv_of() {
+        assert!(Glv::<G2Affine>::of().is_none());
+        let glv = Glv::<G1Affine>::of().expect("G1 has an endomorphism");
+        let p = G1Affine::generator();
+        assert_eq!((glv.endomorphism)(&p), endomorphism(&p));
+        let k = Fr::from(42u64).to_repr();
+        assert_eq!((glv.decompose)(k.as_ref()), decompose(k.as_ref()));
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "prover")]
 use crossbeam_channel::{bounded, Receiver, Sender};
 use ff::Field;
 use group::{Curve, Group};
@@ -11,6 +12,7 @@ use std::sync::{
     atomic::{AtomicBool, Ordering::SeqCst},
     Arc, Mutex,
 };
+#[cfg(feature = "prover")]
 use std::thread;
 
 /// Holds the logic for merging multiple pairing checks of the form
@@ -21,6 +23,9 @@ use std::thread;
 ///
 /// Into a compressed form where only one final exponentiation is required. All
 /// checks but up to one will be randomized.
+///
+/// The checks are merged on a separate thread. Without the `prover` feature no
+/// thread is spawned and the checks are merged directly by the caller.
 #[derive(Debug)]
 pub struct PairingChecks<E, R>
 where
@@ -29,8 +34,13 @@ where
 {
     /// Circuit breaker to allow canceling all checks and marking the whole check as failed.
     valid: Arc<AtomicBool>,
+    #[cfg(feature = "prover")]
     merge_send: Sender<Result<PairingCheck<E>, SynthesisError>>,
+    #[cfg(feature = "prover")]
     valid_recv: Receiver<Result<bool, SynthesisError>>,
+    /// The merged checks.
+    #[cfg(not(feature = "prover"))]
+    acc: Mutex<PairingCheck<E>>,
     /// Random number generator used for generating the random coefficients.
     rng: Mutex<R>,
     /// Ensures that the non randomized check is only added exactly once.
@@ -42,6 +52,7 @@ where
     E: MultiMillerLoop,
     R: RngCore + Send,
 {
+    #[cfg(feature = "prover")]
     #[allow(clippy::type_complexity)]
     pub fn new(rng: R) -> Self {
         let (merge_send, merge_recv): (
@@ -89,17 +100,34 @@ where
         }
     }
 
+    #[cfg(not(feature = "prover"))]
+    pub fn new(rng: R) -> Self {
+        PairingChecks {
+            valid: Arc::new(AtomicBool::new(true)),
+            acc: Mutex::new(PairingCheck::new()),
+            rng: Mutex::new(rng),
+            non_random_check_done: AtomicBool::new(false),
+        }
+    }
+
     /// Fails the whole check.
     pub fn invalidate(&self) {
         self.valid.store(false, SeqCst);
     }
 
+    #[cfg(feature = "prover")]
     pub fn report_err(&self, e: SynthesisError) {
         self.merge_send
             .send(Err(e))
             .expect("expect to send on channel");
     }
 
+    #[cfg(not(feature = "prover"))]
+    pub fn report_err(&self, _e: SynthesisError) {
+        // we signal an invalid proof - malformed for example
+        self.invalidate();
+    }
+
     fn merge_pair(
         &self,
         result: <E as MultiMillerLoop>::Result,
@@ -157,6 +185,25 @@ where
         );
     }
 
+    /// Same as `merge_miller_inputs` but for already prepared G2 elements. It
+    /// is used to randomize checks that would otherwise be merged with
+    /// `merge_nonrandom`, so that more than one of them can be merged.
+    pub fn merge_miller_inputs_prepared(
+        &self,
+        it: &[(&E::G1Affine, &E::G2Prepared)],
+        out: &<E as Engine>::Gt,
+    ) {
+        let must_randomize = self.non_random_check_done.load(SeqCst);
+        let coeff = {
+            let rng: &mut R = &mut self.rng.lock().unwrap();
+            derive_non_zero::<E, _>(rng)
+        };
+        self.merge(
+            PairingCheck::new_random_from_prepared_miller_inputs(coeff, it, out),
+            must_randomize,
+        );
+    }
+
     fn merge(&self, check: PairingCheck<E>, must_randomize: bool) {
         if !check.randomized {
             assert!(
@@ -166,6 +213,11 @@ where
             self.non_random_check_done.store(true, SeqCst);
         };
 
+        self.send(check);
+    }
+
+    #[cfg(feature = "prover")]
+    fn send(&self, check: PairingCheck<E>) {
         // This send is "best effort". If the verification in `verify_tipp_mipp()` identifies
         // an invalid aggregation, the `self.valid` is set to `false`. That terminates the thread
         // that receives those messages, hence also the receiving channel is closed.
@@ -177,6 +229,15 @@ where
         }
     }
 
+    #[cfg(not(feature = "prover"))]
+    fn send(&self, check: PairingCheck<E>) {
+        // only do work as long as we know we are still valid
+        if self.valid.load(SeqCst) {
+            self.acc.lock().unwrap().merge(&check);
+        }
+    }
+
+    #[cfg(feature = "prover")]
     pub fn verify(self) -> Result<bool, SynthesisError> {
         let Self {
             valid,
@@ -192,6 +253,14 @@ where
         }
         valid_recv.recv().unwrap()
     }
+
+    #[cfg(not(feature = "prover"))]
+    pub fn verify(self) -> Result<bool, SynthesisError> {
+        if !self.valid.load(SeqCst) {
+            return Ok(false);
+        }
+        Ok(self.acc.into_inner().unwrap().verify())
+    }
 }
 
 /// PairingCheck represents a check of the form e(A,B)e(C,D)... = T. Checks can
@@ -293,6 +362,35 @@ where
         }
     }
 
+    /// Same as `new_random_from_miller_inputs` but for already prepared G2
+    /// elements.
+    pub fn new_random_from_prepared_miller_inputs(
+        coeff: E::Fr,
+        it: &[(&E::G1Affine, &E::G2Prepared)],
+        out: &<E as Engine>::Gt,
+    ) -> PairingCheck<E> {
+        let miller_out = it
+            .into_par_iter()
+            .map(|(a, b)| {
+                let na = a.mul(coeff).to_affine();
+                E::multi_miller_loop(&[(&na, b)])
+            })
+            .reduce(<E as MultiMillerLoop>::Result::default, |acc, res| {
+                acc + res
+            });
+        let right = if out != &<E as Engine>::Gt::generator() {
+            *out * coeff
+        } else {
+            *out
+        };
+
+        PairingCheck {
+            left: miller_out,
+            right,
+            randomized: true,
+        }
+    }
+
     /// takes another pairing tuple and combine both sides together. Note the checks are not
     /// randomized when merged, the checks must have been randomized before.
     pub fn merge(&mut self, p2: &PairingCheck<E>) {
@@ -0,0 +1,184 @@
+use std::marker::PhantomData;
+
+use blstrs::Compress;
+use ff::PrimeField;
+use group::Group;
+use log::info;
+use pairing::{Engine, MultiMillerLoop};
+
+use super::{
+    commit, inner_product,
+    msm::variable_base::{MultiexpGroup, MultiexpKernels},
+    prove::aggregate_committed_proofs,
+    transcript::{Sha256Transcript, TranscriptProtocol},
+    AggregateProof, AggregateVersion, ProverSRS,
+};
+use crate::groth16::Proof;
+use bellpepper_core::SynthesisError;
+
+/// Number of proofs committed to at once by default, see `Aggregator::chunk_size`.
+const DEFAULT_CHUNK_SIZE: usize = 256;
+
+/// Aggregates proofs that arrive over time into the same `AggregateProof` as
+/// `aggregate_proofs_with_transcript`, called on all of them at once.
+///
+/// The commitments to the A, B and C elements of the proofs are linear in the
+/// proofs. They are computed on chunks of proofs, as soon as enough proofs are
+/// pushed to complete a chunk, so that only the last chunk and the rest of the
+/// aggregation remain to be done once all proofs arrived.
+///
+/// The SRS must be specialized for the number of proofs to aggregate, padded
+/// to the next power of two, like for `aggregate_proofs`.
+pub struct Aggregator<'a, E: Engine, T = Sha256Transcript<E>> {
+    srs: &'a ProverSRS<E>,
+    version: AggregateVersion,
+    chunk_size: usize,
+    a: Vec<E::G1Affine>,
+    b: Vec<E::G2Affine>,
+    c: Vec<E::G1Affine>,
+    /// Number of proofs that are part of the commitments already.
+    committed: usize,
+    com_ab: commit::Output<E>,
+    com_c: commit::Output<E>,
+    _transcript: PhantomData<T>,
+}
+
+impl<'a, E> Aggregator<'a, E>
+where
+    E: MultiMillerLoop,
+    Sha256Transcript<E>: TranscriptProtocol<E>,
+{
+    /// Creates an aggregator using the default SHA-256 transcript, like
+    /// `aggregate_proofs`.
+    pub fn new(srs: &'a ProverSRS<E>, version: AggregateVersion) -> Self {
+        Self::with_transcript(srs, version)
+    }
+}
+
+impl<'a, E, T> Aggregator<'a, E, T>
+where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+{
+    /// Creates an aggregator deriving the challenges with the transcript `T`.
+    pub fn with_transcript(srs: &'a ProverSRS<E>, version: AggregateVersion) -> Self {
+        Aggregator {
+            srs,
+            version,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            a: Vec::with_capacity(srs.n),
+            b: Vec::with_capacity(srs.n),
+            c: Vec::with_capacity(srs.n),
+            committed: 0,
+            com_ab: (E::Gt::identity(), E::Gt::identity()),
+            com_c: (E::Gt::identity(), E::Gt::identity()),
+            _transcript: PhantomData,
+        }
+    }
+
+    /// Sets the number of proofs committed to at once. A size of zero is
+    /// treated as one.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Returns the number of proofs pushed so far.
+    pub fn num_proofs(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Adds a proof to the aggregation. It fails if there are more proofs than
+    /// the SRS is specialized for.
+    pub fn push(&mut self, proof: &Proof<E>) -> Result<(), SynthesisError> {
+        if self.a.len() == self.srs.n {
+            return Err(SynthesisError::MalformedSrs);
+        }
+        self.a.push(proof.a);
+        self.b.push(proof.b);
+        self.c.push(proof.c);
+
+        if self.a.len() - self.committed >= self.chunk_size {
+            self.commit_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Aggregates the pushed proofs. The same assumptions on
+    /// `transcript_include` as for `aggregate_proofs` apply.
+    pub fn finish(mut self, transcript_include: &[u8]) -> Result<AggregateProof<E>, SynthesisError>
+    where
+        E: std::fmt::Debug,
+        <E::Fr as PrimeField>::Repr: Send + Sync,
+        <E as Engine>::Gt: Compress,
+        E::G1Affine: MultiexpGroup,
+        E::G2Affine: MultiexpGroup,
+    {
+        info!("aggregate_proofs [version {}]", self.version);
+        self.version.check_unbound()?;
+        let num_proofs = self.a.len();
+        if num_proofs < 2 {
+            return Err(SynthesisError::MalformedProofs(
+                "aggregating less than 2 proofs is not allowed".to_string(),
+            ));
+        }
+        let padded_len = num_proofs.next_power_of_two();
+        if !self.srs.has_correct_len(padded_len) {
+            return Err(SynthesisError::MalformedSrs);
+        }
+
+        // Pad by repeating the last proof, like `aggregate_proofs`.
+        let (last_a, last_b, last_c) = (
+            self.a[num_proofs - 1],
+            self.b[num_proofs - 1],
+            self.c[num_proofs - 1],
+        );
+        self.a.resize(padded_len, last_a);
+        self.b.resize(padded_len, last_b);
+        self.c.resize(padded_len, last_c);
+        self.commit_pending()?;
+
+        aggregate_committed_proofs::<E, T>(
+            self.srs,
+            transcript_include,
+            num_proofs,
+            self.a,
+            self.b,
+            self.c,
+            self.com_ab,
+            self.com_c,
+            self.version,
+            &MultiexpKernels::new(),
+        )
+    }
+
+    /// Adds the proofs that are not committed to yet to the commitments. This is
+    /// `commit::pair` and `commit::single_g1` restricted to these proofs and the
+    /// corresponding parts of the commitment keys.
+    fn commit_pending(&mut self) -> Result<(), SynthesisError> {
+        let range = self.committed..self.a.len();
+        let (vkey, wkey) = (&self.srs.vkey, &self.srs.wkey);
+        let (a, b, c) = (
+            &self.a[range.clone()],
+            &self.b[range.clone()],
+            &self.c[range.clone()],
+        );
+        let (va, vb) = (&vkey.a[range.clone()], &vkey.b[range.clone()]);
+        let (wa, wb) = (&wkey.a[range.clone()], &wkey.b[range]);
+        try_par! {
+            let t1 = inner_product::pairing::<E>(a, va),
+            let t2 = inner_product::pairing::<E>(wa, b),
+            let u1 = inner_product::pairing::<E>(a, vb),
+            let u2 = inner_product::pairing::<E>(wb, b),
+            let tc = inner_product::pairing::<E>(c, va),
+            let uc = inner_product::pairing::<E>(c, vb)
+        };
+
+        self.com_ab.0 += t1 + t2;
+        self.com_ab.1 += u1 + u2;
+        self.com_c.0 += tc;
+        self.com_c.1 += uc;
+        self.committed = self.a.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use ff::Field;
+use serde::{Deserialize, Serialize};
+
+use bellpepper_core::SynthesisError;
+
+/// A relation between the public inputs of proofs aggregated with
+/// `aggregate_proofs_and_linked_instances`: the input `to` of proof `i + offset`
+/// equals the input `from` of proof `i`, for every proof `i` for which proof
+/// `i + offset` exists.
+///
+/// Links can describe chained state transitions of any shape, e.g. the outputs of
+/// a proof being the inputs of the next one (see `InstanceLink::chain`), inputs
+/// that are shared by all proofs (see `InstanceLink::shared`), or inputs that are
+/// equal within each proof (an `offset` of zero).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceLink {
+    pub from: usize,
+    pub to: usize,
+    pub offset: usize,
+}
+
+impl InstanceLink {
+    pub fn new(from: usize, to: usize, offset: usize) -> Self {
+        InstanceLink { from, to, offset }
+    }
+
+    /// The input is the same for all proofs.
+    pub fn shared(input: usize) -> Self {
+        InstanceLink::new(input, input, 1)
+    }
+
+    /// The relation used by `aggregate_proofs_and_instances`: the second half of
+    /// the `num_inputs` public inputs of each proof equals the first half of the
+    /// public inputs of the next proof.
+    pub fn chain(num_inputs: usize) -> Vec<Self> {
+        let half = num_inputs / 2;
+        (0..half)
+            .map(|j| InstanceLink::new(half + j, j, 1))
+            .collect()
+    }
+
+    /// Evaluates the polynomial of the public inputs `to` at `r`, given the
+    /// evaluation of the polynomial of the public inputs `from`. The polynomial of
+    /// the public inputs `j` is $\sum_i s_i[j] X^i$, where $s_i$ is the statement
+    /// of proof `i`. `value(i, j)` returns $s_i[j]$ for the boundary inputs of the
+    /// link.
+    pub(super) fn eval_to<F: Field>(
+        &self,
+        from_eval: F,
+        r: &F,
+        num_proofs: usize,
+        value: impl Fn(usize, usize) -> F,
+    ) -> F {
+        let (low, r_offset) = self.low(r, &value);
+        let (high, r_shift) = self.high(r, num_proofs, &value);
+        low + r_offset * (from_eval - r_shift * high)
+    }
+
+    /// Evaluates the polynomial of the public inputs `from` at `r`, given the
+    /// evaluation of the polynomial of the public inputs `to`, see `eval_to`.
+    pub(super) fn eval_from<F: Field>(
+        &self,
+        to_eval: F,
+        r: &F,
+        num_proofs: usize,
+        value: impl Fn(usize, usize) -> F,
+    ) -> F {
+        let (low, r_offset) = self.low(r, &value);
+        let (high, r_shift) = self.high(r, num_proofs, &value);
+        (to_eval - low) * r_offset.invert().unwrap() + r_shift * high
+    }
+
+    /// Returns the polynomial of the first `offset` inputs `to`, which are not
+    /// linked, evaluated at `r`, and `r^offset`.
+    fn low<F: Field>(&self, r: &F, value: impl Fn(usize, usize) -> F) -> (F, F) {
+        let mut low = F::ZERO;
+        let mut r_offset = F::ONE;
+        for i in 0..self.offset {
+            low += value(i, self.to) * r_offset;
+            r_offset *= r;
+        }
+        (low, r_offset)
+    }
+
+    /// Returns the polynomial of the last `offset` inputs `from`, which are not
+    /// linked, shifted to degree zero and evaluated at `r`, and
+    /// `r^(num_proofs - offset)`.
+    fn high<F: Field>(
+        &self,
+        r: &F,
+        num_proofs: usize,
+        value: impl Fn(usize, usize) -> F,
+    ) -> (F, F) {
+        let mut high = F::ZERO;
+        let mut r_pow = F::ONE;
+        for i in num_proofs - self.offset..num_proofs {
+            high += value(i, self.from) * r_pow;
+            r_pow *= r;
+        }
+        (high, r.pow_vartime([(num_proofs - self.offset) as u64]))
+    }
+}
+
+/// Returns the public inputs the verifier of an aggregation with linked
+/// instances needs to know, as `(proof, input)` pairs, in the order expected by
+/// `verify_aggregate_proof_and_linked_instances`. These are the inputs `to` of
+/// the first `offset` proofs and the inputs `from` of the last `offset` proofs of
+/// every link, all other linked inputs follow from them.
+pub fn linked_instance_boundary(links: &[InstanceLink], num_proofs: usize) -> Vec<(usize, usize)> {
+    let mut boundary = Vec::new();
+    for link in links {
+        let offset = link.offset.min(num_proofs);
+        boundary.extend((0..offset).map(|i| (i, link.to)));
+        boundary.extend((num_proofs - offset..num_proofs).map(|i| (i, link.from)));
+    }
+    boundary.sort_unstable();
+    boundary.dedup();
+    boundary
+}
+
+/// Describes how the evaluations of the polynomials of all public inputs are
+/// obtained by the verifier. The polynomials of the `committed` inputs are
+/// committed to and opened by the prover, together with a bound on their degree.
+/// The polynomials of the inputs `from` of the `derived` links are derived from
+/// the committed polynomials of their inputs `to`, whose first `shifts`
+/// coefficients are proven to be the boundary values. The remaining links are
+/// checked on the evaluations.
+#[derive(Debug)]
+pub(super) struct LinkPlan {
+    pub committed: Vec<usize>,
+    pub shifts: Vec<usize>,
+    pub derived: Vec<InstanceLink>,
+    pub checked: Vec<InstanceLink>,
+    pub boundary: Vec<(usize, usize)>,
+}
+
+impl LinkPlan {
+    pub fn new(
+        links: &[InstanceLink],
+        num_inputs: usize,
+        num_proofs: usize,
+    ) -> Result<Self, SynthesisError> {
+        for link in links {
+            if link.from >= num_inputs || link.to >= num_inputs {
+                return Err(SynthesisError::MalformedProofs(
+                    "instance link input out of range".to_string(),
+                ));
+            }
+            if link.offset >= num_proofs {
+                return Err(SynthesisError::MalformedProofs(
+                    "instance link offset out of range".to_string(),
+                ));
+            }
+            if link.offset == 0 && link.from == link.to {
+                return Err(SynthesisError::MalformedProofs(
+                    "instance link of an input to itself".to_string(),
+                ));
+            }
+        }
+
+        let mut is_committed = vec![false; num_inputs];
+        let mut known = vec![false; num_inputs];
+        let mut used = vec![false; links.len()];
+        let mut derived = Vec::new();
+
+        // Inputs that are not the source of a link to another input are committed.
+        for (j, (is_committed, known)) in is_committed.iter_mut().zip(known.iter_mut()).enumerate()
+        {
+            if !links.iter().any(|l| l.from == j && l.to != j) {
+                *is_committed = true;
+                *known = true;
+            }
+        }
+
+        loop {
+            // Derive the inputs linked to a committed input.
+            for (link, used) in links.iter().zip(used.iter_mut()) {
+                if is_committed[link.to] && !known[link.from] {
+                    known[link.from] = true;
+                    *used = true;
+                    derived.push(*link);
+                }
+            }
+
+            // The remaining inputs are linked in cycles or only to derived
+            // inputs, one of them is committed.
+            match known.iter().position(|k| !k) {
+                Some(j) => {
+                    is_committed[j] = true;
+                    known[j] = true;
+                }
+                None => break,
+            }
+        }
+
+        let committed: Vec<usize> = (0..num_inputs).filter(|&j| is_committed[j]).collect();
+        let shifts = committed
+            .iter()
+            .map(|&j| {
+                derived
+                    .iter()
+                    .filter(|l| l.to == j)
+                    .map(|l| l.offset)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let checked = links
+            .iter()
+            .zip(used.iter())
+            .filter(|(_, used)| !**used)
+            .map(|(link, _)| *link)
+            .collect();
+
+        Ok(LinkPlan {
+            committed,
+            shifts,
+            derived,
+            checked,
+            boundary: linked_instance_boundary(links, num_proofs),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_link_plan() {
+        let plan = LinkPlan::new(&InstanceLink::chain(4), 4, 8).unwrap();
+        assert_eq!(plan.committed, vec![0, 1]);
+        assert_eq!(plan.shifts, vec![1, 1]);
+        assert_eq!(plan.derived, InstanceLink::chain(4));
+        assert!(plan.checked.is_empty());
+        assert_eq!(plan.boundary, vec![(0, 0), (0, 1), (7, 2), (7, 3)]);
+
+        // a cycle of links and a shared input
+        let links = [
+            InstanceLink::new(0, 1, 1),
+            InstanceLink::new(1, 0, 0),
+            InstanceLink::shared(2),
+        ];
+        let plan = LinkPlan::new(&links, 3, 4).unwrap();
+        assert_eq!(plan.committed, vec![0, 2]);
+        assert_eq!(plan.shifts, vec![0, 0]);
+        assert_eq!(plan.derived, vec![links[1]]);
+        assert_eq!(plan.checked, vec![links[0], links[2]]);
+
+        // inputs are only derived from committed inputs
+        let links = [InstanceLink::new(0, 1, 2), InstanceLink::new(1, 2, 1)];
+        let plan = LinkPlan::new(&links, 3, 4).unwrap();
+        assert_eq!(plan.committed, vec![0, 2]);
+        assert_eq!(plan.shifts, vec![0, 1]);
+        assert_eq!(plan.derived, vec![links[1]]);
+        assert_eq!(plan.checked, vec![links[0]]);
+
+        assert!(LinkPlan::new(&[InstanceLink::new(0, 3, 1)], 3, 4).is_err());
+        assert!(LinkPlan::new(&[InstanceLink::new(0, 1, 4)], 3, 4).is_err());
+        assert!(LinkPlan::new(&[InstanceLink::new(1, 1, 0)], 3, 4).is_err());
+    }
+
+    #[test]
+    fn test_link_eval() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let num_proofs = 8;
+        let r = Fr::random(&mut rng);
+        let eval = |column: &[Fr]| -> Fr {
+            column
+                .iter()
+                .rev()
+                .fold(Fr::ZERO, |acc, coeff| acc * r + coeff)
+        };
+
+        for offset in 0..num_proofs {
+            let link = InstanceLink::new(0, 1, offset);
+            let from: Vec<Fr> = (0..num_proofs).map(|_| Fr::random(&mut rng)).collect();
+            let mut to: Vec<Fr> = (0..num_proofs).map(|_| Fr::random(&mut rng)).collect();
+            to[offset..].copy_from_slice(&from[..num_proofs - offset]);
+
+            let value = |i: usize, j: usize| if j == 0 { from[i] } else { to[i] };
+            assert_eq!(link.eval_to(eval(&from), &r, num_proofs, value), eval(&to));
+            assert_eq!(
+                link.eval_from(eval(&to), &r, num_proofs, value),
+                eval(&from)
+            );
+        }
+    }
+}
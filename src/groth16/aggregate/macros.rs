@@ -1,3 +1,4 @@
+#[cfg(feature = "prover")]
 macro_rules! try_par {
     ($(let $name:ident = $f:expr),+) => {
         $(
@@ -16,6 +17,7 @@ macro_rules! try_par {
     };
 }
 
+#[cfg(feature = "prover")]
 macro_rules! par {
     ($(let $name:ident = $f:expr),+) => {
         $(
@@ -54,3 +56,28 @@ macro_rules! par {
         )+
     }
 }
+
+// Without threads, the expressions are simply evaluated one after another.
+#[cfg(not(feature = "prover"))]
+macro_rules! try_par {
+    ($(let $name:ident = $f:expr),+) => {
+        $(
+            let $name = $f?;
+        )+
+    };
+}
+
+#[cfg(not(feature = "prover"))]
+macro_rules! par {
+    ($(let $name:ident = $f:expr),+) => {
+        $(
+            let $name = $f;
+        )+
+    };
+
+    ($(let ($name1:ident, $name2:ident) = $f:block),+) => {
+        $(
+            let ($name1, $name2) = $f;
+        )+
+    }
+}
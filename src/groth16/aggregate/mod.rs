@@ -5,30 +5,61 @@ use ff::Field;
 use group::{prime::PrimeCurveAffine, Curve};
 use rayon::prelude::*;
 
+use bellpepper_core::SynthesisError;
+
 #[macro_use]
 mod macros;
 
 mod accumulator;
+mod aggregator;
 mod commit;
 mod inner_product;
+mod link;
 mod msm;
 mod poly;
+mod powers_of_tau;
 mod proof;
 mod prove;
 mod srs;
 mod transcript;
 mod verify;
 
+pub use self::aggregator::*;
 pub use self::commit::*;
+pub use self::link::*;
+pub use self::msm::variable_base::MultiexpGroup;
+pub use self::powers_of_tau::*;
 pub use self::proof::*;
 pub use self::prove::*;
 pub use self::srs::*;
+pub use self::transcript::{
+    Challenge, Keccak256Transcript, Sha256Transcript, Transcript, TranscriptProtocol,
+};
 pub use self::verify::*;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum AggregateVersion {
     V1,
     V2,
+    /// The protocol of `V2`, with the aggregated proof bound to the statement it
+    /// proves: the verifying key, the generic SRS, the number of proofs and the
+    /// public inputs. See `aggregate_proofs_with_statement`.
+    V3,
+}
+
+impl AggregateVersion {
+    /// Returns an error for `V3`, which can only be used with the functions that
+    /// bind the aggregated proof to its statement.
+    fn check_unbound(self) -> Result<(), SynthesisError> {
+        match self {
+            AggregateVersion::V1 | AggregateVersion::V2 => Ok(()),
+            AggregateVersion::V3 => Err(SynthesisError::MalformedProofs(
+                "AggregateVersion::V3 requires binding the statement, see \
+                 aggregate_proofs_with_statement"
+                    .to_string(),
+            )),
+        }
+    }
 }
 
 impl Display for AggregateVersion {
@@ -36,6 +67,7 @@ impl Display for AggregateVersion {
         match self {
             AggregateVersion::V1 => write!(f, "V1"),
             AggregateVersion::V2 => write!(f, "V2"),
+            AggregateVersion::V3 => write!(f, "V3"),
         }
     }
 }
@@ -1 +1,2 @@
 pub mod fixed_base;
+pub mod variable_base;
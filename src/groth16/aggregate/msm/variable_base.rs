@@ -0,0 +1,279 @@
+use ff::PrimeField;
+use group::prime::PrimeCurveAffine;
+
+use crate::groth16::{aggregate::inner_product, multiscalar::*};
+use bellpepper_core::SynthesisError;
+
+/// Multiexponentiations with fewer terms are always run on the CPU, as they
+/// don't make up for the cost of setting up the GPU kernel.
+pub const GPU_MULTIEXP_THRESHOLD: usize = 1 << 10;
+
+/// The groups the aggregation computes multiexponentiations in. With GPU
+/// support, they run on the GPU, which requires the kernels of the group, see
+/// `GpuName`. Without it, this is any group.
+#[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+pub trait MultiexpGroup: PrimeCurveAffine + crate::gpu::GpuName {}
+
+#[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+impl<G: PrimeCurveAffine + crate::gpu::GpuName> MultiexpGroup for G {}
+
+/// The groups the aggregation computes multiexponentiations in. With GPU
+/// support, they run on the GPU, which requires the kernels of the group, see
+/// `GpuName`. Without it, this is any group.
+#[cfg(not(all(any(feature = "cuda", feature = "opencl"), feature = "prover")))]
+pub trait MultiexpGroup: PrimeCurveAffine {}
+
+#[cfg(not(all(any(feature = "cuda", feature = "opencl"), feature = "prover")))]
+impl<G: PrimeCurveAffine> MultiexpGroup for G {}
+
+/// The multiexponentiation kernels of an aggregation, one per group. They are
+/// created once per aggregation and passed down to its multiexponentiations,
+/// like the kernels of the Groth16 prover.
+///
+/// A kernel holds the GPU lock once it ran a multiexponentiation, so only one
+/// of them can be in use: getting the kernel of a group frees the kernel of the
+/// other one.
+pub struct MultiexpKernels<G1: MultiexpGroup, G2: MultiexpGroup> {
+    g1: MultiexpKernel<G1>,
+    g2: MultiexpKernel<G2>,
+}
+
+impl<G1: MultiexpGroup, G2: MultiexpGroup> MultiexpKernels<G1, G2> {
+    pub fn new() -> Self {
+        MultiexpKernels {
+            g1: MultiexpKernel::new(),
+            g2: MultiexpKernel::new(),
+        }
+    }
+
+    /// Returns the kernel of the first group, freeing the one of the second.
+    pub fn g1(&self) -> &MultiexpKernel<G1> {
+        self.g2.free();
+        &self.g1
+    }
+
+    /// Returns the kernel of the second group, freeing the one of the first.
+    pub fn g2(&self) -> &MultiexpKernel<G2> {
+        self.g1.free();
+        &self.g2
+    }
+}
+
+/// The multiexponentiation kernel of the group `G`, see `MultiexpKernels`.
+/// Concurrent multiexponentiations, like the ones of GIPA, wait for each other
+/// on the kernel instead of running in parallel on the GPU.
+pub struct MultiexpKernel<G: MultiexpGroup> {
+    #[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+    kern: std::sync::Mutex<crate::gpu::LockedMultiexpKernel<'static, G>>,
+    #[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+    worker: ec_gpu_gen::threadpool::Worker,
+    _group: std::marker::PhantomData<G>,
+}
+
+impl<G: MultiexpGroup> MultiexpKernel<G> {
+    fn new() -> Self {
+        MultiexpKernel {
+            #[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+            kern: std::sync::Mutex::new(crate::gpu::LockedMultiexpKernel::new(false)),
+            #[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+            worker: ec_gpu_gen::threadpool::Worker::new(),
+            _group: std::marker::PhantomData,
+        }
+    }
+
+    /// Frees the GPU resources of the kernel, including the GPU lock. The kernel
+    /// is initialized again by the next multiexponentiation on the GPU.
+    fn free(&self) {
+        #[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+        {
+            *self.kern.lock().unwrap() = crate::gpu::LockedMultiexpKernel::new(false);
+        }
+    }
+
+    /// Runs the multiexponentiation on the GPU, as a low priority computation
+    /// that gives way to processes holding the `PriorityLock`. Returns `None`
+    /// if `use_gpu` is false, and logs a warning and returns `None` if no GPU
+    /// is available or if the computation fails.
+    #[cfg(all(any(feature = "cuda", feature = "opencl"), feature = "prover"))]
+    fn gpu_multiexp<B, S>(&self, use_gpu: bool, bases: B, scalars: S) -> Option<G::Curve>
+    where
+        B: FnOnce() -> Vec<G>,
+        S: FnOnce() -> Vec<<G::Scalar as PrimeField>::Repr>,
+    {
+        use std::sync::Arc;
+
+        use log::warn;
+
+        use crate::gpu::CpuGpuMultiexpKernel;
+
+        if !use_gpu {
+            return None;
+        }
+
+        let bases = Arc::new(bases());
+        let exponents = Arc::new(scalars());
+        let mut kern = self.kern.lock().unwrap();
+        kern.with(|k: &mut CpuGpuMultiexpKernel<G>| {
+            k.multiexp(&self.worker, bases.clone(), exponents.clone(), 0)
+                .map_err(Into::into)
+        })
+        .map_err(|e| warn!("GPU multiexp failed, falling back to the CPU: {}", e))
+        .ok()
+    }
+
+    #[cfg(not(all(any(feature = "cuda", feature = "opencl"), feature = "prover")))]
+    fn gpu_multiexp<B, S>(&self, _use_gpu: bool, _bases: B, _scalars: S) -> Option<G::Curve>
+    where
+        B: FnOnce() -> Vec<G>,
+        S: FnOnce() -> Vec<<G::Scalar as PrimeField>::Repr>,
+    {
+        None
+    }
+}
+
+/// Returns $\prod_i g_i^{s_i}$ where the $g_i$ are the points of the
+/// precomputed `table` and the $s_i$ the `scalars`. It runs on the GPU with the
+/// kernel `kern` if one is available, and on the CPU, using the precomputed
+/// tables, otherwise.
+pub fn multiexp_table<G, F>(
+    kern: &MultiexpKernel<G>,
+    table: &dyn MultiscalarPrecomp<G>,
+    scalars: &ScalarList<'_, G, F>,
+) -> G::Curve
+where
+    G: MultiexpGroup,
+    F: Fn(usize) -> <G::Scalar as PrimeField>::Repr + Sync,
+{
+    let len = scalars.len();
+    let gpu = kern.gpu_multiexp(
+        len >= GPU_MULTIEXP_THRESHOLD,
+        || (0..len).map(|i| table.table(i)[0]).collect(),
+        || match scalars {
+            ScalarList::Slice(s) => s.to_vec(),
+            ScalarList::Getter(getter, _) => (0..len).map(getter).collect(),
+        },
+    );
+    gpu.unwrap_or_else(|| {
+        par_multiscalar::<_, G>(
+            scalars,
+            table,
+            std::mem::size_of::<<G::Scalar as PrimeField>::Repr>() * 8,
+        )
+    })
+}
+
+/// Returns $\prod_i left_i^{right_i}$ like `inner_product::multiexponentiation`,
+/// running on the GPU with the kernel `kern` if one is available.
+pub fn multiexp<G>(
+    kern: &MultiexpKernel<G>,
+    left: &[G],
+    right: &[G::Scalar],
+) -> Result<G::Curve, SynthesisError>
+where
+    G: MultiexpGroup,
+    <G::Scalar as PrimeField>::Repr: Sync,
+{
+    if left.len() != right.len() {
+        return Err(SynthesisError::IncompatibleLengthVector(
+            "multiexponentiation left and right".to_string(),
+        ));
+    }
+
+    let gpu = kern.gpu_multiexp(
+        left.len() >= GPU_MULTIEXP_THRESHOLD,
+        || left.to_vec(),
+        || right.iter().map(|s| s.to_repr()).collect(),
+    );
+    match gpu {
+        Some(res) => Ok(res),
+        None => inner_product::multiexponentiation(left, right),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blstrs::{G1Affine, G1Projective, G2Affine, G2Projective, Scalar as Fr};
+    use ff::Field;
+    use group::{Curve, Group};
+    use rand_core::SeedableRng;
+
+    fn multiexp_naive<G: PrimeCurveAffine>(bases: &[G], scalars: &[G::Scalar]) -> G::Curve {
+        bases
+            .iter()
+            .zip(scalars.iter())
+            .fold(G::Curve::identity(), |acc, (b, s)| acc + *b * s)
+    }
+
+    #[test]
+    fn test_multiexp() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        for len in [3, GPU_MULTIEXP_THRESHOLD + 1] {
+            let scalars: Vec<Fr> = (0..len).map(|_| Fr::random(&mut rng)).collect();
+            let reprs: Vec<_> = scalars.iter().map(|s| s.to_repr()).collect();
+            let g1: Vec<G1Affine> = (0..len)
+                .map(|_| G1Projective::random(&mut rng).to_affine())
+                .collect();
+            let g2: Vec<G2Affine> = (0..len)
+                .map(|_| G2Projective::random(&mut rng).to_affine())
+                .collect();
+            let expected_g1 = multiexp_naive(&g1, &scalars);
+            let expected_g2 = multiexp_naive(&g2, &scalars);
+
+            let kernels = MultiexpKernels::<G1Affine, G2Affine>::new();
+            assert_eq!(multiexp(kernels.g1(), &g1, &scalars).unwrap(), expected_g1);
+            assert_eq!(multiexp(kernels.g2(), &g2, &scalars).unwrap(), expected_g2);
+
+            // the table holds more points than there are scalars
+            let table = precompute_fixed_window(&g1, WINDOW_SIZE);
+            let getter = |i: usize| reprs[i];
+            assert_eq!(
+                multiexp_table::<G1Affine, _>(
+                    kernels.g1(),
+                    &table,
+                    &ScalarList::Getter(getter, len - 1)
+                ),
+                multiexp_naive(&g1[..len - 1], &scalars[..len - 1])
+            );
+            let table = precompute_fixed_window(&g2, WINDOW_SIZE);
+            assert_eq!(
+                multiexp_table::<G2Affine, fn(usize) -> _>(
+                    kernels.g2(),
+                    &table,
+                    &ScalarList::Slice(&reprs)
+                ),
+                expected_g2
+            );
+        }
+
+        let kernels = MultiexpKernels::<G1Affine, G2Affine>::new();
+        assert!(multiexp(kernels.g1(), &[G1Affine::generator()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_multiexp_concurrent() {
+        use rayon::prelude::*;
+
+        // Concurrent calls, like the ones of GIPA, wait for each other on the kernel.
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let len = GPU_MULTIEXP_THRESHOLD + 1;
+        let inputs: Vec<(Vec<G1Affine>, Vec<Fr>)> = (0..4)
+            .map(|_| {
+                let bases = (0..len)
+                    .map(|_| G1Projective::random(&mut rng).to_affine())
+                    .collect();
+                let scalars = (0..len).map(|_| Fr::random(&mut rng)).collect();
+                (bases, scalars)
+            })
+            .collect();
+
+        let kernels = MultiexpKernels::<G1Affine, G2Affine>::new();
+        let results: Vec<_> = inputs
+            .par_iter()
+            .map(|(bases, scalars)| multiexp(kernels.g1(), bases, scalars).unwrap())
+            .collect();
+        for ((bases, scalars), result) in inputs.iter().zip(results) {
+            assert_eq!(result, multiexp_naive(bases, scalars));
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Assembles a [`GenericSRS`] from the transcripts of two distinct powers of tau ceremonies, e.g.
+//! the ones of Zcash and Filecoin. The powers of tau of the first ceremony are used as the powers
+//! of alpha, the ones of the second ceremony as the powers of beta.
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom};
+
+use ff::PrimeField;
+use group::{prime::PrimeCurveAffine, Curve, Group, GroupEncoding, UncompressedEncoding};
+use pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+use rayon::prelude::*;
+
+use super::{GenericSRS, MAX_SRS_SIZE};
+use crate::groth16::multiscalar::{par_multiscalar_generic, precompute_fixed_window, ScalarList};
+
+/// Size of the hash of the previous contribution at the start of a transcript.
+const HASH_SIZE: usize = 64;
+
+/// Layout of a phase-1 transcript as written by the `powersoftau` software used by the Zcash and
+/// Filecoin ceremonies. It starts with a hash, followed by the $2^{power+1} - 1$ powers of tau
+/// in G1 and the $2^{power}$ powers of tau in G2. The elements following them are not needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowersOfTauFormat {
+    /// The log2 of the number of powers of tau in G2.
+    pub power: usize,
+    /// Whether the points are stored in compressed form (responses) or uncompressed form
+    /// (challenges).
+    pub compressed: bool,
+}
+
+impl PowersOfTauFormat {
+    /// Number of powers of tau in G1.
+    fn g1_len(&self) -> usize {
+        (1 << (self.power + 1)) - 1
+    }
+
+    /// Number of powers of tau in G2.
+    fn g2_len(&self) -> usize {
+        1 << self.power
+    }
+
+    fn point_size<G>(&self) -> usize
+    where
+        G: GroupEncoding + UncompressedEncoding,
+    {
+        if self.compressed {
+            <G as GroupEncoding>::Repr::default().as_ref().len()
+        } else {
+            <G as UncompressedEncoding>::Uncompressed::default()
+                .as_ref()
+                .len()
+        }
+    }
+
+    fn read_points<G, R>(&self, reader: &mut R, num_points: usize) -> io::Result<Vec<G>>
+    where
+        G: PrimeCurveAffine + UncompressedEncoding,
+        R: Read,
+    {
+        let point_size = self.point_size::<G>();
+        let mut data = vec![0u8; num_points * point_size];
+        reader.read_exact(&mut data)?;
+
+        data.par_chunks(point_size)
+            .map(|enc| {
+                let opt: Option<G> = if self.compressed {
+                    let mut repr = <G as GroupEncoding>::Repr::default();
+                    repr.as_mut().copy_from_slice(enc);
+                    G::from_bytes(&repr).into()
+                } else {
+                    let mut repr = <G as UncompressedEncoding>::Uncompressed::default();
+                    repr.as_mut().copy_from_slice(enc);
+                    G::from_uncompressed(&repr).into()
+                };
+                opt.ok_or_else(|| Error::new(ErrorKind::InvalidData, "not on curve"))
+            })
+            .collect()
+    }
+
+    /// Reads the first `num_powers` powers of tau in G1 and in G2 from the transcript.
+    pub fn read<E, R>(&self, reader: &mut R, num_powers: usize) -> io::Result<PowersOfTau<E>>
+    where
+        E: Engine,
+        E::G1Affine: UncompressedEncoding,
+        E::G2Affine: UncompressedEncoding,
+        R: Read + Seek,
+    {
+        if num_powers > self.g2_len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "transcript only contains {} powers, {} requested",
+                    self.g2_len(),
+                    num_powers
+                ),
+            ));
+        }
+
+        reader.seek(SeekFrom::Start(HASH_SIZE as u64))?;
+        let g1 = self.read_points::<E::G1Affine, _>(reader, num_powers)?;
+
+        let g2_offset = HASH_SIZE + self.g1_len() * self.point_size::<E::G1Affine>();
+        reader.seek(SeekFrom::Start(g2_offset as u64))?;
+        let g2 = self.read_points::<E::G2Affine, _>(reader, num_powers)?;
+
+        Ok(PowersOfTau { g1, g2 })
+    }
+}
+
+/// The powers of tau in G1 and G2 of a ceremony, $\{g^{\tau^i}\}$ and $\{h^{\tau^i}\}$.
+#[derive(Clone, Debug)]
+pub struct PowersOfTau<E: Engine> {
+    pub g1: Vec<E::G1Affine>,
+    pub g2: Vec<E::G2Affine>,
+}
+
+impl<E> PowersOfTau<E>
+where
+    E: MultiMillerLoop,
+{
+    /// Checks that the powers start at the generators and that they are powers of the same tau,
+    /// using a random linear combination of the pairing checks
+    ///
+    /// $$
+    /// e(g^{\tau^{i+1}}, h) = e(g^{\tau^i}, h^\tau) \quad e(g, h^{\tau^{i+1}}) = e(g^\tau, h^{\tau^i})
+    /// $$
+    pub fn verify<R: rand_core::RngCore>(&self, rng: &mut R) -> bool {
+        let n = self.g1.len();
+        if n < 2 || self.g2.len() != n {
+            return false;
+        }
+        if self.g1[0] != E::G1Affine::generator() || self.g2[0] != E::G2Affine::generator() {
+            return false;
+        }
+        if bool::from(self.g1[1].is_identity()) || bool::from(self.g2[1].is_identity()) {
+            return false;
+        }
+
+        let rho: Vec<E::Fr> = (0..n - 1).map(|_| random_scalar::<E, _>(rng)).collect();
+        let sigma: Vec<E::Fr> = (0..n - 1).map(|_| random_scalar::<E, _>(rng)).collect();
+        let rho = &rho;
+        let sigma = &sigma;
+        par! {
+            let g1_high = random_combination(&self.g1[1..], rho),
+            let g1_low = random_combination(&self.g1[..n - 1], rho),
+            let g2_high = random_combination(&self.g2[1..], sigma),
+            let g2_low = random_combination(&self.g2[..n - 1], sigma)
+        };
+
+        let g1_low = (-g1_low).to_affine();
+        let g1_high = g1_high.to_affine();
+        let g2_low = g2_low.to_affine();
+        let g2_high = g2_high.to_affine();
+        let neg_g_tau = (-self.g1[1].to_curve()).to_affine();
+        let h = E::G2Prepared::from(self.g2[0]);
+        let h_tau = E::G2Prepared::from(self.g2[1]);
+        let g2_high = E::G2Prepared::from(g2_high);
+        let g2_low = E::G2Prepared::from(g2_low);
+
+        E::multi_miller_loop(&[
+            (&g1_high, &h),
+            (&g1_low, &h_tau),
+            (&self.g1[0], &g2_high),
+            (&neg_g_tau, &g2_low),
+        ])
+        .final_exponentiation()
+            == E::Gt::identity()
+    }
+}
+
+/// Assembles a generic SRS from the powers of tau of two distinct ceremonies. The powers are
+/// verified first, the resulting SRS contains as many powers as the shorter of both.
+pub fn assemble_srs<E, R>(
+    mut alpha: PowersOfTau<E>,
+    mut beta: PowersOfTau<E>,
+    rng: &mut R,
+) -> io::Result<GenericSRS<E>>
+where
+    E: MultiMillerLoop,
+    R: rand_core::RngCore,
+{
+    let len = alpha.g1.len().min(beta.g1.len());
+    if len > MAX_SRS_SIZE {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid SRS vector length {}", len),
+        ));
+    }
+    alpha.g1.truncate(len);
+    alpha.g2.truncate(len);
+    beta.g1.truncate(len);
+    beta.g2.truncate(len);
+
+    if !alpha.verify(rng) || !beta.verify(rng) {
+        return Err(Error::new(ErrorKind::InvalidData, "invalid powers of tau"));
+    }
+    if alpha.g1[1] == beta.g1[1] {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "powers of tau of both ceremonies are the same",
+        ));
+    }
+
+    Ok(GenericSRS {
+        g_alpha_powers: alpha.g1,
+        h_alpha_powers: alpha.g2,
+        g_beta_powers: beta.g1,
+        h_beta_powers: beta.g2,
+    })
+}
+
+/// Returns a random 128-bit scalar.
+fn random_scalar<E: Engine, R: rand_core::RngCore>(rng: &mut R) -> E::Fr {
+    let low = rng.next_u64() as u128;
+    let high = rng.next_u64() as u128;
+    E::Fr::from_u128((high << 64) | low)
+}
+
+/// Returns $\sum_i s_i P_i$ for 128-bit scalars $s_i$.
+fn random_combination<G: PrimeCurveAffine>(points: &[G], scalars: &[G::Scalar]) -> G::Curve {
+    // A window size of one doesn't need any additional memory for the tables.
+    let table = precompute_fixed_window(points, 1);
+    let getter = |i: usize| -> <G::Scalar as PrimeField>::Repr { scalars[i].to_repr() };
+    par_multiscalar_generic::<_, G>(&ScalarList::Getter(getter, scalars.len()), &table, 128)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::groth16::aggregate::srs::structured_generators_scalar_power;
+    use blstrs::{Bls12, G1Projective, G2Projective, Scalar as Fr};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use std::io::Cursor;
+
+    /// Writes a transcript with the powers of `tau` in the `powersoftau` layout.
+    fn write_transcript(format: PowersOfTauFormat, tau: &Fr) -> Vec<u8> {
+        let g1: Vec<_> =
+            structured_generators_scalar_power(format.g1_len(), &G1Projective::generator(), tau);
+        let g2: Vec<_> =
+            structured_generators_scalar_power(format.g2_len(), &G2Projective::generator(), tau);
+
+        let mut out = vec![0u8; HASH_SIZE];
+        for p in &g1 {
+            if format.compressed {
+                out.extend_from_slice(p.to_bytes().as_ref());
+            } else {
+                out.extend_from_slice(p.to_uncompressed().as_ref());
+            }
+        }
+        for p in &g2 {
+            if format.compressed {
+                out.extend_from_slice(p.to_bytes().as_ref());
+            } else {
+                out.extend_from_slice(p.to_uncompressed().as_ref());
+            }
+        }
+        // The alpha and beta powers of the transcript, they are not read.
+        out.extend_from_slice(&[1u8; 128]);
+        out
+    }
+
+    #[test]
+    fn test_assemble_srs() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let alpha_format = PowersOfTauFormat {
+            power: 4,
+            compressed: true,
+        };
+        let beta_format = PowersOfTauFormat {
+            power: 3,
+            compressed: false,
+        };
+        let alpha = Fr::random(&mut rng);
+        let beta = Fr::random(&mut rng);
+        let alpha_transcript = write_transcript(alpha_format, &alpha);
+        let beta_transcript = write_transcript(beta_format, &beta);
+
+        let alpha_powers = alpha_format
+            .read::<Bls12, _>(&mut Cursor::new(&alpha_transcript), 16)
+            .unwrap();
+        let beta_powers = beta_format
+            .read::<Bls12, _>(&mut Cursor::new(&beta_transcript), 8)
+            .unwrap();
+        beta_format
+            .read::<Bls12, _>(&mut Cursor::new(&beta_transcript), 9)
+            .expect_err("transcript only contains 8 powers");
+
+        let srs = assemble_srs(alpha_powers.clone(), beta_powers.clone(), &mut rng).unwrap();
+        assert_eq!(srs.g_alpha_powers.len(), 8);
+        assert_eq!(
+            srs.g_alpha_powers[5],
+            (G1Projective::generator() * alpha.pow_vartime([5u64])).to_affine()
+        );
+        assert_eq!(
+            srs.h_beta_powers[7],
+            (G2Projective::generator() * beta.pow_vartime([7u64])).to_affine()
+        );
+
+        let mut buffer = Vec::new();
+        srs.write(&mut buffer).unwrap();
+        let read_srs = GenericSRS::<Bls12>::read(&mut Cursor::new(&buffer)).unwrap();
+        assert!(read_srs == srs);
+
+        // Invalid powers
+        let mut invalid_powers = beta_powers.clone();
+        invalid_powers.g1[3] = invalid_powers.g1[4];
+        assert!(!invalid_powers.verify(&mut rng));
+        assemble_srs(alpha_powers, invalid_powers, &mut rng).expect_err("invalid powers of tau");
+
+        let mut invalid_powers = beta_powers.clone();
+        invalid_powers.g2[6] = G2Projective::random(&mut rng).to_affine();
+        assert!(!invalid_powers.verify(&mut rng));
+
+        // Same ceremony twice
+        assemble_srs(beta_powers.clone(), beta_powers, &mut rng)
+            .expect_err("powers of tau of both ceremonies are the same");
+    }
+}
@@ -129,13 +129,7 @@ where
                 "invalid nproofs field".to_string(),
             ));
         }
-        // 2. Check if it's a power of two
-        if !gipa.nproofs.is_power_of_two() {
-            return Err(SynthesisError::MalformedProofs(
-                "number of proofs is not a power of two".to_string(),
-            ));
-        }
-        // 3. Check all vectors are of the same length and of the correct length
+        // 2. Check all vectors are of the same length and of the correct length
         let ref_len = gipa.comms_ab.len();
         let good_len = ref_len == (gipa.nproofs as f32).log2().ceil() as usize;
         if !good_len {
@@ -177,27 +171,50 @@ where
         Ok(())
     }
 
-    /// Returns the number of bytes this proof is serialized to.
+    /// Returns the number of bytes this proof is serialized to by `write`.
     pub fn serialized_len(&self) -> usize {
-        // TODO: calculate
-        let mut out = Vec::new();
-        self.write(&mut out).unwrap();
+        let log_proofs = log_proofs(self.tmipp.gipa.nproofs as usize);
+        let num_gt = 5 + 10 * log_proofs;
+        let num_g1 = 7 + 2 * log_proofs;
+        let num_g2 = 5;
+        4 + num_gt * gt_len::<E>(&self.ip_ab)
+            + num_g1 * point_len::<E::G1Affine>()
+            + num_g2 * point_len::<E::G2Affine>()
+    }
+
+    /// Writes the proof in the versioned compact encoding: a version byte, which
+    /// is `COMPACT_ENCODING_VERSION`, followed by the output of `write`. All G1 and
+    /// G2 points are compressed and all Gt elements are torus-compressed, see
+    /// `blstrs::Compress`. The encoding is `serialized_len() + 1` bytes long:
+    ///
+    /// - `com_ab`, `com_c`: 2 Gt elements each
+    /// - `ip_ab`: Gt element
+    /// - `agg_c`: G1 point
+    /// - `nproofs`: u32, little endian
+    /// - `comms_ab`, `comms_c`: `log2(nproofs)` times 4 Gt elements each
+    /// - `z_ab`: `log2(nproofs)` times 2 Gt elements
+    /// - `z_c`: `log2(nproofs)` times 2 G1 points
+    /// - `final_a`: G1 point, `final_b`: G2 point, `final_c`: G1 point
+    /// - `final_vkey`: 2 G2 points, `final_wkey`: 2 G1 points
+    /// - `vkey_opening`: 2 G2 points, `wkey_opening`: 2 G1 points
+    pub fn write_compact(&self, mut out: impl Write) -> std::io::Result<()> {
+        out.write_all(&[COMPACT_ENCODING_VERSION])?;
+        self.write(out)
+    }
 
-        out.len()
+    /// Reads a proof in the compact encoding written by `write_compact`. Only
+    /// canonical encodings of the points and Gt elements are accepted.
+    pub fn read_compact(mut source: impl Read) -> std::io::Result<Self> {
+        read_compact_version(&mut source)?;
+        Self::read(source)
     }
 
     pub fn read(mut source: impl Read) -> std::io::Result<Self> {
-        let com_ab = (
-            <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?,
-            <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?,
-        );
+        let com_ab = (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?);
 
-        let com_c = (
-            <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?,
-            <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?,
-        );
+        let com_c = (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?);
 
-        let ip_ab = <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?;
+        let ip_ab = read_gt::<E, _>(&mut source)?;
         let agg_c = read_affine::<E::G1Affine, _>(&mut source)?.to_curve();
 
         let tmipp = TippMippProof::read(&mut source)?;
@@ -212,6 +229,78 @@ where
     }
 }
 
+/// MultiAggregateProof aggregates Groth16 proofs of different circuits. The
+/// proofs are grouped by verifying key and each group is aggregated into its own
+/// `AggregateProof`, in the order in which the verifying keys first appear.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultiAggregateProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    #[serde(bound(
+        serialize = "AggregateProof<E>: Serialize",
+        deserialize = "AggregateProof<E>: Deserialize<'de>",
+    ))]
+    pub groups: Vec<AggregateProof<E>>,
+}
+
+impl<E> PartialEq for MultiAggregateProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.groups == other.groups
+    }
+}
+
+impl<E> MultiAggregateProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    /// Performs the high level checks of all the aggregated proofs.
+    pub fn parsing_check(&self) -> Result<(), SynthesisError> {
+        if self.groups.is_empty() {
+            return Err(SynthesisError::MalformedProofs(
+                "no aggregated proofs".to_string(),
+            ));
+        }
+        for group in &self.groups {
+            group.parsing_check()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the aggregated proofs into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        out.write_all(&(self.groups.len() as u32).to_le_bytes())?;
+        for group in &self.groups {
+            group.write(&mut out)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let mut buffer = 0u32.to_le_bytes();
+        source.read_exact(&mut buffer)?;
+        let num_groups = u32::from_le_bytes(buffer);
+        if num_groups == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no aggregated proofs",
+            ));
+        }
+
+        let groups = (0..num_groups)
+            .map(|_| AggregateProof::read(&mut source))
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(MultiAggregateProof { groups })
+    }
+}
+
 /// It contains all elements derived in the GIPA loop for both TIPP and MIPP at
 /// the same time.
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -219,6 +308,8 @@ pub struct GipaProof<E>
 where
     E: MultiMillerLoop,
 {
+    /// Number of aggregated proofs, without padding. GIPA runs over the next
+    /// power of two of it.
     pub nproofs: u32,
     #[serde(bound(
         serialize = "<E as pairing::Engine>::Gt: Serialize",
@@ -373,8 +464,8 @@ where
             <E as Engine>::Gt: Compress,
             R: Read,
         {
-            let a = <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?;
-            let b = <<E as Engine>::Gt as Compress>::read_compressed(&mut source)?;
+            let a = read_gt::<E, _>(&mut source)?;
+            let b = read_gt::<E, _>(&mut source)?;
             Ok((a, b))
         }
 
@@ -512,6 +603,12 @@ where
 {
     pub fn parsing_check(&self) -> Result<(), SynthesisError> {
         self.pi_agg.parsing_check()?;
+        // The instance aggregation does not support padding.
+        if !self.pi_agg.tmipp.gipa.nproofs.is_power_of_two() {
+            return Err(SynthesisError::MalformedProofs(
+                "number of proofs is not a power of two".to_string(),
+            ));
+        }
         let n = (self.num_inputs / 2) as usize;
 
         if n < 2 {
@@ -587,6 +684,36 @@ where
         Ok(())
     }
 
+    /// Returns the number of bytes this proof is serialized to by `write`.
+    pub fn serialized_len(&self) -> usize {
+        let num_g1 =
+            self.com_f.len() + self.com_w0.len() + self.com_wd.len() + self.f_eval_proof.len();
+        4 + self.pi_agg.serialized_len()
+            + num_g1 * point_len::<E::G1Affine>()
+            + self.f_eval.len() * <E::Fr as PrimeField>::Repr::default().as_ref().len()
+    }
+
+    /// Writes the proof in the versioned compact encoding: a version byte, which
+    /// is `COMPACT_ENCODING_VERSION`, followed by the output of `write`. The
+    /// encoding is `serialized_len() + 1` bytes long:
+    ///
+    /// - `num_inputs`: u32, little endian
+    /// - `pi_agg`: the aggregated proof, see `AggregateProof::write_compact`
+    /// - `com_f`, `com_w0`, `com_wd`: `num_inputs / 2` compressed G1 points each
+    /// - `f_eval`: `num_inputs / 2` scalars, in their canonical representation
+    /// - `f_eval_proof`: `num_inputs / 2` compressed G1 points
+    pub fn write_compact(&self, mut out: impl Write) -> std::io::Result<()> {
+        out.write_all(&[COMPACT_ENCODING_VERSION])?;
+        self.write(out)
+    }
+
+    /// Reads a proof in the compact encoding written by `write_compact`. Only
+    /// canonical encodings of the points, Gt elements and scalars are accepted.
+    pub fn read_compact(mut source: impl Read) -> std::io::Result<Self> {
+        read_compact_version(&mut source)?;
+        Self::read(source)
+    }
+
     pub fn read(mut source: impl Read) -> std::io::Result<Self> {
         let mut buffer = 0u32.to_le_bytes();
         source.read_exact(&mut buffer)?;
@@ -613,7 +740,7 @@ where
         let mut buf = <E::Fr as PrimeField>::Repr::default();
         for _ in 0..n {
             source.read_exact(buf.as_mut())?;
-            f_eval.push(<E::Fr as PrimeField>::from_repr_vartime(buf).unwrap());
+            f_eval.push(read_scalar::<E::Fr>(buf)?);
         }
 
         let mut f_eval_proof = Vec::with_capacity(n);
@@ -633,123 +760,872 @@ where
     }
 }
 
-/// KZGOpening represents the KZG opening of a commitment key (which is a tuple
-/// given commitment keys are a tuple).
-#[allow(clippy::upper_case_acronyms)]
-pub type KZGOpening<G> = (G, G);
-
-fn read_affine<G: PrimeCurveAffine, R: std::io::Read>(mut source: R) -> std::io::Result<G> {
-    // Read as compressed affine point.
-    let mut affine_compressed = <G as GroupEncoding>::Repr::default();
-    source.read_exact(affine_compressed.as_mut())?;
-    let opt: Option<_> = G::from_bytes(&affine_compressed).into();
-
-    let affine =
-        opt.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid point"))?;
+/// AggregateProofAndLinkedInstances aggregates Groth16 proofs whose public
+/// inputs are related by `InstanceLink`s. Besides the aggregated proof, it
+/// contains a KZG commitment and opening for the polynomial of every public input
+/// that cannot be derived through the links, with the commitments proving the
+/// degree of the polynomial and its first coefficients, see
+/// `aggregate_proofs_and_linked_instances`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AggregateProofAndLinkedInstances<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    #[serde(bound(
+        serialize = "AggregateProof<E>: Serialize",
+        deserialize = "AggregateProof<E>: Deserialize<'de>",
+    ))]
+    pub pi_agg: AggregateProof<E>,
+    /// Commitments to the polynomials of the committed public inputs.
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::G1: Serialize",
+        deserialize = "<E as pairing::Engine>::G1: Deserialize<'de>",
+    ))]
+    pub com_inputs: Vec<E::G1>,
+    /// Negated commitments to the committed polynomials without their first
+    /// coefficients, proving these coefficients. They are ordered by committed
+    /// polynomial, then by the number of coefficients removed.
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::G1: Serialize",
+        deserialize = "<E as pairing::Engine>::G1: Deserialize<'de>",
+    ))]
+    pub com_shifts: Vec<E::G1>,
+    /// Negated commitments to the committed polynomials shifted to the end of
+    /// the SRS, proving their degree is less than the number of proofs.
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::G1: Serialize",
+        deserialize = "<E as pairing::Engine>::G1: Deserialize<'de>",
+    ))]
+    pub com_bounds: Vec<E::G1>,
+    /// Evaluations of the polynomials of the committed public inputs.
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Fr: Serialize",
+        deserialize = "<E as pairing::Engine>::Fr: Deserialize<'de>",
+    ))]
+    pub input_evals: Vec<E::Fr>,
+    /// KZG proofs of the evaluations.
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::G1: Serialize",
+        deserialize = "<E as pairing::Engine>::G1: Deserialize<'de>",
+    ))]
+    pub input_eval_proofs: Vec<E::G1>,
+}
 
-    Ok(affine)
+impl<E> PartialEq for AggregateProofAndLinkedInstances<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.pi_agg == other.pi_agg
+            && self.com_inputs == other.com_inputs
+            && self.com_shifts == other.com_shifts
+            && self.com_bounds == other.com_bounds
+            && self.input_evals == other.input_evals
+            && self.input_eval_proofs == other.input_eval_proofs
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<E> AggregateProofAndLinkedInstances<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    pub fn parsing_check(&self) -> Result<(), SynthesisError> {
+        self.pi_agg.parsing_check()?;
+        // The instance aggregation does not support padding.
+        if !self.pi_agg.tmipp.gipa.nproofs.is_power_of_two() {
+            return Err(SynthesisError::MalformedProofs(
+                "number of proofs is not a power of two".to_string(),
+            ));
+        }
 
-    use ff::Field;
-    use group::Group;
+        let n = self.com_inputs.len();
+        if self.com_bounds.len() != n
+            || self.input_evals.len() != n
+            || self.input_eval_proofs.len() != n
+        {
+            return Err(SynthesisError::MalformedProofs(format!(
+                "com_bounds, input_evals and input_eval_proofs must be equal to {}",
+                n
+            )));
+        }
+        Ok(())
+    }
 
-    use blstrs::{Bls12, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+    /// Writes the proof into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        self.pi_agg.write(&mut out)?;
 
-    fn fake_proof() -> AggregateProof<Bls12> {
-        // create pairing, as pairing results can be compressed
-        let p = G1Projective::generator().to_affine();
-        let q = G2Projective::generator().to_affine();
-        let a = Bls12::pairing(&p, &q);
+        out.write_all(&(self.com_inputs.len() as u32).to_le_bytes())?;
+        for e in &self.com_inputs {
+            out.write_all(e.to_affine().to_bytes().as_ref())?;
+        }
 
-        AggregateProof::<Bls12> {
-            com_ab: (a, a),
-            com_c: (a, a),
-            ip_ab: a,
-            agg_c: G1Projective::generator(),
-            tmipp: TippMippProof::<Bls12> {
-                gipa: GipaProof {
-                    nproofs: 4,
-                    comms_ab: vec![((a, a), (a, a)), ((a, a), (a, a))],
-                    comms_c: vec![((a, a), (a, a)), ((a, a), (a, a))],
-                    z_ab: vec![(a, a), (a, a)],
-                    z_c: vec![
-                        (G1Projective::generator(), G1Projective::generator()),
-                        (G1Projective::generator(), G1Projective::generator()),
-                    ],
-                    final_a: G1Affine::generator(),
-                    final_b: G2Affine::generator(),
-                    final_c: G1Affine::generator(),
-                    final_vkey: (G2Affine::generator(), G2Affine::generator()),
-                    final_wkey: (G1Affine::generator(), G1Affine::generator()),
-                },
-                vkey_opening: (G2Affine::generator(), G2Affine::generator()),
-                wkey_opening: (G1Affine::generator(), G1Affine::generator()),
-            },
+        out.write_all(&(self.com_shifts.len() as u32).to_le_bytes())?;
+        for e in self.com_shifts.iter().chain(self.com_bounds.iter()) {
+            out.write_all(e.to_affine().to_bytes().as_ref())?;
         }
-    }
 
-    #[test]
-    fn test_proof_io() {
-        let proof = fake_proof();
-        let mut buffer = Vec::new();
-        proof.write(&mut buffer).unwrap();
-        assert_eq!(buffer.len(), 8_212);
+        for e in &self.input_evals {
+            out.write_all(e.to_repr().as_ref())?;
+        }
 
-        let out = AggregateProof::<Bls12>::read(std::io::Cursor::new(&buffer)).unwrap();
-        assert_eq!(proof, out);
+        for e in &self.input_eval_proofs {
+            out.write_all(e.to_affine().to_bytes().as_ref())?;
+        }
 
-        let ser_proof = bincode::serialize(&proof).unwrap();
-        let des_proof: AggregateProof<Bls12> = bincode::deserialize(&ser_proof).unwrap();
-        assert_eq!(des_proof, proof);
+        Ok(())
     }
 
-    #[test]
-    fn test_proof_check() {
-        let p = G1Projective::generator().to_affine();
-        let q = G2Projective::generator().to_affine();
-        let a = Bls12::pairing(&p, &q);
+    pub fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let pi_agg = AggregateProof::read(&mut source)?;
 
-        let mut proof = fake_proof();
-        proof.parsing_check().expect("proof should be valid");
+        let mut buffer = 0u32.to_le_bytes();
+        source.read_exact(&mut buffer)?;
+        let n = u32::from_le_bytes(buffer) as usize;
 
-        let oldn = proof.tmipp.gipa.nproofs;
-        proof.tmipp.gipa.nproofs = 14;
-        proof.parsing_check().expect_err("proof should be invalid");
-        proof.tmipp.gipa.nproofs = oldn;
+        let com_inputs = (0..n)
+            .map(|_| Ok(read_affine::<E::G1Affine, _>(&mut source)?.to_curve()))
+            .collect::<std::io::Result<_>>()?;
 
-        proof
-            .tmipp
-            .gipa
-            .comms_ab
-            .append(&mut vec![((a, a), (a, a))]);
-        proof.parsing_check().expect_err("Proof should be invalid");
+        source.read_exact(&mut buffer)?;
+        let num_shifts = u32::from_le_bytes(buffer) as usize;
+        let com_shifts = (0..num_shifts)
+            .map(|_| Ok(read_affine::<E::G1Affine, _>(&mut source)?.to_curve()))
+            .collect::<std::io::Result<_>>()?;
+        let com_bounds = (0..n)
+            .map(|_| Ok(read_affine::<E::G1Affine, _>(&mut source)?.to_curve()))
+            .collect::<std::io::Result<_>>()?;
+
+        let input_evals = (0..n)
+            .map(|_| {
+                let mut buf = <E::Fr as PrimeField>::Repr::default();
+                source.read_exact(buf.as_mut())?;
+                read_scalar::<E::Fr>(buf)
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        let input_eval_proofs = (0..n)
+            .map(|_| Ok(read_affine::<E::G1Affine, _>(&mut source)?.to_curve()))
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(AggregateProofAndLinkedInstances {
+            pi_agg,
+            com_inputs,
+            com_shifts,
+            com_bounds,
+            input_evals,
+            input_eval_proofs,
+        })
     }
+}
 
-    fn fake_proof_instance() -> AggregateProofAndInstance<Bls12> {
-        // create pairing, as pairing results can be compressed
-        let a = G1Projective::generator();
-        let b = G1Projective::generator();
-        let c = Scalar::ZERO;
+/// Commitment of a shard of a sharded aggregation to the A, B and C of its
+/// proofs, see `commit_shard`. The proofs are padded to the size of the SRS by
+/// repeating the last proof, so that the commitments of all shards use the same
+/// keys.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardCommitment<E>
+where
+    E: MultiMillerLoop,
+{
+    /// Number of proofs of the shard, without padding.
+    pub nproofs: u32,
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Gt: Serialize",
+        deserialize = "<E as pairing::Engine>::Gt: Deserialize<'de>",
+    ))]
+    pub com_ab: commit::Output<E>,
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Gt: Serialize",
+        deserialize = "<E as pairing::Engine>::Gt: Deserialize<'de>",
+    ))]
+    pub com_c: commit::Output<E>,
+}
 
-        AggregateProofAndInstance::<Bls12> {
-            num_inputs: 8,
-            pi_agg: fake_proof(),
-            com_f: vec![a, a, a, a],
-            com_w0: vec![a, a, a, a],
-            com_wd: vec![a, a, a, a],
-            f_eval: vec![c, c, c, c],
-            f_eval_proof: vec![b, b, b, b],
-        }
+impl<E> PartialEq for ShardCommitment<E>
+where
+    E: MultiMillerLoop,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.nproofs == other.nproofs && self.com_ab == other.com_ab && self.com_c == other.com_c
     }
+}
 
-    #[test]
-    fn test_proof_io_instance() {
-        let proof = fake_proof_instance();
-        proof.parsing_check().unwrap();
+impl<E> ShardCommitment<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    /// Writes the commitment into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        out.write_all(&self.nproofs.to_le_bytes())?;
+        self.com_ab.0.write_compressed(&mut out)?;
+        self.com_ab.1.write_compressed(&mut out)?;
+        self.com_c.0.write_compressed(&mut out)?;
+        self.com_c.1.write_compressed(&mut out)?;
+        Ok(())
+    }
+
+    pub fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let mut buffer = 0u32.to_le_bytes();
+        source.read_exact(&mut buffer)?;
+        let nproofs = u32::from_le_bytes(buffer);
+
+        let com_ab = (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?);
+        let com_c = (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?);
+
+        Ok(ShardCommitment {
+            nproofs,
+            com_ab,
+            com_c,
+        })
+    }
+}
+
+/// TIPP proof of a shard, see `prove_shard`: the GIPA recursion over A and
+/// $B^r$, with the KZG openings of the final commitment keys $v$ and $w$.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TippProof<E>
+where
+    E: MultiMillerLoop,
+{
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Gt: Serialize",
+        deserialize = "<E as pairing::Engine>::Gt: Deserialize<'de>",
+    ))]
+    pub comms_ab: Vec<(commit::Output<E>, commit::Output<E>)>,
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Gt: Serialize",
+        deserialize = "<E as pairing::Engine>::Gt: Deserialize<'de>",
+    ))]
+    pub z_ab: Vec<(<E as Engine>::Gt, <E as Engine>::Gt)>,
+    #[serde(bound(
+        serialize = "E::G1Affine: Serialize",
+        deserialize = "E::G1Affine: Deserialize<'de>",
+    ))]
+    pub final_a: E::G1Affine,
+    #[serde(bound(
+        serialize = "E::G2Affine: Serialize",
+        deserialize = "E::G2Affine: Deserialize<'de>",
+    ))]
+    pub final_b: E::G2Affine,
+    #[serde(bound(
+        serialize = "E::G2Affine: Serialize",
+        deserialize = "E::G2Affine: Deserialize<'de>",
+    ))]
+    pub final_vkey: (E::G2Affine, E::G2Affine),
+    #[serde(bound(
+        serialize = "E::G1Affine: Serialize",
+        deserialize = "E::G1Affine: Deserialize<'de>",
+    ))]
+    pub final_wkey: (E::G1Affine, E::G1Affine),
+    #[serde(bound(
+        serialize = "E::G2Affine: Serialize",
+        deserialize = "E::G2Affine: Deserialize<'de>",
+    ))]
+    pub vkey_opening: KZGOpening<E::G2Affine>,
+    #[serde(bound(
+        serialize = "E::G1Affine: Serialize",
+        deserialize = "E::G1Affine: Deserialize<'de>",
+    ))]
+    pub wkey_opening: KZGOpening<E::G1Affine>,
+}
+
+impl<E> PartialEq for TippProof<E>
+where
+    E: MultiMillerLoop,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.comms_ab == other.comms_ab
+            && self.z_ab == other.z_ab
+            && self.final_a == other.final_a
+            && self.final_b == other.final_b
+            && self.final_vkey == other.final_vkey
+            && self.final_wkey == other.final_wkey
+            && self.vkey_opening == other.vkey_opening
+            && self.wkey_opening == other.wkey_opening
+    }
+}
+
+impl<E> TippProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    /// Writes the proof into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        assert_eq!(self.comms_ab.len(), self.z_ab.len());
+        out.write_all(&(self.comms_ab.len() as u32).to_le_bytes())?;
+        for (x, y) in &self.comms_ab {
+            x.0.write_compressed(&mut out)?;
+            x.1.write_compressed(&mut out)?;
+            y.0.write_compressed(&mut out)?;
+            y.1.write_compressed(&mut out)?;
+        }
+        for (x, y) in &self.z_ab {
+            x.write_compressed(&mut out)?;
+            y.write_compressed(&mut out)?;
+        }
+
+        out.write_all(self.final_a.to_bytes().as_ref())?;
+        out.write_all(self.final_b.to_bytes().as_ref())?;
+        out.write_all(self.final_vkey.0.to_bytes().as_ref())?;
+        out.write_all(self.final_vkey.1.to_bytes().as_ref())?;
+        out.write_all(self.final_wkey.0.to_bytes().as_ref())?;
+        out.write_all(self.final_wkey.1.to_bytes().as_ref())?;
+        out.write_all(self.vkey_opening.0.to_bytes().as_ref())?;
+        out.write_all(self.vkey_opening.1.to_bytes().as_ref())?;
+        out.write_all(self.wkey_opening.0.to_bytes().as_ref())?;
+        out.write_all(self.wkey_opening.1.to_bytes().as_ref())?;
+        Ok(())
+    }
+
+    fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let rounds = read_rounds(&mut source)?;
+        let comms_ab = (0..rounds)
+            .map(|_| {
+                Ok((
+                    (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?),
+                    (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?),
+                ))
+            })
+            .collect::<std::io::Result<_>>()?;
+        let z_ab = (0..rounds)
+            .map(|_| Ok((read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?)))
+            .collect::<std::io::Result<_>>()?;
+
+        let final_a = read_affine(&mut source)?;
+        let final_b = read_affine(&mut source)?;
+        let final_vkey = (read_affine(&mut source)?, read_affine(&mut source)?);
+        let final_wkey = (read_affine(&mut source)?, read_affine(&mut source)?);
+        let vkey_opening = (read_affine(&mut source)?, read_affine(&mut source)?);
+        let wkey_opening = (read_affine(&mut source)?, read_affine(&mut source)?);
+
+        Ok(TippProof {
+            comms_ab,
+            z_ab,
+            final_a,
+            final_b,
+            final_vkey,
+            final_wkey,
+            vkey_opening,
+            wkey_opening,
+        })
+    }
+}
+
+/// MIPP proof over the C of all shards of a sharded aggregation, see
+/// `aggregate_shards`: the GIPA recursion over C and r, with the KZG opening of
+/// the final commitment key $v$.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MippProof<E>
+where
+    E: MultiMillerLoop,
+{
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Gt: Serialize",
+        deserialize = "<E as pairing::Engine>::Gt: Deserialize<'de>",
+    ))]
+    pub comms_c: Vec<(commit::Output<E>, commit::Output<E>)>,
+    #[serde(bound(
+        serialize = "E::G1: Serialize",
+        deserialize = "E::G1: Deserialize<'de>",
+    ))]
+    pub z_c: Vec<(E::G1, E::G1)>,
+    #[serde(bound(
+        serialize = "E::G1Affine: Serialize",
+        deserialize = "E::G1Affine: Deserialize<'de>",
+    ))]
+    pub final_c: E::G1Affine,
+    #[serde(bound(
+        serialize = "E::G2Affine: Serialize",
+        deserialize = "E::G2Affine: Deserialize<'de>",
+    ))]
+    pub final_vkey: (E::G2Affine, E::G2Affine),
+    #[serde(bound(
+        serialize = "E::G2Affine: Serialize",
+        deserialize = "E::G2Affine: Deserialize<'de>",
+    ))]
+    pub vkey_opening: KZGOpening<E::G2Affine>,
+}
+
+impl<E> PartialEq for MippProof<E>
+where
+    E: MultiMillerLoop,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.comms_c == other.comms_c
+            && self.z_c == other.z_c
+            && self.final_c == other.final_c
+            && self.final_vkey == other.final_vkey
+            && self.vkey_opening == other.vkey_opening
+    }
+}
+
+impl<E> MippProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    /// Writes the proof into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        assert_eq!(self.comms_c.len(), self.z_c.len());
+        out.write_all(&(self.comms_c.len() as u32).to_le_bytes())?;
+        for (x, y) in &self.comms_c {
+            x.0.write_compressed(&mut out)?;
+            x.1.write_compressed(&mut out)?;
+            y.0.write_compressed(&mut out)?;
+            y.1.write_compressed(&mut out)?;
+        }
+        for (x, y) in &self.z_c {
+            out.write_all(x.to_affine().to_bytes().as_ref())?;
+            out.write_all(y.to_affine().to_bytes().as_ref())?;
+        }
+
+        out.write_all(self.final_c.to_bytes().as_ref())?;
+        out.write_all(self.final_vkey.0.to_bytes().as_ref())?;
+        out.write_all(self.final_vkey.1.to_bytes().as_ref())?;
+        out.write_all(self.vkey_opening.0.to_bytes().as_ref())?;
+        out.write_all(self.vkey_opening.1.to_bytes().as_ref())?;
+        Ok(())
+    }
+
+    fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let rounds = read_rounds(&mut source)?;
+        let comms_c = (0..rounds)
+            .map(|_| {
+                Ok((
+                    (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?),
+                    (read_gt::<E, _>(&mut source)?, read_gt::<E, _>(&mut source)?),
+                ))
+            })
+            .collect::<std::io::Result<_>>()?;
+        let z_c = (0..rounds)
+            .map(|_| {
+                Ok((
+                    read_affine::<E::G1Affine, _>(&mut source)?.to_curve(),
+                    read_affine::<E::G1Affine, _>(&mut source)?.to_curve(),
+                ))
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        let final_c = read_affine(&mut source)?;
+        let final_vkey = (read_affine(&mut source)?, read_affine(&mut source)?);
+        let vkey_opening = (read_affine(&mut source)?, read_affine(&mut source)?);
+
+        Ok(MippProof {
+            comms_c,
+            z_c,
+            final_c,
+            final_vkey,
+            vkey_opening,
+        })
+    }
+}
+
+/// Proof of a shard of a sharded aggregation, see `prove_shard`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardProof<E>
+where
+    E: MultiMillerLoop,
+{
+    /// $A^r * B = Z$ of the shard
+    #[serde(bound(
+        serialize = "<E as pairing::Engine>::Gt: Serialize",
+        deserialize = "<E as pairing::Engine>::Gt: Deserialize<'de>",
+    ))]
+    pub ip_ab: <E as Engine>::Gt,
+    #[serde(bound(
+        serialize = "TippProof<E>: Serialize",
+        deserialize = "TippProof<E>: Deserialize<'de>",
+    ))]
+    pub tipp: TippProof<E>,
+}
+
+impl<E> PartialEq for ShardProof<E>
+where
+    E: MultiMillerLoop,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.ip_ab == other.ip_ab && self.tipp == other.tipp
+    }
+}
+
+impl<E> ShardProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    /// Writes the proof into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        self.ip_ab.write_compressed(&mut out)?;
+        self.tipp.write(&mut out)
+    }
+
+    pub fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let ip_ab = read_gt::<E, _>(&mut source)?;
+        let tipp = TippProof::read(&mut source)?;
+        Ok(ShardProof { ip_ab, tipp })
+    }
+}
+
+/// ShardedAggregateProof aggregates Groth16 proofs split into shards, which can
+/// hold more proofs in total than a single SRS supports. Each shard proves the
+/// TIPP relation of its own A and B, and a single MIPP proof over a random linear
+/// combination of the C of all shards ties them into one aggregated Groth16
+/// equation, see `aggregate_proofs_sharded`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardedAggregateProof<E>
+where
+    E: MultiMillerLoop,
+{
+    #[serde(bound(
+        serialize = "ShardCommitment<E>: Serialize",
+        deserialize = "ShardCommitment<E>: Deserialize<'de>",
+    ))]
+    pub commitments: Vec<ShardCommitment<E>>,
+    #[serde(bound(
+        serialize = "ShardProof<E>: Serialize",
+        deserialize = "ShardProof<E>: Deserialize<'de>",
+    ))]
+    pub shards: Vec<ShardProof<E>>,
+    /// $\sum_j s^j C_j^r$ is used on the right side of the combined Groth16
+    /// equation, where $C_j^r$ is the `agg_c` of shard $j$.
+    pub agg_c: E::G1,
+    #[serde(bound(
+        serialize = "MippProof<E>: Serialize",
+        deserialize = "MippProof<E>: Deserialize<'de>",
+    ))]
+    pub mipp: MippProof<E>,
+}
+
+impl<E> PartialEq for ShardedAggregateProof<E>
+where
+    E: MultiMillerLoop,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.commitments == other.commitments
+            && self.shards == other.shards
+            && self.agg_c == other.agg_c
+            && self.mipp == other.mipp
+    }
+}
+
+impl<E> ShardedAggregateProof<E>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
+    /// Returns the number of proofs the shards are padded to, the size of the
+    /// SRS they were aggregated with.
+    pub fn shard_size(&self) -> usize {
+        1 << self.mipp.comms_c.len()
+    }
+
+    /// Returns the total number of aggregated proofs, without padding.
+    pub fn nproofs(&self) -> usize {
+        self.commitments.iter().map(|c| c.nproofs as usize).sum()
+    }
+
+    /// Performs some high level checks on the length of vectors and others to
+    /// make sure all items in the proofs are consistent with each other.
+    pub fn parsing_check(&self) -> Result<(), SynthesisError> {
+        if self.commitments.is_empty() {
+            return Err(SynthesisError::MalformedProofs("no shards".to_string()));
+        }
+        if self.commitments.len() != self.shards.len() {
+            return Err(SynthesisError::MalformedProofs(
+                "number of shard commitments does not match the number of shard proofs".to_string(),
+            ));
+        }
+        let rounds = self.mipp.comms_c.len();
+        if rounds == 0 || rounds >= usize::BITS as usize || self.shard_size() > srs::MAX_SRS_SIZE {
+            return Err(SynthesisError::MalformedProofs(
+                "invalid shard size".to_string(),
+            ));
+        }
+        if self.mipp.z_c.len() != rounds
+            || self
+                .shards
+                .iter()
+                .any(|s| s.tipp.comms_ab.len() != rounds || s.tipp.z_ab.len() != rounds)
+        {
+            return Err(SynthesisError::MalformedProofs(
+                "proofs vectors don't have the same size".to_string(),
+            ));
+        }
+        if self
+            .commitments
+            .iter()
+            .any(|c| c.nproofs == 0 || c.nproofs as usize > self.shard_size())
+        {
+            return Err(SynthesisError::MalformedProofs(
+                "invalid nproofs field".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the aggregated proof into the provided buffer.
+    pub fn write(&self, mut out: impl Write) -> std::io::Result<()> {
+        assert_eq!(self.commitments.len(), self.shards.len());
+        out.write_all(&(self.commitments.len() as u32).to_le_bytes())?;
+        for commitment in &self.commitments {
+            commitment.write(&mut out)?;
+        }
+        for shard in &self.shards {
+            shard.write(&mut out)?;
+        }
+        out.write_all(self.agg_c.to_affine().to_bytes().as_ref())?;
+        self.mipp.write(&mut out)
+    }
+
+    pub fn read(mut source: impl Read) -> std::io::Result<Self> {
+        let mut buffer = 0u32.to_le_bytes();
+        source.read_exact(&mut buffer)?;
+        let num_shards = u32::from_le_bytes(buffer);
+        if num_shards == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no shards",
+            ));
+        }
+
+        let commitments = (0..num_shards)
+            .map(|_| ShardCommitment::read(&mut source))
+            .collect::<std::io::Result<_>>()?;
+        let shards = (0..num_shards)
+            .map(|_| ShardProof::read(&mut source))
+            .collect::<std::io::Result<_>>()?;
+        let agg_c = read_affine::<E::G1Affine, _>(&mut source)?.to_curve();
+        let mipp = MippProof::read(&mut source)?;
+
+        Ok(ShardedAggregateProof {
+            commitments,
+            shards,
+            agg_c,
+            mipp,
+        })
+    }
+}
+
+/// Reads the number of GIPA rounds of a TIPP or MIPP proof, which is the
+/// logarithm of the size of the SRS.
+fn read_rounds<R: std::io::Read>(mut source: R) -> std::io::Result<usize> {
+    let mut buffer = 0u32.to_le_bytes();
+    source.read_exact(&mut buffer)?;
+    let rounds = u32::from_le_bytes(buffer) as usize;
+    if rounds == 0 || rounds >= usize::BITS as usize || (1 << rounds) > srs::MAX_SRS_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid number of rounds",
+        ));
+    }
+    Ok(rounds)
+}
+
+/// KZGOpening represents the KZG opening of a commitment key (which is a tuple
+/// given commitment keys are a tuple).
+#[allow(clippy::upper_case_acronyms)]
+pub type KZGOpening<G> = (G, G);
+
+/// Version of the compact encoding of aggregated proofs, see
+/// `AggregateProof::write_compact`.
+pub const COMPACT_ENCODING_VERSION: u8 = 1;
+
+fn read_compact_version<R: std::io::Read>(mut source: R) -> std::io::Result<()> {
+    let mut version = [0u8];
+    source.read_exact(&mut version)?;
+    if version[0] != COMPACT_ENCODING_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unsupported proof encoding version {}", version[0]),
+        ));
+    }
+    Ok(())
+}
+
+fn read_affine<G: PrimeCurveAffine, R: std::io::Read>(mut source: R) -> std::io::Result<G> {
+    // Read as compressed affine point.
+    let mut affine_compressed = <G as GroupEncoding>::Repr::default();
+    source.read_exact(affine_compressed.as_mut())?;
+    let opt: Option<_> = G::from_bytes(&affine_compressed).into();
+
+    let affine: G =
+        opt.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid point"))?;
+
+    // Only accept the canonical encoding of the point.
+    if affine.to_bytes().as_ref() != affine_compressed.as_ref() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "non-canonical point encoding",
+        ));
+    }
+
+    Ok(affine)
+}
+
+/// Reads a torus-compressed Gt element, only its canonical encoding is accepted.
+fn read_gt<E, R>(source: R) -> std::io::Result<<E as Engine>::Gt>
+where
+    E: Engine,
+    <E as Engine>::Gt: Compress,
+    R: std::io::Read,
+{
+    let mut recorder = RecordingReader {
+        inner: source,
+        bytes: Vec::new(),
+    };
+    let gt = <<E as Engine>::Gt as Compress>::read_compressed(&mut recorder)?;
+
+    let mut encoded = Vec::with_capacity(recorder.bytes.len());
+    gt.write_compressed(&mut encoded)?;
+    if encoded != recorder.bytes {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "non-canonical Gt encoding",
+        ));
+    }
+    Ok(gt)
+}
+
+fn read_scalar<F: PrimeField>(repr: F::Repr) -> std::io::Result<F> {
+    Option::from(F::from_repr(repr))
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "non-canonical scalar"))
+}
+
+/// Records all bytes read from the inner reader.
+struct RecordingReader<R> {
+    inner: R,
+    bytes: Vec<u8>,
+}
+
+impl<R: std::io::Read> std::io::Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+fn point_len<G: GroupEncoding>() -> usize {
+    G::Repr::default().as_ref().len()
+}
+
+/// Returns the length of the torus-compressed encoding of a Gt element.
+fn gt_len<E>(gt: &<E as Engine>::Gt) -> usize
+where
+    E: Engine,
+    <E as Engine>::Gt: Compress,
+{
+    let mut out = Vec::new();
+    gt.write_compressed(&mut out).expect("vec");
+    out.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use group::Group;
+
+    use blstrs::{Bls12, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+
+    fn fake_proof() -> AggregateProof<Bls12> {
+        // create pairing, as pairing results can be compressed
+        let p = G1Projective::generator().to_affine();
+        let q = G2Projective::generator().to_affine();
+        let a = Bls12::pairing(&p, &q);
+
+        AggregateProof::<Bls12> {
+            com_ab: (a, a),
+            com_c: (a, a),
+            ip_ab: a,
+            agg_c: G1Projective::generator(),
+            tmipp: TippMippProof::<Bls12> {
+                gipa: GipaProof {
+                    nproofs: 4,
+                    comms_ab: vec![((a, a), (a, a)), ((a, a), (a, a))],
+                    comms_c: vec![((a, a), (a, a)), ((a, a), (a, a))],
+                    z_ab: vec![(a, a), (a, a)],
+                    z_c: vec![
+                        (G1Projective::generator(), G1Projective::generator()),
+                        (G1Projective::generator(), G1Projective::generator()),
+                    ],
+                    final_a: G1Affine::generator(),
+                    final_b: G2Affine::generator(),
+                    final_c: G1Affine::generator(),
+                    final_vkey: (G2Affine::generator(), G2Affine::generator()),
+                    final_wkey: (G1Affine::generator(), G1Affine::generator()),
+                },
+                vkey_opening: (G2Affine::generator(), G2Affine::generator()),
+                wkey_opening: (G1Affine::generator(), G1Affine::generator()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_proof_io() {
+        let proof = fake_proof();
+        let mut buffer = Vec::new();
+        proof.write(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), 8_212);
+
+        let out = AggregateProof::<Bls12>::read(std::io::Cursor::new(&buffer)).unwrap();
+        assert_eq!(proof, out);
+
+        let ser_proof = bincode::serialize(&proof).unwrap();
+        let des_proof: AggregateProof<Bls12> = bincode::deserialize(&ser_proof).unwrap();
+        assert_eq!(des_proof, proof);
+    }
+
+    #[test]
+    fn test_proof_check() {
+        let p = G1Projective::generator().to_affine();
+        let q = G2Projective::generator().to_affine();
+        let a = Bls12::pairing(&p, &q);
+
+        let mut proof = fake_proof();
+        proof.parsing_check().expect("proof should be valid");
+
+        let oldn = proof.tmipp.gipa.nproofs;
+        proof.tmipp.gipa.nproofs = 14;
+        proof.parsing_check().expect_err("proof should be invalid");
+        proof.tmipp.gipa.nproofs = oldn;
+
+        proof
+            .tmipp
+            .gipa
+            .comms_ab
+            .append(&mut vec![((a, a), (a, a))]);
+        proof.parsing_check().expect_err("Proof should be invalid");
+    }
+
+    fn fake_proof_instance() -> AggregateProofAndInstance<Bls12> {
+        // create pairing, as pairing results can be compressed
+        let a = G1Projective::generator();
+        let b = G1Projective::generator();
+        let c = Scalar::ZERO;
+
+        AggregateProofAndInstance::<Bls12> {
+            num_inputs: 8,
+            pi_agg: fake_proof(),
+            com_f: vec![a, a, a, a],
+            com_w0: vec![a, a, a, a],
+            com_wd: vec![a, a, a, a],
+            f_eval: vec![c, c, c, c],
+            f_eval_proof: vec![b, b, b, b],
+        }
+    }
+
+    #[test]
+    fn test_proof_io_instance() {
+        let proof = fake_proof_instance();
+        proof.parsing_check().unwrap();
         let mut buffer = Vec::new();
         proof.write(&mut buffer).unwrap();
         assert_eq!(buffer.len(), 9_112);
@@ -762,4 +1638,47 @@ mod tests {
         assert_eq!(des_proof, proof);
         des_proof.parsing_check().unwrap();
     }
+
+    #[test]
+    fn test_proof_compact_io() {
+        let proof = fake_proof();
+        assert_eq!(proof.serialized_len(), 8_212);
+        let mut buffer = Vec::new();
+        proof.write_compact(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), proof.serialized_len() + 1);
+        assert_eq!(buffer[0], COMPACT_ENCODING_VERSION);
+        let out = AggregateProof::<Bls12>::read_compact(&buffer[..]).unwrap();
+        assert_eq!(proof, out);
+
+        // unknown version
+        let mut invalid = buffer.clone();
+        invalid[0] = COMPACT_ENCODING_VERSION + 1;
+        AggregateProof::<Bls12>::read_compact(&invalid[..]).unwrap_err();
+
+        // the first coordinate of com_ab plus the field modulus
+        let modulus = hex_literal::hex!("abaafffffffffeb9ffff53b1feffab1e24f6b0f6a0d23067bf1285f3844b7764d7ac4b43b6a71b4b9ae67f39ea11011a");
+        let mut invalid = buffer.clone();
+        let mut carry = 0u16;
+        for (b, m) in invalid[1..49].iter_mut().zip(modulus.iter()) {
+            let sum = *b as u16 + *m as u16 + carry;
+            *b = sum as u8;
+            carry = sum >> 8;
+        }
+        assert_eq!(carry, 0);
+        AggregateProof::<Bls12>::read_compact(&invalid[..]).unwrap_err();
+
+        let proof = fake_proof_instance();
+        assert_eq!(proof.serialized_len(), 9_112);
+        let mut buffer = Vec::new();
+        proof.write_compact(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), proof.serialized_len() + 1);
+        let out = AggregateProofAndInstance::<Bls12>::read_compact(&buffer[..]).unwrap();
+        assert_eq!(proof, out);
+
+        // a scalar larger than the modulus
+        let scalar_offset = 1 + 4 + proof.pi_agg.serialized_len() + 3 * 4 * 48;
+        let mut invalid = buffer;
+        invalid[scalar_offset..scalar_offset + 32].fill(0xff);
+        AggregateProofAndInstance::<Bls12>::read_compact(&invalid[..]).unwrap_err();
+    }
 }
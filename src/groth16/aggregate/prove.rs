@@ -3,7 +3,7 @@ use std::ops::{AddAssign, MulAssign};
 
 use blstrs::Compress;
 use ff::{Field, PrimeField};
-use group::{prime::PrimeCurveAffine, Curve};
+use group::{prime::PrimeCurveAffine, Curve, Group};
 use log::{debug, info};
 use rayon::prelude::*;
 use serde::Serialize;
@@ -12,17 +12,28 @@ use super::{
     commit,
     commit::{VKey, WKey},
     compress, inner_product,
+    link::LinkPlan,
+    msm::variable_base::{
+        multiexp, multiexp_table, MultiexpGroup, MultiexpKernel, MultiexpKernels,
+    },
     poly::DensePolynomial,
     structured_scalar_power,
-    transcript::Transcript,
-    AggregateProof, AggregateProofAndInstance, GipaProof, KZGOpening, ProverSRS,
-    ProverSRSInputAggregation, TippMippProof,
+    transcript::{Challenge, Sha256Transcript, TranscriptProtocol},
+    AggregateProof, AggregateProofAndInstance, AggregateProofAndLinkedInstances, GipaProof,
+    InstanceLink, KZGOpening, MippProof, MultiAggregateProof, ProverSRS, ProverSRSInputAggregation,
+    ShardCommitment, ShardProof, ShardedAggregateProof, TippMippProof, TippProof,
 };
-use crate::groth16::{aggregate::AggregateVersion, multiscalar::*, Proof};
+use crate::groth16::{aggregate::AggregateVersion, multiscalar::*, Proof, VerifyingKey};
 use bellpepper_core::SynthesisError;
 use pairing::{Engine, MultiMillerLoop};
 
-/// Aggregate `n` zkSnark proofs, where `n` must be a power of two.
+/// Aggregate `n` zkSnark proofs, where `n` must be at least two.
+///
+/// If `n` is not a power of two, the proofs are padded to the next power of two by repeating the
+/// last proof. The number of real proofs is recorded in the aggregate proof and bound to the
+/// transcript, the verifier only needs the public inputs of the real proofs. The SRS must be
+/// specialized for the padded number of proofs, which `GenericSRS::specialize` takes care of.
+///
 /// WARNING: transcript_include represents everything that should be included in
 /// the transcript from outside the boundary of this function. This is especially
 /// relevant for ALL public inputs of ALL individual proofs. In the regular case,
@@ -45,228 +56,1594 @@ where
     <E::Fr as PrimeField>::Repr: Send + Sync,
     <E as Engine>::Gt: Compress + Serialize,
     E::G1: Serialize,
-    E::G1Affine: Serialize,
-    E::G2Affine: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        proofs,
+        version,
+    )
+}
+
+/// Aggregate `n` zkSnark proofs like `aggregate_proofs`, deriving the
+/// challenges with the transcript `T` instead of the default SHA-256 one. The
+/// aggregated proof must be verified with the same transcript, see
+/// `verify_aggregate_proof_with_transcript`.
+pub fn aggregate_proofs_with_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    proofs: &[Proof<E>],
+    version: AggregateVersion,
+) -> Result<AggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    version.check_unbound()?;
+    aggregate_proofs_inner::<E, T>(
+        srs,
+        transcript_include,
+        proofs,
+        version,
+        &MultiexpKernels::new(),
+    )
+}
+
+/// Aggregates the proofs like `aggregate_proofs_with_transcript`, for any version,
+/// with the multiexponentiation `kernels`.
+fn aggregate_proofs_inner<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    proofs: &[Proof<E>],
+    version: AggregateVersion,
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
+) -> Result<AggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
 {
     info!("aggregate_proofs [version {}]", version);
     if proofs.len() < 2 {
         return Err(SynthesisError::MalformedProofs(
-            "aggregating less than 2 proofs is not allowed".to_string(),
+            "aggregating less than 2 proofs is not allowed".to_string(),
+        ));
+    }
+
+    let num_proofs = proofs.len();
+    let padded_proofs;
+    let proofs = if num_proofs.is_power_of_two() {
+        proofs
+    } else {
+        padded_proofs = pad_proofs(proofs);
+        &padded_proofs[..]
+    };
+
+    if !srs.has_correct_len(proofs.len()) {
+        return Err(SynthesisError::MalformedSrs);
+    }
+    // We first commit to A B and C - these commitments are what the verifier
+    // will use later to verify the TIPP and MIPP proofs
+    par! {
+        let a = proofs.iter().map(|proof| proof.a).collect::<Vec<_>>(),
+        let b = proofs.iter().map(|proof| proof.b).collect::<Vec<_>>(),
+        let c = proofs.iter().map(|proof| proof.c).collect::<Vec<_>>()
+    };
+
+    // A and B are committed together in this scheme
+    // we need to take the reference so the macro doesn't consume the value
+    // first
+    let refa = &a;
+    let refb = &b;
+    let refc = &c;
+    try_par! {
+        let com_ab = commit::pair::<E>(&srs.vkey, &srs.wkey, refa, refb),
+        let com_c = commit::single_g1::<E>(&srs.vkey, refc)
+    };
+
+    aggregate_committed_proofs::<E, T>(
+        srs,
+        transcript_include,
+        num_proofs,
+        a,
+        b,
+        c,
+        com_ab,
+        com_c,
+        version,
+        kernels,
+    )
+}
+
+/// Aggregates the proofs given by their `a`, `b` and `c` vectors, padded to a
+/// power of two, once they are committed to in `com_ab` and `com_c`. `num_proofs`
+/// is the number of real proofs. The multiexponentiations run with the `kernels`.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn aggregate_committed_proofs<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    num_proofs: usize,
+    a: Vec<E::G1Affine>,
+    b: Vec<E::G2Affine>,
+    c: Vec<E::G1Affine>,
+    com_ab: commit::Output<E>,
+    com_c: commit::Output<E>,
+    version: AggregateVersion,
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
+) -> Result<AggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    let refa = &a;
+    let refc = &c;
+    let mut hcom_transcript = T::new("hcom")
+        .append_gt(&com_ab.0)
+        .append_gt(&com_ab.1)
+        .append_gt(&com_c.0)
+        .append_gt(&com_c.1);
+    // The number of real proofs is only bound if padding was applied, so that the transcript of
+    // proofs without padding stays the same.
+    if !num_proofs.is_power_of_two() {
+        hcom_transcript = hcom_transcript.append_u64(num_proofs as u64);
+    }
+    let hcom = hcom_transcript.into_challenge();
+
+    // Derive a random scalar to perform a linear combination of proofs
+    let r = T::new("random-r")
+        .append_scalar(&hcom)
+        .append_bytes(transcript_include)
+        .into_challenge();
+
+    // 1,r, r^2, r^3, r^4 ...
+    let r_vec: Vec<E::Fr> = structured_scalar_power(a.len(), &*r);
+    // 1,r^-1, r^-2, r^-3
+    let r_inv = r_vec
+        .par_iter()
+        .map(|ri| ri.invert().unwrap())
+        .collect::<Vec<_>>();
+
+    // B^{r}
+    let b_r = b
+        .par_iter()
+        .zip(r_vec.par_iter())
+        .map(|(bi, ri)| (bi.to_curve() * ri).to_affine())
+        .collect::<Vec<_>>();
+    let refb_r = &b_r;
+    let refr_vec = &r_vec;
+    try_par! {
+        // compute A * B^r for the verifier
+        let ip_ab = inner_product::pairing::<E>(refa, refb_r),
+        // compute C^r for the verifier
+        let agg_c = multiexp::<E::G1Affine>(kernels.g1(), refc, refr_vec)
+    };
+
+    // w^{r^{-1}}
+    let wkey_r_inv = srs.wkey.scale(&r_inv)?;
+
+    // we prove tipp and mipp using the same recursive loop
+    let mut tmipp = prove_tipp_mipp::<E, T>(
+        srs,
+        &a,
+        &b_r,
+        &c,
+        &wkey_r_inv,
+        &r_vec,
+        &ip_ab,
+        &agg_c,
+        &hcom,
+        version,
+        kernels,
+    )?;
+    debug_assert!({
+        let computed_com_ab = commit::pair::<E>(&srs.vkey, &wkey_r_inv, &a, &b_r).unwrap();
+        com_ab == computed_com_ab
+    });
+    tmipp.gipa.nproofs = num_proofs as u32;
+
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        ip_ab,
+        agg_c,
+        tmipp,
+    })
+}
+
+/// Aggregates proofs like `aggregate_proofs`, binding the aggregated proof to
+/// the statement it proves with `AggregateVersion::V3`. The transcript then
+/// absorbs the digest of the verifying key `vk`, the hash of the generic SRS
+/// `srs` was specialized from (see `ProverSRS::generic_hash`), the number of
+/// proofs, the number of public inputs and the `public_inputs` of all proofs,
+/// on top of `transcript_include`. With the other versions, the statement is
+/// not absorbed and the aggregated proof is the same as the one of
+/// `aggregate_proofs`.
+///
+/// The aggregated proof is verified by `verify_aggregate_proof_with_statement`.
+pub fn aggregate_proofs_with_statement<E>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    transcript_include: &[u8],
+    proofs: &[Proof<E>],
+    version: AggregateVersion,
+) -> Result<AggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_with_statement_and_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        vk,
+        public_inputs,
+        transcript_include,
+        proofs,
+        version,
+    )
+}
+
+/// Aggregates proofs like `aggregate_proofs_with_statement`, deriving the
+/// challenges with the transcript `T`, see `aggregate_proofs_with_transcript`.
+pub fn aggregate_proofs_with_statement_and_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    transcript_include: &[u8],
+    proofs: &[Proof<E>],
+    version: AggregateVersion,
+) -> Result<AggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    if public_inputs.len() != proofs.len() {
+        return Err(SynthesisError::MalformedProofs(
+            "public inputs length does not match the number of proofs".to_string(),
+        ));
+    }
+    if public_inputs
+        .iter()
+        .any(|inputs| inputs.len() + 1 != vk.ic.len())
+    {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let kernels = MultiexpKernels::new();
+    match version {
+        AggregateVersion::V1 | AggregateVersion::V2 => {
+            aggregate_proofs_inner::<E, T>(srs, transcript_include, proofs, version, &kernels)
+        }
+        AggregateVersion::V3 => {
+            let statement_include = statement_transcript_include::<E, T>(
+                &vk.digest(),
+                &srs.generic_hash,
+                vk.ic.len() - 1,
+                public_inputs,
+                transcript_include,
+            );
+            aggregate_proofs_inner::<E, T>(srs, &statement_include, proofs, version, &kernels)
+        }
+    }
+}
+
+/// Returns the transcript inclusion of an aggregated proof of version V3, which
+/// binds the statement the proof is for, see `aggregate_proofs_with_statement`.
+pub(super) fn statement_transcript_include<E, T>(
+    vk_digest: &[u8; 32],
+    srs_hash: &[u8],
+    num_inputs: usize,
+    public_inputs: &[Vec<E::Fr>],
+    transcript_include: &[u8],
+) -> Vec<u8>
+where
+    E: Engine,
+    T: TranscriptProtocol<E>,
+{
+    let transcript = T::new("statement")
+        .append_bytes(vk_digest)
+        .append_bytes(srs_hash)
+        .append_u64(public_inputs.len() as u64)
+        .append_u64(num_inputs as u64);
+    public_inputs
+        .iter()
+        .fold(transcript, |t, inputs| t.append_scalars(inputs))
+        .append_bytes(transcript_include)
+        .into_bytes()
+}
+
+/// Aggregate Groth16 proofs of different circuits. The proofs are grouped by
+/// verifying key, keys are compared by `VerifyingKey::digest`, and each group
+/// is aggregated on its own, see `aggregate_proofs`. A group with a single proof is
+/// aggregated with a copy of it. The SRS must be specialized for the largest
+/// group, the SRS of the smaller groups are derived from it.
+///
+/// WARNING: the same assumptions on transcript_include as for `aggregate_proofs` apply.
+pub fn aggregate_proofs_multi_vk<E>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    proofs: &[(&VerifyingKey<E>, &Proof<E>)],
+    version: AggregateVersion,
+) -> Result<MultiAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_multi_vk_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        proofs,
+        version,
+    )
+}
+
+/// Aggregates proofs of different circuits like `aggregate_proofs_multi_vk`,
+/// deriving the challenges with the transcript `T`.
+pub fn aggregate_proofs_multi_vk_with_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    proofs: &[(&VerifyingKey<E>, &Proof<E>)],
+    version: AggregateVersion,
+) -> Result<MultiAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    version.check_unbound()?;
+    aggregate_proofs_multi_vk_inner::<E, T>(srs, None, transcript_include, proofs, version)
+}
+
+/// Aggregates proofs of different circuits like `aggregate_proofs_multi_vk`,
+/// binding the aggregated proof of each circuit to its statement with
+/// `AggregateVersion::V3`, see `aggregate_proofs_with_statement`.
+/// `public_inputs` contains the public inputs of every proof, in the order of
+/// `proofs`. With the other versions, the statements are not absorbed and the
+/// aggregated proof is the same as the one of `aggregate_proofs_multi_vk`.
+///
+/// The aggregated proof is verified by `verify_aggregate_proof_multi_vk`.
+pub fn aggregate_proofs_multi_vk_with_statement<E>(
+    srs: &ProverSRS<E>,
+    public_inputs: &[Vec<E::Fr>],
+    transcript_include: &[u8],
+    proofs: &[(&VerifyingKey<E>, &Proof<E>)],
+    version: AggregateVersion,
+) -> Result<MultiAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_multi_vk_with_statement_and_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        public_inputs,
+        transcript_include,
+        proofs,
+        version,
+    )
+}
+
+/// Aggregates proofs of different circuits like
+/// `aggregate_proofs_multi_vk_with_statement`, deriving the challenges with the
+/// transcript `T`.
+pub fn aggregate_proofs_multi_vk_with_statement_and_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    public_inputs: &[Vec<E::Fr>],
+    transcript_include: &[u8],
+    proofs: &[(&VerifyingKey<E>, &Proof<E>)],
+    version: AggregateVersion,
+) -> Result<MultiAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    if public_inputs.len() != proofs.len() {
+        return Err(SynthesisError::MalformedProofs(
+            "public inputs length does not match the number of proofs".to_string(),
+        ));
+    }
+    if public_inputs
+        .iter()
+        .zip(proofs.iter())
+        .any(|(inputs, (vk, _))| inputs.len() + 1 != vk.ic.len())
+    {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let statements = match version {
+        AggregateVersion::V1 | AggregateVersion::V2 => None,
+        AggregateVersion::V3 => Some(public_inputs),
+    };
+    aggregate_proofs_multi_vk_inner::<E, T>(srs, statements, transcript_include, proofs, version)
+}
+
+/// Aggregates proofs of different circuits like
+/// `aggregate_proofs_multi_vk_with_transcript`. If `public_inputs` is given,
+/// the aggregated proof of each circuit is bound to its statement.
+fn aggregate_proofs_multi_vk_inner<E, T>(
+    srs: &ProverSRS<E>,
+    public_inputs: Option<&[Vec<E::Fr>]>,
+    transcript_include: &[u8],
+    proofs: &[(&VerifyingKey<E>, &Proof<E>)],
+    version: AggregateVersion,
+) -> Result<MultiAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    info!("aggregate_proofs_multi_vk [version {}]", version);
+    if proofs.is_empty() {
+        return Err(SynthesisError::MalformedProofs(
+            "no proofs to aggregate".to_string(),
+        ));
+    }
+
+    // Group the proofs and their public inputs by verifying key.
+    let mut groups: Vec<MultiVkGroup<'_, E>> = Vec::new();
+    for (i, (vk, proof)) in proofs.iter().enumerate() {
+        let digest = vk.digest();
+        let group = match groups.iter_mut().position(|g| g.digest == digest) {
+            Some(j) => &mut groups[j],
+            None => {
+                groups.push(MultiVkGroup {
+                    digest,
+                    vk,
+                    proofs: Vec::new(),
+                    public_inputs: Vec::new(),
+                });
+                groups.last_mut().unwrap()
+            }
+        };
+        group.proofs.push((*proof).clone());
+        if let Some(public_inputs) = public_inputs {
+            group.public_inputs.push(public_inputs[i].clone());
+        }
+    }
+
+    let num_groups = groups.len();
+    let kernels = MultiexpKernels::new();
+    let groups = groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut group)| {
+            if group.proofs.len() == 1 {
+                group.proofs.push(group.proofs[0].clone());
+                if public_inputs.is_some() {
+                    group.public_inputs.push(group.public_inputs[0].clone());
+                }
+            }
+            if group.proofs.len().next_power_of_two() > srs.n {
+                return Err(SynthesisError::MalformedSrs);
+            }
+            let group_srs = srs.specialize(group.proofs.len());
+            let mut group_include =
+                multi_vk_transcript_include::<E, T>(transcript_include, i, num_groups);
+            if public_inputs.is_some() {
+                group_include = statement_transcript_include::<E, T>(
+                    &group.digest,
+                    &srs.generic_hash,
+                    group.vk.ic.len() - 1,
+                    &group.public_inputs,
+                    &group_include,
+                );
+            }
+            aggregate_proofs_inner::<E, T>(
+                &group_srs,
+                &group_include,
+                &group.proofs,
+                version,
+                &kernels,
+            )
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(MultiAggregateProof { groups })
+}
+
+/// The proofs of one circuit of an aggregation of proofs of different circuits.
+struct MultiVkGroup<'a, E: MultiMillerLoop> {
+    digest: [u8; 32],
+    vk: &'a VerifyingKey<E>,
+    proofs: Vec<Proof<E>>,
+    /// Only filled if the statements are bound.
+    public_inputs: Vec<Vec<E::Fr>>,
+}
+
+/// Returns the transcript inclusion of the `group`-th aggregation of an
+/// aggregation of proofs of different circuits.
+pub(super) fn multi_vk_transcript_include<E, T>(
+    transcript_include: &[u8],
+    group: usize,
+    num_groups: usize,
+) -> Vec<u8>
+where
+    E: Engine,
+    T: TranscriptProtocol<E>,
+{
+    T::new("multi-vk")
+        .append_bytes(transcript_include)
+        .append_u64(group as u64)
+        .append_u64(num_groups as u64)
+        .into_bytes()
+}
+
+/// Pads the proofs to the next power of two by repeating the last proof.
+fn pad_proofs<E: Engine>(proofs: &[Proof<E>]) -> Vec<Proof<E>> {
+    let last = proofs.last().expect("no proofs to pad");
+    let mut padded = proofs.to_vec();
+    padded.resize(proofs.len().next_power_of_two(), last.clone());
+    padded
+}
+
+/// Aggregates proofs whose statements are chained: the second half of the
+/// public inputs of each proof equals the first half of the public inputs of the
+/// next proof, see `InstanceLink::chain`. The verifier only needs the first half
+/// of the public inputs of the first proof and the second half of the public
+/// inputs of the last proof. This is the aggregation of
+/// `aggregate_proofs_and_linked_instances` with the chain links, kept for its
+/// proof format. The number of proofs must be a power of two.
+///
+/// These aggregations don't support `AggregateVersion::V3`. The public inputs
+/// are bound through their commitments, but binding the aggregated proof to the
+/// verifying key and to the generic SRS is left to the caller, e.g. by adding
+/// `VerifyingKey::digest` and `GenericSRS::hash` to `transcript_include`.
+///
+/// WARNING: the same assumptions on transcript_include as for `aggregate_proofs` apply.
+pub fn aggregate_proofs_and_instances<E: Engine + std::fmt::Debug>(
+    srs: &ProverSRSInputAggregation<E>,
+    transcript_include: &[u8],
+    statements: &[Vec<E::Fr>],
+    proofs: &[Proof<E>],
+    version: AggregateVersion,
+) -> Result<AggregateProofAndInstance<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_and_instances_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        statements,
+        proofs,
+        version,
+    )
+}
+
+/// Aggregates proofs with chained statements like
+/// `aggregate_proofs_and_instances`, deriving the challenges with the
+/// transcript `T`.
+pub fn aggregate_proofs_and_instances_with_transcript<E, T>(
+    srs: &ProverSRSInputAggregation<E>,
+    transcript_include: &[u8],
+    statements: &[Vec<E::Fr>],
+    proofs: &[Proof<E>],
+    version: AggregateVersion,
+) -> Result<AggregateProofAndInstance<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    version.check_unbound()?;
+    if statements.len() < 2 {
+        return Err(SynthesisError::MalformedProofs(
+            "aggregating less than 2 proofs is not allowed".to_string(),
+        ));
+    }
+    let n = statements[0].len();
+    if n % 2 != 0 {
+        return Err(SynthesisError::MalformedProofs(
+            "statements must have an even number of inputs".to_string(),
+        ));
+    }
+
+    let links = InstanceLink::chain(n);
+    let proof = aggregate_linked_inputs::<E, T>(srs, statements, proofs, &links, version, |com| {
+        instance_transcript_include::<E, T>(com, transcript_include)
+    })?;
+
+    Ok(AggregateProofAndInstance {
+        num_inputs: u32::try_from(n).expect("too many statements"),
+        pi_agg: proof.pi_agg,
+        com_f: proof.com_inputs,
+        com_w0: proof.com_shifts,
+        com_wd: proof.com_bounds,
+        f_eval: proof.input_evals,
+        f_eval_proof: proof.input_eval_proofs,
+    })
+}
+
+/// Aggregates proofs whose public inputs are related by the `links`, see
+/// `InstanceLink`. This generalizes `aggregate_proofs_and_instances`, which
+/// only supports the relation given by `InstanceLink::chain`. The verifier only
+/// needs the public inputs returned by `linked_instance_boundary`, instead of
+/// the statements of all proofs. The number of proofs must be a power of two.
+///
+/// These aggregations don't support `AggregateVersion::V3`. The public inputs
+/// are bound through their commitments, but binding the aggregated proof to the
+/// verifying key and to the generic SRS is left to the caller, e.g. by adding
+/// `VerifyingKey::digest` and `GenericSRS::hash` to `transcript_include`.
+///
+/// WARNING: the same assumptions on transcript_include as for `aggregate_proofs` apply.
+pub fn aggregate_proofs_and_linked_instances<E>(
+    srs: &ProverSRSInputAggregation<E>,
+    transcript_include: &[u8],
+    statements: &[Vec<E::Fr>],
+    proofs: &[Proof<E>],
+    links: &[InstanceLink],
+    version: AggregateVersion,
+) -> Result<AggregateProofAndLinkedInstances<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_and_linked_instances_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        statements,
+        proofs,
+        links,
+        version,
+    )
+}
+
+/// Aggregates proofs with linked public inputs like
+/// `aggregate_proofs_and_linked_instances`, deriving the challenges with the
+/// transcript `T`.
+pub fn aggregate_proofs_and_linked_instances_with_transcript<E, T>(
+    srs: &ProverSRSInputAggregation<E>,
+    transcript_include: &[u8],
+    statements: &[Vec<E::Fr>],
+    proofs: &[Proof<E>],
+    links: &[InstanceLink],
+    version: AggregateVersion,
+) -> Result<AggregateProofAndLinkedInstances<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    info!(
+        "aggregate_proofs_and_linked_instances [version {}]",
+        version
+    );
+    version.check_unbound()?;
+    aggregate_linked_inputs::<E, T>(srs, statements, proofs, links, version, |com| {
+        linked_instance_transcript_include::<E, T>(links, com, transcript_include)
+    })
+}
+
+/// Returns the transcript inclusion of an aggregated proof of
+/// `aggregate_proofs_and_instances`.
+pub(super) fn instance_transcript_include<E, T>(
+    com: &LinkedInputCommitments<'_, E>,
+    transcript_include: &[u8],
+) -> Vec<u8>
+where
+    E: Engine,
+    T: TranscriptProtocol<E>,
+{
+    T::new("transcript-with-coms")
+        .append_g1s(&to_affine::<E>(com.com_inputs))
+        .append_g1s(&to_affine::<E>(com.com_shifts))
+        .append_g1s(&to_affine::<E>(com.com_bounds))
+        .append_bytes(transcript_include)
+        .into_bytes()
+}
+
+/// Returns the transcript inclusion of an aggregated proof of
+/// `aggregate_proofs_and_linked_instances`.
+pub(super) fn linked_instance_transcript_include<E, T>(
+    links: &[InstanceLink],
+    com: &LinkedInputCommitments<'_, E>,
+    transcript_include: &[u8],
+) -> Vec<u8>
+where
+    E: Engine,
+    T: TranscriptProtocol<E>,
+{
+    links
+        .iter()
+        .fold(
+            T::new("transcript-with-links").append_u64(links.len() as u64),
+            |t, link| {
+                t.append_u64(link.from as u64)
+                    .append_u64(link.to as u64)
+                    .append_u64(link.offset as u64)
+            },
+        )
+        .append_scalars(com.boundary)
+        .append_g1s(&to_affine::<E>(com.com_inputs))
+        .append_g1s(&to_affine::<E>(com.com_shifts))
+        .append_g1s(&to_affine::<E>(com.com_bounds))
+        .append_bytes(transcript_include)
+        .into_bytes()
+}
+
+fn to_affine<E: Engine>(points: &[E::G1]) -> Vec<E::G1Affine> {
+    points.iter().map(|p| p.to_affine()).collect()
+}
+
+/// The commitments to the public inputs of an aggregation with linked instances,
+/// which are absorbed by its transcript.
+pub(super) struct LinkedInputCommitments<'a, E: Engine> {
+    pub boundary: &'a [E::Fr],
+    pub com_inputs: &'a [E::G1],
+    pub com_shifts: &'a [E::G1],
+    pub com_bounds: &'a [E::G1],
+}
+
+/// Aggregates the proofs and commits to the polynomials of their public inputs
+/// following the `LinkPlan` of the `links`. `transcript_include` returns the
+/// transcript inclusion of the aggregated proof, which must absorb the
+/// commitments.
+fn aggregate_linked_inputs<E, T>(
+    srs: &ProverSRSInputAggregation<E>,
+    statements: &[Vec<E::Fr>],
+    proofs: &[Proof<E>],
+    links: &[InstanceLink],
+    version: AggregateVersion,
+    transcript_include: impl FnOnce(&LinkedInputCommitments<'_, E>) -> Vec<u8>,
+) -> Result<AggregateProofAndLinkedInstances<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    if statements.len() < 2 {
+        return Err(SynthesisError::MalformedProofs(
+            "aggregating less than 2 proofs is not allowed".to_string(),
+        ));
+    }
+    if statements.len() != proofs.len() {
+        return Err(SynthesisError::MalformedProofs(
+            "number of statements does not match the number of proofs".to_string(),
+        ));
+    }
+    // The instance aggregation does not support padding.
+    if !proofs.len().is_power_of_two() {
+        return Err(SynthesisError::NonPowerOfTwo);
+    }
+    let num_proofs = proofs.len();
+    let num_inputs = statements[0].len();
+    if statements.iter().any(|s| s.len() != num_inputs) {
+        return Err(SynthesisError::MalformedProofs(
+            "all statements must be equally sized".to_string(),
+        ));
+    }
+
+    let plan = LinkPlan::new(links, num_inputs, num_proofs)?;
+    for link in links {
+        if (0..num_proofs - link.offset)
+            .any(|i| statements[i + link.offset][link.to] != statements[i][link.from])
+        {
+            return Err(SynthesisError::MalformedProofs(
+                "statements do not satisfy the instance links".to_string(),
+            ));
+        }
+    }
+    let boundary: Vec<E::Fr> = plan
+        .boundary
+        .iter()
+        .map(|&(i, j)| statements[i][j])
+        .collect();
+
+    let kernels = MultiexpKernels::new();
+    let g1_kern = kernels.g1();
+    let commit = |poly: &[E::Fr], table: &MultiscalarPrecompOwned<E::G1Affine>| {
+        let getter = |i: usize| -> <E::Fr as PrimeField>::Repr { poly[i].to_repr() };
+        multiexp_table::<E::G1Affine, _>(g1_kern, table, &ScalarList::Getter(getter, poly.len()))
+    };
+
+    // The coefficients of the polynomial of input j are the inputs j of all statements.
+    let polys: Vec<Vec<E::Fr>> = plan
+        .committed
+        .iter()
+        .map(|&j| statements.iter().map(|s| s[j]).collect())
+        .collect();
+    let com_inputs: Vec<E::G1> = polys
+        .par_iter()
+        .map(|poly| commit(poly, &srs.g_alpha_powers_table))
+        .collect();
+    // The polynomial without its first k coefficients proves them: F - F_0 = X W_1,
+    // W_1 - F_1 = X W_2, ...
+    let com_shifts: Vec<E::G1> = polys
+        .par_iter()
+        .zip(plan.shifts.par_iter())
+        .flat_map_iter(|(poly, &k)| {
+            (1..=k).map(move |t| -commit(&poly[t..], &srs.g_alpha_powers_table))
+        })
+        .collect();
+    // Check F^x^(n - d) exists i.e. that F is bounded
+    let com_bounds: Vec<E::G1> = polys
+        .par_iter()
+        .map(|poly| -commit(poly, &srs.g_alpha_powers_end_table))
+        .collect();
+
+    let transcript_new = transcript_include(&LinkedInputCommitments {
+        boundary: &boundary,
+        com_inputs: &com_inputs,
+        com_shifts: &com_shifts,
+        com_bounds: &com_bounds,
+    });
+
+    let pi_agg = aggregate_proofs_inner::<E, T>(srs, &transcript_new, proofs, version, &kernels)?;
+
+    let hcom = T::new("hcom")
+        .append_gt(&pi_agg.com_ab.0)
+        .append_gt(&pi_agg.com_ab.1)
+        .append_gt(&pi_agg.com_c.0)
+        .append_gt(&pi_agg.com_c.1)
+        .into_challenge();
+
+    // Random linear combination of proofs
+    let r = T::new("random-r")
+        .append_scalar(&hcom)
+        .append_bytes(&transcript_new)
+        .into_challenge();
+
+    let input_evals: Vec<E::Fr> = polys
+        .iter()
+        .map(|poly| poly.iter().rev().fold(E::Fr::ZERO, |acc, c| acc * *r + c))
+        .collect();
+    let g1_kern = kernels.g1();
+    let input_eval_proofs = polys
+        .into_par_iter()
+        .zip(input_evals.par_iter())
+        .map(|(poly, eval)| {
+            create_kzg_opening_for_instance::<E>(
+                g1_kern,
+                &srs.g_alpha_powers_table,
+                DensePolynomial::from_coeffs(poly),
+                *eval,
+                &r,
+            )
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(AggregateProofAndLinkedInstances {
+        pi_agg,
+        com_inputs,
+        com_shifts,
+        com_bounds,
+        input_evals,
+        input_eval_proofs,
+    })
+}
+
+/// Aggregates proofs split into shards, so that the total number of proofs is
+/// not bounded by the size of the SRS. Every shard holds between one and `srs.n`
+/// proofs and is padded to `srs.n` proofs by repeating its last proof.
+///
+/// The shards are first committed to and proven independently, see
+/// `commit_shard` and `prove_shard`, which can run in different processes. The
+/// shard proofs are then tied together by `aggregate_shards` with a single MIPP
+/// proof over a random linear combination of the C of all shards, so that the
+/// verifier checks one Groth16 equation for all proofs. The verifier still checks
+/// the TIPP proof of every shard, see `verify_sharded_aggregate_proof`.
+///
+/// The transcript absorbs the hash of the generic SRS `srs` was specialized
+/// from (see `ProverSRS::generic_hash`), but binding the aggregated proof to the
+/// verifying key is left to the caller, e.g. by adding `VerifyingKey::digest` to
+/// `transcript_include`.
+///
+/// WARNING: the same assumptions on transcript_include as for `aggregate_proofs` apply.
+pub fn aggregate_proofs_sharded<E>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    shards: &[&[Proof<E>]],
+) -> Result<ShardedAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_proofs_sharded_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        shards,
+    )
+}
+
+/// Aggregates shards of proofs like `aggregate_proofs_sharded`, deriving the
+/// challenges with the transcript `T`.
+pub fn aggregate_proofs_sharded_with_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    shards: &[&[Proof<E>]],
+) -> Result<ShardedAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    info!("aggregate_proofs_sharded [{} shards]", shards.len());
+    let commitments = shards
+        .iter()
+        .map(|proofs| commit_shard(srs, proofs))
+        .collect::<Result<Vec<_>, _>>()?;
+    let r = sharded_challenge_r::<E, T>(&srs.generic_hash, srs.n, &commitments, transcript_include);
+
+    let kernels = MultiexpKernels::new();
+    let shard_proofs = shards
+        .iter()
+        .enumerate()
+        .map(|(i, proofs)| prove_shard_inner::<E, T>(srs, &r, &commitments, i, proofs, &kernels))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    aggregate_shards_inner::<E, T>(srs, &r, commitments, shard_proofs, shards, &kernels)
+}
+
+/// Commits to the proofs of a shard, the first step of a sharded aggregation,
+/// see `aggregate_proofs_sharded`. The shard holds between one and `srs.n`
+/// proofs.
+pub fn commit_shard<E>(
+    srs: &ProverSRS<E>,
+    proofs: &[Proof<E>],
+) -> Result<ShardCommitment<E>, SynthesisError>
+where
+    E: MultiMillerLoop,
+{
+    let padded = pad_shard(srs, proofs)?;
+    let refproofs = &padded;
+    par! {
+        let a = refproofs.iter().map(|proof| proof.a).collect::<Vec<_>>(),
+        let b = refproofs.iter().map(|proof| proof.b).collect::<Vec<_>>(),
+        let c = refproofs.iter().map(|proof| proof.c).collect::<Vec<_>>()
+    };
+
+    let refa = &a;
+    let refb = &b;
+    let refc = &c;
+    try_par! {
+        let com_ab = commit::pair::<E>(&srs.vkey, &srs.wkey, refa, refb),
+        let com_c = commit::single_g1::<E>(&srs.vkey, refc)
+    };
+
+    Ok(ShardCommitment {
+        nproofs: u32::try_from(proofs.len()).expect("too many proofs"),
+        com_ab,
+        com_c,
+    })
+}
+
+/// Proves the TIPP relation of the `shard`-th shard of a sharded aggregation,
+/// the second step of `aggregate_proofs_sharded`. `commitments` are the
+/// commitments of all shards, in order, from which the random linear combination
+/// of the proofs is derived. `proofs` are the proofs of the shard.
+///
+/// WARNING: the same assumptions on transcript_include as for `aggregate_proofs` apply. It must be
+/// the same for all shards.
+pub fn prove_shard<E>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    commitments: &[ShardCommitment<E>],
+    shard: usize,
+    proofs: &[Proof<E>],
+) -> Result<ShardProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    prove_shard_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        commitments,
+        shard,
+        proofs,
+    )
+}
+
+/// Proves the TIPP relation of a shard like `prove_shard`, deriving the
+/// challenges with the transcript `T`.
+pub fn prove_shard_with_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    commitments: &[ShardCommitment<E>],
+    shard: usize,
+    proofs: &[Proof<E>],
+) -> Result<ShardProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    let r = sharded_challenge_r::<E, T>(&srs.generic_hash, srs.n, commitments, transcript_include);
+    let kernels = MultiexpKernels::new();
+    prove_shard_inner::<E, T>(srs, &r, commitments, shard, proofs, &kernels)
+}
+
+/// Combines the shard proofs of a sharded aggregation with a MIPP proof over
+/// the C of all shards, the last step of `aggregate_proofs_sharded`.
+/// `commitments` and `shard_proofs` are the commitments and proofs of all
+/// shards, in order, and `proofs` the proofs of every shard.
+///
+/// WARNING: the same assumptions on transcript_include as for `aggregate_proofs` apply. It must be
+/// the one the shards were proven with.
+pub fn aggregate_shards<E>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    commitments: Vec<ShardCommitment<E>>,
+    shard_proofs: Vec<ShardProof<E>>,
+    proofs: &[&[Proof<E>]],
+) -> Result<ShardedAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize + MultiexpGroup,
+    E::G2Affine: Serialize + MultiexpGroup,
+{
+    aggregate_shards_with_transcript::<E, Sha256Transcript<E>>(
+        srs,
+        transcript_include,
+        commitments,
+        shard_proofs,
+        proofs,
+    )
+}
+
+/// Combines the shard proofs of a sharded aggregation like `aggregate_shards`,
+/// deriving the challenges with the transcript `T`.
+pub fn aggregate_shards_with_transcript<E, T>(
+    srs: &ProverSRS<E>,
+    transcript_include: &[u8],
+    commitments: Vec<ShardCommitment<E>>,
+    shard_proofs: Vec<ShardProof<E>>,
+    proofs: &[&[Proof<E>]],
+) -> Result<ShardedAggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    let r = sharded_challenge_r::<E, T>(&srs.generic_hash, srs.n, &commitments, transcript_include);
+    let kernels = MultiexpKernels::new();
+    aggregate_shards_inner::<E, T>(srs, &r, commitments, shard_proofs, proofs, &kernels)
+}
+
+/// Returns the random scalar r of the linear combination of the proofs of a
+/// sharded aggregation, which is the same for all shards. It is derived from the
+/// hash of the generic SRS, the number of proofs the shards are padded to and
+/// the commitments of all shards.
+pub(super) fn sharded_challenge_r<E, T>(
+    generic_hash: &[u8],
+    shard_size: usize,
+    commitments: &[ShardCommitment<E>],
+    transcript_include: &[u8],
+) -> Challenge<E>
+where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+{
+    commitments
+        .iter()
+        .fold(
+            T::new("sharded-random-r")
+                .append_bytes(generic_hash)
+                .append_u64(shard_size as u64)
+                .append_u64(commitments.len() as u64),
+            |t, com| {
+                t.append_u64(com.nproofs as u64)
+                    .append_gt(&com.com_ab.0)
+                    .append_gt(&com.com_ab.1)
+                    .append_gt(&com.com_c.0)
+                    .append_gt(&com.com_c.1)
+            },
+        )
+        .append_bytes(transcript_include)
+        .into_challenge()
+}
+
+/// Returns the random scalar s of the linear combination of the Groth16
+/// equations of the shards of a sharded aggregation. It is derived from r and
+/// the inner pairing products of all shards.
+pub(super) fn sharded_challenge_s<E, T>(r: &E::Fr, shard_proofs: &[ShardProof<E>]) -> Challenge<E>
+where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+{
+    shard_proofs
+        .iter()
+        .fold(
+            T::new("sharded-random-s")
+                .append_scalar(r)
+                .append_u64(shard_proofs.len() as u64),
+            |t, shard| t.append_gt(&shard.ip_ab),
+        )
+        .into_challenge()
+}
+
+/// Pads the proofs of a shard to the size of the SRS by repeating the last
+/// proof.
+fn pad_shard<E: Engine>(
+    srs: &ProverSRS<E>,
+    proofs: &[Proof<E>],
+) -> Result<Vec<Proof<E>>, SynthesisError> {
+    if proofs.is_empty() {
+        return Err(SynthesisError::MalformedProofs(
+            "no proofs in the shard".to_string(),
         ));
     }
-    if !proofs.len().is_power_of_two() {
-        return Err(SynthesisError::NonPowerOfTwo);
-    }
-
-    if !srs.has_correct_len(proofs.len()) {
+    if srs.n < 2 || !srs.has_correct_len(srs.n) || proofs.len() > srs.n {
         return Err(SynthesisError::MalformedSrs);
     }
-    // We first commit to A B and C - these commitments are what the verifier
-    // will use later to verify the TIPP and MIPP proofs
-    par! {
-        let a = proofs.iter().map(|proof| proof.a).collect::<Vec<_>>(),
-        let b = proofs.iter().map(|proof| proof.b).collect::<Vec<_>>(),
-        let c = proofs.iter().map(|proof| proof.c).collect::<Vec<_>>()
-    };
-
-    // A and B are committed together in this scheme
-    // we need to take the reference so the macro doesn't consume the value
-    // first
-    let refa = &a;
-    let refb = &b;
-    let refc = &c;
-    try_par! {
-        let com_ab = commit::pair::<E>(&srs.vkey, &srs.wkey, refa, refb),
-        let com_c = commit::single_g1::<E>(&srs.vkey, refc)
-    };
-
-    let hcom = Transcript::<E>::new("hcom")
-        .write(&com_ab)
-        .write(&com_c)
-        .into_challenge();
+    let mut padded = proofs.to_vec();
+    padded.resize(srs.n, proofs[proofs.len() - 1].clone());
+    Ok(padded)
+}
 
-    // Derive a random scalar to perform a linear combination of proofs
-    let r = Transcript::<E>::new("random-r")
-        .write(&hcom)
-        .write(&transcript_include)
-        .into_challenge();
+/// Proves the TIPP relation of a shard like `prove_shard_with_transcript`,
+/// with the random scalar `r` and the multiexponentiation `kernels`.
+fn prove_shard_inner<E, T>(
+    srs: &ProverSRS<E>,
+    r: &E::Fr,
+    commitments: &[ShardCommitment<E>],
+    shard: usize,
+    proofs: &[Proof<E>],
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
+) -> Result<ShardProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    let commitment = commitments.get(shard).ok_or_else(|| {
+        SynthesisError::MalformedProofs("no commitment for the shard".to_string())
+    })?;
+    if commitment.nproofs as usize != proofs.len() {
+        return Err(SynthesisError::MalformedProofs(
+            "number of proofs does not match the shard commitment".to_string(),
+        ));
+    }
+    let proofs = pad_shard(srs, proofs)?;
 
     // 1,r, r^2, r^3, r^4 ...
-    let r_vec: Vec<E::Fr> = structured_scalar_power(proofs.len(), &*r);
+    let r_vec: Vec<E::Fr> = structured_scalar_power(srs.n, r);
     // 1,r^-1, r^-2, r^-3
     let r_inv = r_vec
         .par_iter()
         .map(|ri| ri.invert().unwrap())
         .collect::<Vec<_>>();
 
+    let a = proofs.iter().map(|proof| proof.a).collect::<Vec<_>>();
     // B^{r}
-    let b_r = b
+    let b_r = proofs
         .par_iter()
         .zip(r_vec.par_iter())
-        .map(|(bi, ri)| (bi.to_curve() * ri).to_affine())
+        .map(|(proof, ri)| (proof.b.to_curve() * ri).to_affine())
         .collect::<Vec<_>>();
-    let refb_r = &b_r;
-    let refr_vec = &r_vec;
-    try_par! {
-        // compute A * B^r for the verifier
-        let ip_ab = inner_product::pairing::<E>(refa, refb_r),
-        // compute C^r for the verifier
-        let agg_c = inner_product::multiexponentiation::<E::G1Affine>(refc, refr_vec)
-    };
-
+    // compute A * B^r for the verifier
+    let ip_ab = inner_product::pairing::<E>(&a, &b_r)?;
     // w^{r^{-1}}
     let wkey_r_inv = srs.wkey.scale(&r_inv)?;
-
-    // we prove tipp and mipp using the same recursive loop
-    let tmipp = prove_tipp_mipp::<E>(
-        srs,
-        &a,
-        &b_r,
-        &c,
-        &wkey_r_inv,
-        &r_vec,
-        &ip_ab,
-        &agg_c,
-        &hcom,
-        version,
-    )?;
     debug_assert!({
         let computed_com_ab = commit::pair::<E>(&srs.vkey, &wkey_r_inv, &a, &b_r).unwrap();
-        com_ab == computed_com_ab
+        commitment.com_ab == computed_com_ab
     });
 
-    Ok(AggregateProof {
-        com_ab,
-        com_c,
-        ip_ab,
-        agg_c,
-        tmipp,
-    })
+    let tipp = prove_tipp::<E, T>(srs, a, b_r, wkey_r_inv, &ip_ab, r, shard, kernels)?;
+    Ok(ShardProof { ip_ab, tipp })
 }
 
-pub fn aggregate_proofs_and_instances<E: Engine + std::fmt::Debug>(
-    srs: &ProverSRSInputAggregation<E>,
-    transcript_include: &[u8],
-    statements: &[Vec<E::Fr>],
-    proofs: &[Proof<E>],
-    version: AggregateVersion,
-) -> Result<AggregateProofAndInstance<E>, SynthesisError>
+/// Combines the shard proofs like `aggregate_shards_with_transcript`, with the
+/// random scalar `r` and the multiexponentiation `kernels`.
+fn aggregate_shards_inner<E, T>(
+    srs: &ProverSRS<E>,
+    r: &E::Fr,
+    commitments: Vec<ShardCommitment<E>>,
+    shard_proofs: Vec<ShardProof<E>>,
+    proofs: &[&[Proof<E>]],
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
+) -> Result<ShardedAggregateProof<E>, SynthesisError>
 where
     E: MultiMillerLoop + std::fmt::Debug,
-    E::Fr: Serialize,
     <E::Fr as PrimeField>::Repr: Send + Sync,
-    <E as Engine>::Gt: Compress + Serialize,
-    E::G1: Serialize,
-    E::G1Affine: Serialize,
-    E::G2Affine: Serialize,
+    <E as Engine>::Gt: Compress,
+    T: TranscriptProtocol<E>,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
 {
-    if statements.len() < 2 {
+    if proofs.is_empty() {
         return Err(SynthesisError::MalformedProofs(
-            "aggregating less than 2 proofs is not allowed".to_string(),
+            "no shards to aggregate".to_string(),
         ));
     }
-    assert!(statements.len() > 1);
-    let n = statements[0].len();
-    for s in statements {
-        if s.len() != n {
+    if commitments.len() != proofs.len() || shard_proofs.len() != proofs.len() {
+        return Err(SynthesisError::MalformedProofs(
+            "number of shard commitments or proofs does not match the number of shards".to_string(),
+        ));
+    }
+    for (commitment, shard) in commitments.iter().zip(proofs.iter()) {
+        if commitment.nproofs as usize != shard.len() {
             return Err(SynthesisError::MalformedProofs(
-                "all statements must be equally sized".to_string(),
+                "number of proofs does not match the shard commitment".to_string(),
             ));
         }
     }
-    let mut com_f: Vec<E::G1> = Vec::new();
-    let mut com_w0: Vec<E::G1> = Vec::new();
-    let mut com_wd: Vec<E::G1> = Vec::new();
-    let mut f_eval: Vec<E::Fr> = Vec::new();
-    let mut f_eval_proof: Vec<E::G1> = Vec::new();
-
-    let mut poly_f: Vec<Vec<E::Fr>> = Vec::new();
-
-    for j in 0..n / 2 {
-        let mut poly_f_j: Vec<E::Fr> = Vec::new();
-        let mut poly_w0: Vec<E::Fr> = Vec::new();
-        for i in 0..proofs.len() {
-            poly_f_j.push(statements[i][j]);
-            if i < (proofs.len() - 1) {
-                poly_w0.push(statements[i + 1][j]);
-            }
-        }
+    let padded = proofs
+        .iter()
+        .map(|shard| pad_shard(srs, shard))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let s = sharded_challenge_s::<E, T>(r, &shard_proofs);
+    // 1, s, s^2, ... for the shards
+    let s_vec: Vec<E::Fr> = structured_scalar_power(padded.len(), &*s);
+
+    // The C of all shards are combined with s: C' = \sum_j C_j^{s^j}, it is
+    // committed to by \prod_j com_c_j^{s^j} under the same key.
+    let c_combined = (0..srs.n)
+        .into_par_iter()
+        .map(|i| {
+            padded
+                .iter()
+                .zip(s_vec.iter())
+                .fold(E::G1::identity(), |acc, (shard, sj)| acc + shard[i].c * sj)
+                .to_affine()
+        })
+        .collect::<Vec<_>>();
+    debug_assert!({
+        let com_c = commit::single_g1::<E>(&srs.vkey, &c_combined).unwrap();
+        let combined = commitments
+            .iter()
+            .zip(s_vec.iter())
+            .fold((E::Gt::identity(), E::Gt::identity()), |acc, (com, sj)| {
+                (acc.0 + com.com_c.0 * sj, acc.1 + com.com_c.1 * sj)
+            });
+        com_c == combined
+    });
+
+    // 1,r, r^2, r^3, r^4 ...
+    let r_vec: Vec<E::Fr> = structured_scalar_power(srs.n, r);
+    // compute C'^r for the verifier, which is \sum_j s^j C_j^r
+    let agg_c = multiexp::<E::G1Affine>(kernels.g1(), &c_combined, &r_vec)?;
 
-        let poly_f_cp = poly_f_j.clone();
-        let poly_f_cp_2 = poly_f_j.clone();
+    let mipp = prove_mipp::<E, T>(srs, c_combined, r_vec, &agg_c, &s, kernels)?;
 
-        let calc_multiscalar = |left: &[E::Fr], table, len: usize| {
-            let getter = |i: usize| -> <E::Fr as PrimeField>::Repr { left[i].to_repr() };
-            par_multiscalar::<_, E::G1Affine>(
-                &ScalarList::Getter(getter, len),
-                table,
-                std::mem::size_of::<<E::Fr as PrimeField>::Repr>() * 8,
-            )
+    Ok(ShardedAggregateProof {
+        commitments,
+        shards: shard_proofs,
+        agg_c,
+        mipp,
+    })
+}
+
+/// Proves the TIPP relation between A and B of a shard of a sharded
+/// aggregation, with B = B^r and wkey scaled by r^{-1}. Contrary to
+/// `prove_tipp_mipp`, the GIPA recursion only covers A and B, and the KZG
+/// openings of both final commitment keys are part of the proof. The challenges
+/// depend on r and on the index of the `shard`.
+#[allow(clippy::too_many_arguments)]
+fn prove_tipp<E, T>(
+    srs: &ProverSRS<E>,
+    a: Vec<E::G1Affine>,
+    b: Vec<E::G2Affine>,
+    wkey: WKey<E>, // scaled key w^r^-1
+    ip_ab: &<E as Engine>::Gt,
+    r_shift: &E::Fr,
+    shard: usize,
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
+) -> Result<TippProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    // the values of vectors A and B rescaled at each step of the loop
+    let (mut m_a, mut m_b) = (a, b);
+    // the values of the commitment keys rescaled at each step of the loop
+    let (mut vkey, mut wkey) = (srs.vkey.clone(), wkey);
+
+    let mut comms_ab = Vec::new();
+    let mut z_ab = Vec::new();
+    let mut challenges: Vec<E::Fr> = Vec::new();
+    let mut challenges_inv: Vec<E::Fr> = Vec::new();
+
+    let mut c_inv: E::Fr = *T::new("sharded-tipp")
+        .append_scalar(r_shift)
+        .append_u64(shard as u64)
+        .append_gt(ip_ab)
+        .into_challenge();
+
+    let mut i = 0;
+    while m_a.len() > 1 {
+        // recursive step
+        // Recurse with problem of half size
+        let split = m_a.len() / 2;
+
+        let (a_left, a_right) = m_a.split_at(split);
+        let (b_left, b_right) = m_b.split_at(split);
+        let (vk_left, vk_right) = vkey.split(split);
+        let (wk_left, wk_right) = wkey.split(split);
+
+        let (rvk_left, rvk_right) = (&vk_left, &vk_right);
+        let (rwk_left, rwk_right) = (&wk_left, &wk_right);
+        try_par! {
+            let tab_l = commit::pair::<E>(rvk_left, rwk_right, a_right, b_left),
+            let tab_r = commit::pair::<E>(rvk_right, rwk_left, a_left, b_right),
+            // \prod e(A_right,B_left)
+            let zab_l = inner_product::pairing::<E>(a_right, b_left),
+            let zab_r = inner_product::pairing::<E>(a_left, b_right)
         };
 
-        // compute F
-        let com_f_j = calc_multiscalar(&poly_f_j, &srs.g_alpha_powers_table, proofs.len());
+        // Fiat-Shamir challenge
+        c_inv = *T::new(&format!("sharded-tipp-{}", i))
+            .append_scalar(&c_inv)
+            .append_gt(&zab_l)
+            .append_gt(&zab_r)
+            .append_gt(&tab_l.0)
+            .append_gt(&tab_l.1)
+            .append_gt(&tab_r.0)
+            .append_gt(&tab_r.1)
+            .into_challenge();
+        let c = c_inv.invert().unwrap();
 
-        // check that a0 is the zero coefficient of F
-        let com_w0_j = calc_multiscalar(&poly_w0, &srs.g_alpha_powers_table, proofs.len() - 1);
+        // A[:n'] + A[n':] ^ x
+        compress(&mut m_a, split, &c);
+        // B[:n'] + B[n':] ^ x^-1
+        compress(&mut m_b, split, &c_inv);
+        // v_left + v_right^x^-1
+        vkey = vk_left.compress(&vk_right, &c_inv)?;
+        // w_left + w_right^x
+        wkey = wk_left.compress(&wk_right, &c)?;
 
-        // Check F^x^(n - d) exists i.e. that F is bounded
-        let com_wd_j = calc_multiscalar(&poly_f_cp, &srs.g_alpha_powers_end_table, proofs.len());
+        comms_ab.push((tab_l, tab_r));
+        z_ab.push((zab_l, zab_r));
+        challenges.push(c);
+        challenges_inv.push(c_inv);
 
-        com_f.push(com_f_j);
-        com_w0.push(-com_w0_j);
-        com_wd.push(-com_wd_j);
-        poly_f.push(poly_f_cp_2);
+        i += 1;
     }
 
-    let transcript_new = Transcript::<E>::new("transcript-with-coms")
-        .write(&com_f)
-        .write(&com_w0)
-        .write(&com_wd)
-        .write(&transcript_include)
-        .into_bytes();
-
-    let pi_agg = aggregate_proofs(srs, &transcript_new, proofs, version).unwrap();
+    let (final_a, final_b) = (m_a[0], m_b[0]);
+    let (final_vkey, final_wkey) = (vkey.first(), wkey.first());
 
-    let hcom = Transcript::<E>::new("hcom")
-        .write(&pi_agg.com_ab)
-        .write(&pi_agg.com_c)
+    // KZG challenge point
+    let z = T::new("sharded-tipp-z")
+        .append_scalar(&c_inv)
+        .append_g1(&final_a)
+        .append_g2(&final_b)
+        .append_g2(&final_vkey.0)
+        .append_g2(&final_vkey.1)
+        .append_g1(&final_wkey.0)
+        .append_g1(&final_wkey.1)
         .into_challenge();
 
-    // Random linear combination of proofs
-    let r = Transcript::<E>::new("random-r")
-        .write(&hcom)
-        .write(&transcript_new)
+    // Prove final commitment keys are wellformed, the polynomials take the
+    // challenges in reverse order.
+    challenges.reverse();
+    challenges_inv.reverse();
+    let wkey_opening = prove_commitment_w(
+        kernels.g1(),
+        &srs.g_alpha_powers_table,
+        &srs.g_beta_powers_table,
+        srs.n,
+        &challenges,
+        &r_shift.invert().unwrap(),
+        &z,
+    )?;
+    let vkey_opening = prove_commitment_v(
+        kernels.g2(),
+        &srs.h_alpha_powers_table,
+        &srs.h_beta_powers_table,
+        srs.n,
+        &challenges_inv,
+        &z,
+    )?;
+
+    Ok(TippProof {
+        comms_ab,
+        z_ab,
+        final_a,
+        final_b,
+        final_vkey,
+        final_wkey,
+        vkey_opening,
+        wkey_opening,
+    })
+}
+
+/// Proves the MIPP relation between C and r of a sharded aggregation, where C
+/// is the combination of the C of all shards and `agg_c` is C^r. The challenges
+/// depend on the random scalar `s` of the combination.
+fn prove_mipp<E, T>(
+    srs: &ProverSRS<E>,
+    c: Vec<E::G1Affine>,
+    r: Vec<E::Fr>,
+    agg_c: &E::G1,
+    s: &E::Fr,
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
+) -> Result<MippProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+    <E::Fr as PrimeField>::Repr: Send + Sync,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
+{
+    // the values of vectors C and r rescaled at each step of the loop
+    let (mut m_c, mut m_r) = (c, r);
+    // the values of the commitment key rescaled at each step of the loop
+    let mut vkey = srs.vkey.clone();
+
+    let mut comms_c = Vec::new();
+    let mut z_c = Vec::new();
+    let mut challenges_inv: Vec<E::Fr> = Vec::new();
+
+    let mut c_inv: E::Fr = *T::new("sharded-mipp")
+        .append_scalar(s)
+        .append_g1(&agg_c.to_affine())
         .into_challenge();
 
-    for poly_f_j in poly_f {
-        let mut poly_eval = E::Fr::ZERO;
-        let mut r_pow = E::Fr::ONE;
-        for poly_f_j_i in &poly_f_j {
-            poly_eval += *poly_f_j_i * r_pow;
-            r_pow *= &*r;
-        }
-        f_eval.push(poly_eval);
+    let g1_kern = kernels.g1();
+    let mut i = 0;
+    while m_c.len() > 1 {
+        // recursive step
+        // Recurse with problem of half size
+        let split = m_c.len() / 2;
 
-        f_eval_proof.push(
-            create_kzg_opening_for_instance::<E>(
-                &srs.g_alpha_powers_table,
-                DensePolynomial::from_coeffs(poly_f_j.clone()),
-                poly_eval,
-                &r,
-            )
-            .unwrap(),
-        );
+        let (c_left, c_right) = m_c.split_at(split);
+        let (r_left, r_right) = m_r.split_at_mut(split);
+        let (vk_left, vk_right) = vkey.split(split);
+
+        let (rvk_left, rvk_right) = (&vk_left, &vk_right);
+        let (rr_left, rr_right) = (&*r_left, &*r_right);
+        try_par! {
+            // z_l = c[n':] ^ r[:n']
+            let zc_l = multiexp::<E::G1Affine>(g1_kern, c_right, rr_left),
+            // Z_r = c[:n'] ^ r[n':]
+            let zc_r = multiexp::<E::G1Affine>(g1_kern, c_left, rr_right),
+            // u_l = c[n':] * v[:n']
+            let tuc_l = commit::single_g1::<E>(rvk_left, c_right),
+            // u_r = c[:n'] * v[n':]
+            let tuc_r = commit::single_g1::<E>(rvk_right, c_left)
+        };
+
+        // Fiat-Shamir challenge
+        c_inv = *T::new(&format!("sharded-mipp-{}", i))
+            .append_scalar(&c_inv)
+            .append_g1(&zc_l.to_affine())
+            .append_g1(&zc_r.to_affine())
+            .append_gt(&tuc_l.0)
+            .append_gt(&tuc_l.1)
+            .append_gt(&tuc_r.0)
+            .append_gt(&tuc_r.1)
+            .into_challenge();
+        let c = c_inv.invert().unwrap();
+
+        // c[:n'] + c[n':]^x
+        compress(&mut m_c, split, &c);
+        r_left
+            .par_iter_mut()
+            .zip(r_right.par_iter_mut())
+            .for_each(|(r_l, r_r)| {
+                // r[:n'] + r[n':]^x^-1
+                r_r.mul_assign(&c_inv);
+                r_l.add_assign(*r_r);
+            });
+        m_r.truncate(split);
+        // v_left + v_right^x^-1
+        vkey = vk_left.compress(&vk_right, &c_inv)?;
+
+        comms_c.push((tuc_l, tuc_r));
+        z_c.push((zc_l, zc_r));
+        challenges_inv.push(c_inv);
+
+        i += 1;
     }
 
-    Ok(AggregateProofAndInstance {
-        num_inputs: u32::try_from(n).expect("too many statements"),
-        pi_agg,
-        com_f,
-        com_w0,
-        com_wd,
-        f_eval,
-        f_eval_proof,
+    let final_c = m_c[0];
+    let final_vkey = vkey.first();
+
+    // KZG challenge point
+    let z = T::new("sharded-mipp-z")
+        .append_scalar(&c_inv)
+        .append_g1(&final_c)
+        .append_g2(&final_vkey.0)
+        .append_g2(&final_vkey.1)
+        .into_challenge();
+
+    challenges_inv.reverse();
+    let vkey_opening = prove_commitment_v(
+        kernels.g2(),
+        &srs.h_alpha_powers_table,
+        &srs.h_beta_powers_table,
+        srs.n,
+        &challenges_inv,
+        &z,
+    )?;
+
+    Ok(MippProof {
+        comms_c,
+        z_c,
+        final_c,
+        final_vkey,
+        vkey_opening,
     })
 }
 
@@ -277,7 +1654,7 @@ where
 /// only one KZG proof is needed for v. In the original paper version, since the
 /// challenges of GIPA would be different, two KZG proofs would be needed.
 #[allow(clippy::too_many_arguments)]
-fn prove_tipp_mipp<E>(
+fn prove_tipp_mipp<E, T>(
     srs: &ProverSRS<E>,
     a: &[E::G1Affine],
     b: &[E::G2Affine],
@@ -288,20 +1665,21 @@ fn prove_tipp_mipp<E>(
     agg_c: &E::G1,
     hcom: &E::Fr,
     version: AggregateVersion,
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
 ) -> Result<TippMippProof<E>, SynthesisError>
 where
     E: MultiMillerLoop,
-    E::Fr: Serialize,
+    T: TranscriptProtocol<E>,
     <E::Fr as PrimeField>::Repr: Send + Sync,
-    <E as Engine>::Gt: Compress + Serialize,
-    E::G1: Serialize,
-    E::G1Affine: Serialize,
-    E::G2Affine: Serialize,
+    <E as Engine>::Gt: Compress,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
 {
     let r_shift = r_vec[1];
     // Run GIPA
-    let (proof, mut challenges, mut challenges_inv, extra_challenge) =
-        gipa_tipp_mipp::<E>(a, b, c, &srs.vkey, wkey, r_vec, ip_ab, agg_c, hcom, version)?;
+    let (proof, mut challenges, mut challenges_inv, extra_challenge) = gipa_tipp_mipp::<E, T>(
+        a, b, c, &srs.vkey, wkey, r_vec, ip_ab, agg_c, hcom, version, kernels,
+    )?;
 
     // Prove final commitment keys are wellformed
     // we reverse the transcript so the polynomial in kzg opening is constructed
@@ -313,48 +1691,49 @@ where
 
     // KZG challenge point
     let z = match version {
-        AggregateVersion::V1 => Transcript::<E>::new("random-z")
-            .write(&challenges[0])
-            .write(&proof.final_vkey.0)
-            .write(&proof.final_vkey.1)
-            .write(&proof.final_wkey.0)
-            .write(&proof.final_wkey.1)
+        AggregateVersion::V1 => T::new("random-z")
+            .append_scalar(&challenges[0])
+            .append_g2(&proof.final_vkey.0)
+            .append_g2(&proof.final_vkey.1)
+            .append_g1(&proof.final_wkey.0)
+            .append_g1(&proof.final_wkey.1)
             .into_challenge(),
-        AggregateVersion::V2 => Transcript::<E>::new("random-z")
-            .write(&extra_challenge)
-            .write(&proof.final_vkey.0)
-            .write(&proof.final_vkey.1)
-            .write(&proof.final_wkey.0)
-            .write(&proof.final_wkey.1)
-            .write(&proof.final_a)
-            .write(&proof.final_b)
-            .write(&proof.final_c)
+        AggregateVersion::V2 | AggregateVersion::V3 => T::new("random-z")
+            .append_scalar(&extra_challenge)
+            .append_g2(&proof.final_vkey.0)
+            .append_g2(&proof.final_vkey.1)
+            .append_g1(&proof.final_wkey.0)
+            .append_g1(&proof.final_wkey.1)
+            .append_g1(&proof.final_a)
+            .append_g2(&proof.final_b)
+            .append_g1(&proof.final_c)
             .into_challenge(),
     };
 
-    // Complete KZG proofs
-    par! {
-        let vkey_opening = prove_commitment_v(
-            &srs.h_alpha_powers_table,
-            &srs.h_beta_powers_table,
-            srs.n,
-            &challenges_inv,
-            &z,
-        ),
-        let wkey_opening = prove_commitment_w(
-            &srs.g_alpha_powers_table,
-            &srs.g_beta_powers_table,
-            srs.n,
-            &challenges,
-            &r_inverse,
-            &z,
-        )
-    };
+    // Complete KZG proofs. They run one after the other, as only the kernel of
+    // one group can be in use at a time.
+    let wkey_opening = prove_commitment_w(
+        kernels.g1(),
+        &srs.g_alpha_powers_table,
+        &srs.g_beta_powers_table,
+        srs.n,
+        &challenges,
+        &r_inverse,
+        &z,
+    )?;
+    let vkey_opening = prove_commitment_v(
+        kernels.g2(),
+        &srs.h_alpha_powers_table,
+        &srs.h_beta_powers_table,
+        srs.n,
+        &challenges_inv,
+        &z,
+    )?;
 
     Ok(TippMippProof {
         gipa: proof,
-        vkey_opening: vkey_opening?,
-        wkey_opening: wkey_opening?,
+        vkey_opening,
+        wkey_opening,
     })
 }
 
@@ -369,7 +1748,7 @@ where
     clippy::type_complexity,
     clippy::too_many_arguments
 )]
-fn gipa_tipp_mipp<E>(
+fn gipa_tipp_mipp<E, T>(
     a: &[E::G1Affine],
     b: &[E::G2Affine],
     c: &[E::G1Affine],
@@ -380,15 +1759,14 @@ fn gipa_tipp_mipp<E>(
     agg_c: &E::G1,
     hcom: &E::Fr,
     version: AggregateVersion,
+    kernels: &MultiexpKernels<E::G1Affine, E::G2Affine>,
 ) -> Result<(GipaProof<E>, Vec<E::Fr>, Vec<E::Fr>, E::Fr), SynthesisError>
 where
     E: MultiMillerLoop,
-    E::Fr: Serialize,
+    T: TranscriptProtocol<E>,
     <E::Fr as PrimeField>::Repr: Sync,
-    <E as Engine>::Gt: Serialize,
-    E::G1: Serialize,
-    E::G1Affine: Serialize,
-    E::G2Affine: Serialize,
+    E::G1Affine: MultiexpGroup,
+    E::G2Affine: MultiexpGroup,
 {
     // the values of vectors A and B rescaled at each step of the loop
     let (mut m_a, mut m_b) = (a.to_vec(), b.to_vec());
@@ -405,14 +1783,15 @@ where
     let mut challenges: Vec<E::Fr> = Vec::new();
     let mut challenges_inv: Vec<E::Fr> = Vec::new();
 
-    let mut c_inv: E::Fr = *Transcript::<E>::new("gipa-0")
-        .write(hcom)
-        .write(ip_ab)
-        .write(agg_c)
-        .write(&r[1])
+    let mut c_inv: E::Fr = *T::new("gipa-0")
+        .append_scalar(hcom)
+        .append_gt(ip_ab)
+        .append_g1(&agg_c.to_affine())
+        .append_scalar(&r[1])
         .into_challenge();
     let mut c = c_inv.invert().unwrap();
 
+    let g1_kern = kernels.g1();
     let mut i = 0;
 
     while m_a.len() > 1 {
@@ -450,10 +1829,13 @@ where
             let zab_r = inner_product::pairing::<E>(ra_left, rb_right),
 
             // MIPP part
+            // On the GPU, the two multiexponentiations don't run in parallel: the kernel
+            // makes the second one wait for the first one, which uses the whole GPU. The
+            // commitments and pairings keep running on the CPU in the meantime.
             // z_l = c[n':] ^ r[:n']
-            let zc_l = inner_product::multiexponentiation::<E::G1Affine>(rc_right, rr_left),
+            let zc_l = multiexp::<E::G1Affine>(g1_kern, rc_right, rr_left),
             // Z_r = c[:n'] ^ r[n':]
-            let zc_r = inner_product::multiexponentiation::<E::G1Affine>(rc_left, rr_right),
+            let zc_r = multiexp::<E::G1Affine>(g1_kern, rc_left, rr_right),
             // u_l = c[n':] * v[:n']
             let tuc_l = commit::single_g1::<E>(rvk_left, rc_right),
             // u_r = c[:n'] * v[n':]
@@ -467,42 +1849,42 @@ where
                 AggregateVersion::V1 => {
                     // already generated c_inv and c outside of the loop
                 }
-                AggregateVersion::V2 => {
+                AggregateVersion::V2 | AggregateVersion::V3 => {
                     // in this version we do fiat shamir with the first inputs
-                    c_inv = *Transcript::<E>::new("gipa-0")
-                        .write(&c_inv)
-                        .write(&zab_l)
-                        .write(&zab_r)
-                        .write(&zc_l)
-                        .write(&zc_r)
-                        .write(&tab_l.0)
-                        .write(&tab_l.1)
-                        .write(&tab_r.0)
-                        .write(&tab_r.1)
-                        .write(&tuc_l.0)
-                        .write(&tuc_l.1)
-                        .write(&tuc_r.0)
-                        .write(&tuc_r.1)
+                    c_inv = *T::new("gipa-0")
+                        .append_scalar(&c_inv)
+                        .append_gt(&zab_l)
+                        .append_gt(&zab_r)
+                        .append_g1(&zc_l.to_affine())
+                        .append_g1(&zc_r.to_affine())
+                        .append_gt(&tab_l.0)
+                        .append_gt(&tab_l.1)
+                        .append_gt(&tab_r.0)
+                        .append_gt(&tab_r.1)
+                        .append_gt(&tuc_l.0)
+                        .append_gt(&tuc_l.1)
+                        .append_gt(&tuc_r.0)
+                        .append_gt(&tuc_r.1)
                         .into_challenge();
 
                     c = c_inv.invert().unwrap();
                 }
             };
         } else {
-            c_inv = *Transcript::<E>::new(&format!("gipa-{}", i))
-                .write(&c_inv)
-                .write(&zab_l)
-                .write(&zab_r)
-                .write(&zc_l)
-                .write(&zc_r)
-                .write(&tab_l.0)
-                .write(&tab_l.1)
-                .write(&tab_r.0)
-                .write(&tab_r.1)
-                .write(&tuc_l.0)
-                .write(&tuc_l.1)
-                .write(&tuc_r.0)
-                .write(&tuc_r.1)
+            c_inv = *T::new(&format!("gipa-{}", i))
+                .append_scalar(&c_inv)
+                .append_gt(&zab_l)
+                .append_gt(&zab_r)
+                .append_g1(&zc_l.to_affine())
+                .append_g1(&zc_r.to_affine())
+                .append_gt(&tab_l.0)
+                .append_gt(&tab_l.1)
+                .append_gt(&tab_r.0)
+                .append_gt(&tab_r.1)
+                .append_gt(&tuc_l.0)
+                .append_gt(&tuc_l.1)
+                .append_gt(&tuc_r.0)
+                .append_gt(&tuc_r.1)
                 .into_challenge();
 
             // Optimization for multiexponentiation to rescale G2 elements with
@@ -559,23 +1941,23 @@ where
     let (final_tuc_l, final_tuc_r) = comms_c.last().unwrap();
     // This extra challenge is simply done to make the bridge between the
     // MIPP/TIPP proofs and the KZG proofs, but is not used in TIPP/MIPP.
-    let extra_challenge = *Transcript::<E>::new("gipa-extra-link")
-        .write(&challenges.last().unwrap())
-        .write(&final_a)
-        .write(&final_b)
-        .write(&final_c)
-        .write(&final_zab_l)
-        .write(&final_zab_r)
-        .write(&final_zc_l)
-        .write(&final_zc_r)
-        .write(&final_tab_l.0)
-        .write(&final_tab_l.1)
-        .write(&final_tab_r.0)
-        .write(&final_tab_r.1)
-        .write(&final_tuc_l.0)
-        .write(&final_tuc_l.1)
-        .write(&final_tuc_r.0)
-        .write(&final_tuc_r.1)
+    let extra_challenge = *T::new("gipa-extra-link")
+        .append_scalar(challenges.last().unwrap())
+        .append_g1(&final_a)
+        .append_g2(&final_b)
+        .append_g1(&final_c)
+        .append_gt(final_zab_l)
+        .append_gt(final_zab_r)
+        .append_g1(&final_zc_l.to_affine())
+        .append_g1(&final_zc_r.to_affine())
+        .append_gt(&final_tab_l.0)
+        .append_gt(&final_tab_l.1)
+        .append_gt(&final_tab_r.0)
+        .append_gt(&final_tab_r.1)
+        .append_gt(&final_tuc_l.0)
+        .append_gt(&final_tuc_l.1)
+        .append_gt(&final_tuc_r.0)
+        .append_gt(&final_tuc_r.1)
         .into_challenge();
     debug!("prover: extra challenge {:?}", extra_challenge);
     Ok((
@@ -598,6 +1980,7 @@ where
 }
 
 fn prove_commitment_v<G>(
+    kern: &MultiexpKernel<G>,
     srs_powers_alpha_table: &dyn MultiscalarPrecomp<G>,
     srs_powers_beta_table: &dyn MultiscalarPrecomp<G>,
     n: usize,
@@ -605,7 +1988,7 @@ fn prove_commitment_v<G>(
     kzg_challenge: &G::Scalar,
 ) -> Result<KZGOpening<G>, SynthesisError>
 where
-    G: PrimeCurveAffine,
+    G: MultiexpGroup,
     <G::Scalar as PrimeField>::Repr: Send + Sync,
 {
     // f_v
@@ -622,6 +2005,7 @@ where
     );
 
     create_kzg_opening(
+        kern,
         srs_powers_alpha_table,
         srs_powers_beta_table,
         n,
@@ -632,6 +2016,7 @@ where
 }
 
 fn prove_commitment_w<G>(
+    kern: &MultiexpKernel<G>,
     srs_powers_alpha_table: &dyn MultiscalarPrecomp<G>,
     srs_powers_beta_table: &dyn MultiscalarPrecomp<G>,
     n: usize,
@@ -640,7 +2025,7 @@ fn prove_commitment_w<G>(
     kzg_challenge: &G::Scalar,
 ) -> Result<KZGOpening<G>, SynthesisError>
 where
-    G: PrimeCurveAffine,
+    G: MultiexpGroup,
     <G::Scalar as PrimeField>::Repr: Send + Sync,
 {
     // this computes f(X) = \prod (1 + x (rX)^{2^j})
@@ -661,6 +2046,7 @@ where
     fwz.mul_assign(&zn);
 
     create_kzg_opening(
+        kern,
         srs_powers_alpha_table,
         srs_powers_beta_table,
         2 * n, // here we have twice the coefficients size
@@ -670,12 +2056,17 @@ where
     )
 }
 
-fn create_kzg_opening_for_instance<E: Engine>(
+fn create_kzg_opening_for_instance<E>(
+    kern: &MultiexpKernel<E::G1Affine>,
     srs_powers_alpha_table: &dyn MultiscalarPrecomp<E::G1Affine>, // h^alpha^i
     poly: DensePolynomial<E::Fr>,
     eval_poly: E::Fr,
     kzg_challenge: &E::Fr,
-) -> Result<E::G1, SynthesisError> {
+) -> Result<E::G1, SynthesisError>
+where
+    E: Engine,
+    E::G1Affine: MultiexpGroup,
+{
     let neg_kzg_challenge = -*kzg_challenge;
 
     // f_v(X) - f_v(z) / (X - z)
@@ -689,10 +2080,10 @@ fn create_kzg_opening_for_instance<E: Engine>(
         let getter =
             |i: usize| -> <E::Fr as PrimeField>::Repr { quotient_polynomial_coeffs[i].to_repr() };
 
-        par_multiscalar::<_, E::G1Affine>(
-            &ScalarList::Getter(getter, quotient_polynomial_coeffs.len()),
+        multiexp_table::<E::G1Affine, _>(
+            kern,
             srs_powers_alpha_table,
-            std::mem::size_of::<<E::Fr as PrimeField>::Repr>() * 8,
+            &ScalarList::Getter(getter, quotient_polynomial_coeffs.len()),
         )
     };
 
@@ -702,6 +2093,7 @@ fn create_kzg_opening_for_instance<E: Engine>(
 /// Returns the KZG opening proof for the given commitment key. Specifically, it
 /// returns $g^{f(alpha) - f(z) / (alpha - z)}$ for $a$ and $b$.
 fn create_kzg_opening<G>(
+    kern: &MultiexpKernel<G>,
     srs_powers_alpha_table: &dyn MultiscalarPrecomp<G>, // h^alpha^i
     srs_powers_beta_table: &dyn MultiscalarPrecomp<G>,  // h^beta^i
     srs_powers_len: usize,
@@ -710,7 +2102,7 @@ fn create_kzg_opening<G>(
     kzg_challenge: &G::Scalar,
 ) -> Result<KZGOpening<G>, SynthesisError>
 where
-    G: PrimeCurveAffine,
+    G: MultiexpGroup,
     <G::Scalar as PrimeField>::Repr: Send + Sync,
 {
     let neg_kzg_challenge = -*kzg_challenge;
@@ -740,18 +2132,18 @@ where
     // of Bunz'19
     Ok(rayon::join(
         || {
-            par_multiscalar::<_, G>(
-                &ScalarList::Getter(getter, srs_powers_len),
+            multiexp_table::<G, _>(
+                kern,
                 srs_powers_alpha_table,
-                std::mem::size_of::<<G::Scalar as PrimeField>::Repr>() * 8,
+                &ScalarList::Getter(getter, srs_powers_len),
             )
             .to_affine()
         },
         || {
-            par_multiscalar::<_, G>(
-                &ScalarList::Getter(getter, srs_powers_len),
+            multiexp_table::<G, _>(
+                kern,
                 srs_powers_beta_table,
-                std::mem::size_of::<<G::Scalar as PrimeField>::Repr>() * 8,
+                &ScalarList::Getter(getter, srs_powers_len),
             )
             .to_affine()
         },
@@ -1,12 +1,14 @@
 use super::msm;
 use crate::groth16::aggregate::commit::*;
-use crate::groth16::multiscalar::{precompute_fixed_window, MultiscalarPrecompOwned, WINDOW_SIZE};
+use crate::groth16::multiscalar::{
+    precompute_fixed_window, MultiscalarPrecomp, MultiscalarPrecompOwned, WINDOW_SIZE,
+};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use digest::Digest;
 use ff::{Field, PrimeField, PrimeFieldBits};
 use group::{
     prime::{PrimeCurve, PrimeCurveAffine},
-    Curve, Group, GroupEncoding,
+    Curve, Group, GroupEncoding, UncompressedEncoding,
 };
 #[cfg(not(target_arch = "wasm32"))]
 use memmap2::Mmap;
@@ -15,9 +17,10 @@ use rayon::prelude::*;
 use sha2::Sha256;
 use std::convert::TryFrom;
 use std::io::{self, Error, ErrorKind, Read, Write};
-#[cfg(not(target_arch = "wasm32"))]
 use std::mem::size_of;
 use std::ops::MulAssign;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
 
 /// Maximum size of the generic SRS constructed from Filecoin and Zcash power of
 /// taus.
@@ -31,7 +34,7 @@ pub const MAX_SRS_SIZE: usize = (2 << 19) + 1;
 /// precomputed tables that drastically increase prover's performance.
 /// This GenericSRS is usually formed from the transcript of two distinct power of taus ceremony
 /// ,in other words from two distinct Groth16 CRS.
-/// See [there](https://github.com/nikkolasg/taupipp) a way on how to generate this GenesisSRS.
+/// See `assemble_srs` on how to generate this GenericSRS from the transcripts of two ceremonies.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub struct GenericSRS<E: Engine> {
@@ -47,7 +50,7 @@ pub struct GenericSRS<E: Engine> {
 
 /// ProverSRS is the specialized SRS version for the prover for a specific number of proofs to
 /// aggregate. It contains as well the commitment keys for this specific size.
-/// Note the size must be a power of two for the moment - if it is not, padding must be applied.
+/// Note the size is always a power of two, proofs are padded to it when aggregated.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub struct ProverSRS<E: Engine> {
@@ -69,6 +72,8 @@ pub struct ProverSRS<E: Engine> {
     pub vkey: VKey<E>,
     /// commitment key using in TIPP
     pub wkey: WKey<E>,
+    /// hash of the generic SRS this SRS was specialized from, see `GenericSRS::hash()`
+    pub generic_hash: Vec<u8>,
 }
 
 /// ProverSRS is the specialized SRS version for the prover for a specific number of proofs to
@@ -104,6 +109,8 @@ pub struct VerifierSRS<E: Engine> {
     pub h_alpha: E::G2,
     pub h_beta: E::G2,
     pub h_alpha_d: E::G2,
+    /// hash of the generic SRS this SRS was specialized from, see `GenericSRS::hash()`
+    pub generic_hash: Vec<u8>,
 }
 
 impl<E: Engine> PartialEq for GenericSRS<E> {
@@ -124,6 +131,7 @@ impl<E: Engine> PartialEq for VerifierSRS<E> {
             && self.h_alpha == other.h_alpha
             && self.h_beta == other.h_beta
             && self.h_alpha_d == other.h_alpha_d
+            && self.generic_hash == other.generic_hash
     }
 }
 
@@ -135,6 +143,41 @@ impl<E: Engine> ProverSRS<E> {
     pub fn has_correct_len(&self, n: usize) -> bool {
         self.vkey.has_correct_len(n) && self.wkey.has_correct_len(n)
     }
+
+    /// Returns the prover SRS for a smaller number of proofs to aggregate.
+    /// The number of proofs is rounded up to the next power of two. It is
+    /// derived from the precomputed tables of this SRS, hence much cheaper than
+    /// specializing the generic SRS again. It panics if the padded number of
+    /// proofs is larger than the one of this SRS.
+    pub fn specialize(&self, num_proofs: usize) -> ProverSRS<E> {
+        let n = num_proofs.next_power_of_two();
+        assert!(n <= self.n);
+        if n == self.n {
+            return self.clone();
+        }
+
+        let tn = 2 * n;
+        let vkey = VKey::<E> {
+            a: self.vkey.a[..n].to_vec(),
+            b: self.vkey.b[..n].to_vec(),
+        };
+        let wkey = WKey::<E> {
+            a: (n..tn)
+                .map(|i| self.g_alpha_powers_table.point(i))
+                .collect(),
+            b: (n..tn).map(|i| self.g_beta_powers_table.point(i)).collect(),
+        };
+        ProverSRS::<E> {
+            g_alpha_powers_table: self.g_alpha_powers_table.truncate(tn),
+            g_beta_powers_table: self.g_beta_powers_table.truncate(tn),
+            h_alpha_powers_table: self.h_alpha_powers_table.truncate(n),
+            h_beta_powers_table: self.h_beta_powers_table.truncate(n),
+            vkey,
+            wkey,
+            n,
+            generic_hash: self.generic_hash.clone(),
+        }
+    }
 }
 
 impl<E: Engine> ProverSRSInputAggregation<E> {
@@ -154,12 +197,26 @@ where
     <E::G2Affine as GroupEncoding>::Repr: Sync,
 {
     /// Returns the prover and verifier SRS for a specific number of
-    /// proofs to aggregate. The number of proofs MUST BE a power of two, it
-    /// panics otherwise. The number of proofs must be inferior to half of the
-    /// size of the generic srs otherwise it panics.
+    /// proofs to aggregate. The number of proofs is rounded up to the next
+    /// power of two, as the proofs are padded to it when aggregated. The
+    /// padded number of proofs must be inferior to half of the size of the
+    /// generic srs otherwise it panics.
+    ///
+    /// It hashes the whole generic SRS, use `specialize_with_hash` to
+    /// specialize it more than once.
     pub fn specialize(&self, num_proofs: usize) -> (ProverSRS<E>, VerifierSRS<E>) {
-        let pk = self.specialize_prover(num_proofs);
-        let vk = self.specialize_vk(num_proofs);
+        self.specialize_with_hash(num_proofs, &self.hash())
+    }
+
+    /// Same as `specialize` with the hash of this generic SRS, as returned by
+    /// `hash()`.
+    pub fn specialize_with_hash(
+        &self,
+        num_proofs: usize,
+        generic_hash: &[u8],
+    ) -> (ProverSRS<E>, VerifierSRS<E>) {
+        let pk = self.specialize_prover_with_hash(num_proofs, generic_hash);
+        let vk = self.specialize_vk_with_hash(num_proofs, generic_hash);
         (pk, vk)
     }
 
@@ -167,11 +224,25 @@ where
     /// proofs to aggregate. The number of proofs MUST BE a power of two, it
     /// panics otherwise. The number of proofs must be inferior to half of the
     /// size of the generic srs otherwise it panics.
+    ///
+    /// It hashes the whole generic SRS, use
+    /// `specialize_input_aggregation_with_hash` to specialize it more than once.
     pub fn specialize_input_aggregation(
         &self,
         num_proofs: usize,
     ) -> (ProverSRSInputAggregation<E>, VerifierSRS<E>) {
-        let prover_srs = self.specialize_prover(num_proofs);
+        self.specialize_input_aggregation_with_hash(num_proofs, &self.hash())
+    }
+
+    /// Same as `specialize_input_aggregation` with the hash of this generic
+    /// SRS, as returned by `hash()`.
+    pub fn specialize_input_aggregation_with_hash(
+        &self,
+        num_proofs: usize,
+        generic_hash: &[u8],
+    ) -> (ProverSRSInputAggregation<E>, VerifierSRS<E>) {
+        assert!(num_proofs.is_power_of_two());
+        let prover_srs = self.specialize_prover_with_hash(num_proofs, generic_hash);
 
         let g_alpha_powers_end_table = precompute_fixed_window(
             &self.g_alpha_powers[self.g_alpha_powers.len() - num_proofs..],
@@ -182,18 +253,35 @@ where
             prover_srs,
             g_alpha_powers_end_table,
         };
-        let vk = self.specialize_vk(num_proofs);
+        let vk = self.specialize_vk_with_hash(num_proofs, generic_hash);
         (pk, vk)
     }
 
+    /// Returns the prover SRS of `specialize`. It hashes the whole generic
+    /// SRS, use `specialize_prover_with_hash` to specialize it more than once.
     pub fn specialize_prover(&self, num_proofs: usize) -> ProverSRS<E> {
-        assert!(num_proofs.is_power_of_two());
-        let tn = 2 * num_proofs; // size of the CRS we need
+        self.specialize_prover_with_hash(num_proofs, &self.hash())
+    }
+
+    /// Returns the verifier SRS of `specialize`. It hashes the whole generic
+    /// SRS, use `specialize_vk_with_hash` to specialize it more than once.
+    pub fn specialize_vk(&self, num_proofs: usize) -> VerifierSRS<E> {
+        self.specialize_vk_with_hash(num_proofs, &self.hash())
+    }
+
+    /// Same as `specialize_prover` with the hash of this generic SRS, as
+    /// returned by `hash()`.
+    pub fn specialize_prover_with_hash(
+        &self,
+        num_proofs: usize,
+        generic_hash: &[u8],
+    ) -> ProverSRS<E> {
+        let n = num_proofs.next_power_of_two();
+        let tn = 2 * n; // size of the CRS we need
         assert!(self.g_alpha_powers.len() >= tn);
         assert!(self.h_alpha_powers.len() >= tn);
         assert!(self.g_beta_powers.len() >= tn);
         assert!(self.h_beta_powers.len() >= tn);
-        let n = num_proofs;
 
         // when doing the KZG opening we need _all_ coefficients from 0
         // to 2n-1 because the polynomial is of degree 2n-1.
@@ -227,12 +315,18 @@ where
             vkey,
             wkey,
             n,
+            generic_hash: generic_hash.to_vec(),
         }
     }
 
-    pub fn specialize_vk(&self, num_proofs: usize) -> VerifierSRS<E> {
-        assert!(num_proofs.is_power_of_two());
-        let n = num_proofs;
+    /// Same as `specialize_vk` with the hash of this generic SRS, as returned
+    /// by `hash()`.
+    pub fn specialize_vk_with_hash(
+        &self,
+        num_proofs: usize,
+        generic_hash: &[u8],
+    ) -> VerifierSRS<E> {
+        let n = num_proofs.next_power_of_two();
         VerifierSRS::<E> {
             n,
             g: self.g_alpha_powers[0].to_curve(),
@@ -241,7 +335,8 @@ where
             g_beta: self.g_beta_powers[1].to_curve(),
             h_alpha: self.h_alpha_powers[1].to_curve(),
             h_beta: self.h_beta_powers[1].to_curve(),
-            h_alpha_d: self.h_alpha_powers[self.g_alpha_powers.len() - num_proofs].into(),
+            h_alpha_d: self.h_alpha_powers[self.g_alpha_powers.len() - n].into(),
+            generic_hash: generic_hash.to_vec(),
         }
     }
 
@@ -253,11 +348,12 @@ where
         Ok(())
     }
 
-    /// Returns the hash over all powers of this generic srs.
+    /// Returns the hash over all powers of this generic srs. The powers are
+    /// streamed into the hasher, without serializing the whole SRS first.
     pub fn hash(&self) -> Vec<u8> {
-        let mut v = Vec::new();
-        self.write(&mut v).expect("failed to compute hash");
-        Sha256::digest(&v).to_vec()
+        let mut hasher = Sha256::new();
+        self.write(&mut hasher).expect("failed to compute hash");
+        hasher.finalize().to_vec()
     }
 
     pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
@@ -336,6 +432,308 @@ where
     }
 }
 
+/// Identifies the files of specialized SRS.
+const SPECIALIZED_SRS_MAGIC: &[u8] = b"snarkpack-srs";
+
+/// Version of the on-disk format of specialized SRS.
+const SPECIALIZED_SRS_VERSION: u32 = 3;
+
+/// The precomputed tables start at a multiple of this offset within the file, so that they can
+/// be used in place from a memory mapped file.
+const TABLE_ALIGNMENT: usize = 64;
+
+/// The kind of a specialized SRS, it is part of the on-disk format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SpecializedSrsKind {
+    Prover = 0,
+    ProverInputAggregation = 1,
+    Verifier = 2,
+}
+
+/// Affine points whose precomputed tables are stored with their in-memory representation in
+/// specialized SRS files, so that the tables can be used in place from a memory mapped file.
+/// Such files can only be read on platforms with the same representation of the points.
+///
+/// # Safety
+///
+/// The type must not contain padding and every bit pattern of its size must be a valid value,
+/// e.g. a `repr(C)` struct of integers. Its alignment must not be larger than 64 bytes.
+///
+/// The trait is sealed, it is implemented for the points of BLS12-381.
+pub unsafe trait RawAffine:
+    PrimeCurveAffine + UncompressedEncoding + sealed::Sealed
+{
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for blstrs::G1Affine {}
+    impl Sealed for blstrs::G2Affine {}
+}
+
+unsafe impl RawAffine for blstrs::G1Affine {}
+unsafe impl RawAffine for blstrs::G2Affine {}
+
+impl<E> ProverSRS<E>
+where
+    E: Engine,
+    E::G1Affine: RawAffine,
+    E::G2Affine: RawAffine,
+{
+    /// Writes the specialized SRS including its precomputed tables. The SRS is bound to the hash
+    /// of the generic SRS it was specialized from, see `GenericSRS::hash()`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut writer = OffsetWriter::new(writer);
+        write_header(
+            &mut writer,
+            SpecializedSrsKind::Prover,
+            &self.generic_hash,
+            self.n,
+            &raw_layout::<E::G1Affine, E::G2Affine>(),
+        )?;
+        self.write_content(&mut writer)
+    }
+
+    /// Reads a specialized SRS written by `write`. It fails if the SRS was not specialized from
+    /// the generic SRS with the given hash, or not for the given number of proofs, which is
+    /// rounded up to the next power of two. If `checked` is false, the points are not checked to
+    /// be valid and in the correct subgroup.
+    ///
+    /// The tables are stored with the in-memory representation of the points, so reading them
+    /// is a copy and does not recompute them. The SRS takes about as much memory as the file,
+    /// `read_mmap` avoids that.
+    pub fn read<R: Read>(
+        reader: &mut R,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::read_data(&SrsData::Slice(&data), generic_hash, num_proofs, checked)
+    }
+
+    /// Same as `read`, but the precomputed tables of the returned SRS are used in place from the
+    /// memory mapped file, which is kept alive by them. Only the header and the commitment keys
+    /// are read, so loading is instant and the pages of the tables are read when the SRS is
+    /// used. Checking the points reads the whole file, aggregators that trust the file should
+    /// set `checked` to false.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_mmap(
+        mmap: Mmap,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let mmap = Arc::new(mmap);
+        Self::read_data(&SrsData::Mapped(&mmap), generic_hash, num_proofs, checked)
+    }
+
+    fn read_data(
+        data: &SrsData<'_>,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let n = num_proofs.next_power_of_two();
+        let mut offset = read_header_at(
+            data,
+            SpecializedSrsKind::Prover,
+            generic_hash,
+            n,
+            &raw_layout::<E::G1Affine, E::G2Affine>(),
+        )?;
+        Self::read_content(data, &mut offset, n, generic_hash, checked)
+    }
+
+    fn write_content<W: Write>(&self, writer: &mut OffsetWriter<W>) -> io::Result<()> {
+        write_table(writer, &self.g_alpha_powers_table)?;
+        write_table(writer, &self.g_beta_powers_table)?;
+        write_table(writer, &self.h_alpha_powers_table)?;
+        write_table(writer, &self.h_beta_powers_table)?;
+        Ok(())
+    }
+
+    /// Reads the tables, the commitment keys are derived from them as they consist of the
+    /// points the tables were created for.
+    fn read_content(
+        data: &SrsData<'_>,
+        offset: &mut usize,
+        n: usize,
+        generic_hash: &[u8],
+        checked: bool,
+    ) -> io::Result<Self> {
+        let g_alpha_powers_table = read_table(data, offset, 2 * n, checked)?;
+        let g_beta_powers_table = read_table(data, offset, 2 * n, checked)?;
+        let h_alpha_powers_table = read_table(data, offset, n, checked)?;
+        let h_beta_powers_table = read_table(data, offset, n, checked)?;
+        let vkey = VKey::<E> {
+            a: (0..n).map(|i| h_alpha_powers_table.point(i)).collect(),
+            b: (0..n).map(|i| h_beta_powers_table.point(i)).collect(),
+        };
+        let wkey = WKey::<E> {
+            a: (n..2 * n).map(|i| g_alpha_powers_table.point(i)).collect(),
+            b: (n..2 * n).map(|i| g_beta_powers_table.point(i)).collect(),
+        };
+        Ok(ProverSRS {
+            n,
+            g_alpha_powers_table,
+            h_alpha_powers_table,
+            g_beta_powers_table,
+            h_beta_powers_table,
+            vkey,
+            wkey,
+            generic_hash: generic_hash.to_vec(),
+        })
+    }
+}
+
+impl<E> ProverSRSInputAggregation<E>
+where
+    E: Engine,
+    E::G1Affine: RawAffine,
+    E::G2Affine: RawAffine,
+{
+    /// Writes the specialized SRS including its precomputed tables, see `ProverSRS::write`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut writer = OffsetWriter::new(writer);
+        write_header(
+            &mut writer,
+            SpecializedSrsKind::ProverInputAggregation,
+            &self.generic_hash,
+            self.n,
+            &raw_layout::<E::G1Affine, E::G2Affine>(),
+        )?;
+        self.prover_srs.write_content(&mut writer)?;
+        write_table(&mut writer, &self.g_alpha_powers_end_table)
+    }
+
+    /// Reads a specialized SRS written by `write`, see `ProverSRS::read`. The number of proofs
+    /// must be a power of two.
+    pub fn read<R: Read>(
+        reader: &mut R,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::read_data(&SrsData::Slice(&data), generic_hash, num_proofs, checked)
+    }
+
+    /// Same as `read`, but the tables are used in place from the memory mapped file, see
+    /// `ProverSRS::read_mmap`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_mmap(
+        mmap: Mmap,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let mmap = Arc::new(mmap);
+        Self::read_data(&SrsData::Mapped(&mmap), generic_hash, num_proofs, checked)
+    }
+
+    fn read_data(
+        data: &SrsData<'_>,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        if !num_proofs.is_power_of_two() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "number of proofs is not a power of two",
+            ));
+        }
+        let mut offset = read_header_at(
+            data,
+            SpecializedSrsKind::ProverInputAggregation,
+            generic_hash,
+            num_proofs,
+            &raw_layout::<E::G1Affine, E::G2Affine>(),
+        )?;
+        let prover_srs =
+            ProverSRS::read_content(data, &mut offset, num_proofs, generic_hash, checked)?;
+        let g_alpha_powers_end_table = read_table(data, &mut offset, num_proofs, checked)?;
+        Ok(ProverSRSInputAggregation {
+            prover_srs,
+            g_alpha_powers_end_table,
+        })
+    }
+}
+
+impl<E> VerifierSRS<E>
+where
+    E: Engine,
+    E::G1Affine: UncompressedEncoding,
+    E::G2Affine: UncompressedEncoding,
+{
+    /// Writes the specialized SRS. The SRS is bound to the hash of the generic SRS it was
+    /// specialized from, see `GenericSRS::hash()`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_header(
+            writer,
+            SpecializedSrsKind::Verifier,
+            &self.generic_hash,
+            self.n,
+            &[],
+        )?;
+        for g in [&self.g, &self.g_alpha, &self.g_beta] {
+            writer.write_all(g.to_affine().to_uncompressed().as_ref())?;
+        }
+        for h in [&self.h, &self.h_alpha, &self.h_beta, &self.h_alpha_d] {
+            writer.write_all(h.to_affine().to_uncompressed().as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a specialized SRS written by `write`. It fails if the SRS was not specialized from
+    /// the generic SRS with the given hash, or not for the given number of proofs, which is
+    /// rounded up to the next power of two.
+    pub fn read<R: Read>(
+        reader: &mut R,
+        generic_hash: &[u8],
+        num_proofs: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let n = num_proofs.next_power_of_two();
+        read_header(reader, SpecializedSrsKind::Verifier, generic_hash, n, &[])?;
+
+        let mut read_g1 = || -> io::Result<E::G1> {
+            let mut repr = <E::G1Affine as UncompressedEncoding>::Uncompressed::default();
+            reader.read_exact(repr.as_mut())?;
+            Ok(decode_uncompressed::<E::G1Affine>(repr.as_ref(), checked)?.to_curve())
+        };
+        let g = read_g1()?;
+        let g_alpha = read_g1()?;
+        let g_beta = read_g1()?;
+
+        let mut read_g2 = || -> io::Result<E::G2> {
+            let mut repr = <E::G2Affine as UncompressedEncoding>::Uncompressed::default();
+            reader.read_exact(repr.as_mut())?;
+            Ok(decode_uncompressed::<E::G2Affine>(repr.as_ref(), checked)?.to_curve())
+        };
+        let h = read_g2()?;
+        let h_alpha = read_g2()?;
+        let h_beta = read_g2()?;
+        let h_alpha_d = read_g2()?;
+
+        Ok(VerifierSRS {
+            n,
+            g,
+            h,
+            g_alpha,
+            g_beta,
+            h_alpha,
+            h_beta,
+            h_alpha_d,
+            generic_hash: generic_hash.to_vec(),
+        })
+    }
+}
+
 pub fn setup_fake_srs<E, R>(rng: &mut R, size: usize) -> GenericSRS<E>
 where
     E: Engine,
@@ -449,6 +847,277 @@ where
         .collect::<io::Result<Vec<_>>>()
 }
 
+/// Writes the header of a specialized SRS, it binds the SRS to the generic SRS it was
+/// specialized from and to the number of proofs. The layout is the one of the points stored with
+/// their in-memory representation, see `raw_layout`, it is empty if there are none.
+fn write_header<W: Write>(
+    w: &mut W,
+    kind: SpecializedSrsKind,
+    generic_hash: &[u8],
+    n: usize,
+    layout: &[u8],
+) -> io::Result<()> {
+    w.write_all(SPECIALIZED_SRS_MAGIC)?;
+    w.write_u32::<BigEndian>(SPECIALIZED_SRS_VERSION)?;
+    w.write_u8(kind as u8)?;
+    w.write_u32::<BigEndian>(generic_hash.len() as u32)?;
+    w.write_all(generic_hash)?;
+    w.write_u32::<BigEndian>(n as u32)?;
+    w.write_u32::<BigEndian>(layout.len() as u32)?;
+    w.write_all(layout)?;
+    Ok(())
+}
+
+fn read_header<R: Read>(
+    r: &mut R,
+    kind: SpecializedSrsKind,
+    generic_hash: &[u8],
+    n: usize,
+    layout: &[u8],
+) -> io::Result<()> {
+    let mut magic = [0u8; SPECIALIZED_SRS_MAGIC.len()];
+    r.read_exact(&mut magic)?;
+    if magic != SPECIALIZED_SRS_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "not a specialized SRS"));
+    }
+
+    let version = r.read_u32::<BigEndian>()?;
+    if version != SPECIALIZED_SRS_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported specialized SRS version {}", version),
+        ));
+    }
+
+    if r.read_u8()? != kind as u8 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("specialized SRS is not of kind {:?}", kind),
+        ));
+    }
+
+    let hash_len = r.read_u32::<BigEndian>()? as usize;
+    if hash_len != generic_hash.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "specialized SRS was specialized from a different generic SRS",
+        ));
+    }
+    let mut hash = vec![0u8; hash_len];
+    r.read_exact(&mut hash)?;
+    if hash != generic_hash {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "specialized SRS was specialized from a different generic SRS",
+        ));
+    }
+
+    let srs_n = r.read_u32::<BigEndian>()? as usize;
+    if srs_n != n {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("specialized SRS is for {} proofs, not {}", srs_n, n),
+        ));
+    }
+
+    let layout_len = r.read_u32::<BigEndian>()? as usize;
+    let mut srs_layout = vec![0u8; layout_len.min(layout.len() + 1)];
+    r.read_exact(&mut srs_layout)?;
+    if srs_layout != layout {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "specialized SRS was written on a platform with a different point representation",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the layout of the points of a platform: its native byte order, followed by the size
+/// and the in-memory representation of the generator of each group. Tables written on a
+/// platform, or with a version of the curve implementation, with a different layout can't be
+/// read.
+fn raw_layout<G1: RawAffine, G2: RawAffine>() -> Vec<u8> {
+    let mut layout = 1u32.to_ne_bytes().to_vec();
+    layout.extend_from_slice(&(size_of::<G1>() as u32).to_be_bytes());
+    layout.extend_from_slice(raw_bytes(&[G1::generator()]));
+    layout.extend_from_slice(&(size_of::<G2>() as u32).to_be_bytes());
+    layout.extend_from_slice(raw_bytes(&[G2::generator()]));
+    layout
+}
+
+/// Returns the in-memory representation of the points.
+fn raw_bytes<G: RawAffine>(points: &[G]) -> &[u8] {
+    // Safety: `G` has no padding, see `RawAffine`.
+    unsafe {
+        std::slice::from_raw_parts(points.as_ptr() as *const u8, std::mem::size_of_val(points))
+    }
+}
+
+/// The data a prover SRS is read from.
+enum SrsData<'a> {
+    /// The tables are copied into memory.
+    Slice(&'a [u8]),
+    /// The tables are used in place.
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(&'a Arc<Mmap>),
+}
+
+impl SrsData<'_> {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            SrsData::Slice(data) => data,
+            #[cfg(not(target_arch = "wasm32"))]
+            SrsData::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// Wraps a writer to keep track of the offset within the file, to align the tables.
+struct OffsetWriter<W> {
+    writer: W,
+    offset: usize,
+}
+
+impl<W: Write> OffsetWriter<W> {
+    fn new(writer: W) -> Self {
+        OffsetWriter { writer, offset: 0 }
+    }
+}
+
+impl<W: Write> Write for OffsetWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.offset += len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Returns the number of bytes from `offset` to the next multiple of `TABLE_ALIGNMENT`.
+fn table_padding(offset: usize) -> usize {
+    (TABLE_ALIGNMENT - offset % TABLE_ALIGNMENT) % TABLE_ALIGNMENT
+}
+
+/// Reads the header of a prover SRS and returns the offset of the data following it.
+fn read_header_at(
+    data: &SrsData<'_>,
+    kind: SpecializedSrsKind,
+    generic_hash: &[u8],
+    n: usize,
+    layout: &[u8],
+) -> io::Result<usize> {
+    let mut rest = data.bytes();
+    read_header(&mut rest, kind, generic_hash, n, layout)?;
+    Ok(data.bytes().len() - rest.len())
+}
+
+/// Writes the precomputed tables with the in-memory representation of the points, whose layout
+/// is recorded in the header of the file.
+fn write_table<G, W>(w: &mut OffsetWriter<W>, table: &MultiscalarPrecompOwned<G>) -> io::Result<()>
+where
+    G: RawAffine,
+    W: Write,
+{
+    w.write_u32::<BigEndian>(table.window_size() as u32)?;
+    w.write_u32::<BigEndian>(table.num_points() as u32)?;
+    let padding = table_padding(w.offset);
+    w.write_all(&[0u8; TABLE_ALIGNMENT][..padding])?;
+    w.write_all(raw_bytes(table.tables()))
+}
+
+/// Reads the precomputed tables of `num_points` points at `offset` and advances it.
+fn read_table<G>(
+    data: &SrsData<'_>,
+    offset: &mut usize,
+    num_points: usize,
+    checked: bool,
+) -> io::Result<MultiscalarPrecompOwned<G>>
+where
+    G: RawAffine,
+{
+    let mut header = take_bytes(data.bytes(), offset, 2 * size_of::<u32>())?;
+    let window_size = header.read_u32::<BigEndian>()? as usize;
+    if window_size != WINDOW_SIZE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid table window size {}", window_size),
+        ));
+    }
+    if header.read_u32::<BigEndian>()? as usize != num_points {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid number of table points",
+        ));
+    }
+    *offset += table_padding(*offset);
+
+    let len = num_points * ((1 << window_size) - 1);
+    let bytes = take_bytes(data.bytes(), offset, len * size_of::<G>())?;
+    let table = match data {
+        SrsData::Slice(_) => {
+            let mut tables = vec![G::identity(); len];
+            // Safety: every bit pattern is a valid point, see `RawAffine`.
+            unsafe { std::slice::from_raw_parts_mut(tables.as_mut_ptr() as *mut u8, bytes.len()) }
+                .copy_from_slice(bytes);
+            MultiscalarPrecompOwned::from_tables(window_size, tables)
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        SrsData::Mapped(mmap) => {
+            // Safety: the tables are aligned to `TABLE_ALIGNMENT` within the file, which is
+            // mapped at a page boundary, and every bit pattern is a valid point, see `RawAffine`.
+            unsafe {
+                MultiscalarPrecompOwned::from_mapped(
+                    window_size,
+                    num_points,
+                    Arc::clone(mmap),
+                    *offset - bytes.len(),
+                )
+            }
+        }
+    };
+
+    if checked {
+        check_points(table.tables())?;
+    }
+    Ok(table)
+}
+
+/// Checks that the points read from their in-memory representation are valid, in the correct
+/// subgroup and in canonical form, by checking that they survive an encoding round trip.
+fn check_points<G: RawAffine>(points: &[G]) -> io::Result<()> {
+    points.par_iter().try_for_each(|point| {
+        let decoded: Option<G> = G::from_uncompressed(&point.to_uncompressed()).into();
+        if decoded.as_ref() == Some(point) {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::InvalidData, "invalid point"))
+        }
+    })
+}
+
+/// Returns the `len` bytes at `offset` and advances it.
+fn take_bytes<'a>(data: &'a [u8], offset: &mut usize, len: usize) -> io::Result<&'a [u8]> {
+    let bytes = data
+        .get(*offset..*offset + len)
+        .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "specialized SRS is truncated"))?;
+    *offset += len;
+    Ok(bytes)
+}
+
+fn decode_uncompressed<G: UncompressedEncoding>(enc: &[u8], checked: bool) -> io::Result<G> {
+    let mut repr = G::Uncompressed::default();
+    repr.as_mut().copy_from_slice(enc);
+    let opt: Option<G> = if checked {
+        G::from_uncompressed(&repr).into()
+    } else {
+        G::from_uncompressed_unchecked(&repr).into()
+    };
+    opt.ok_or_else(|| Error::new(ErrorKind::InvalidData, "not on curve"))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -483,4 +1152,107 @@ mod test {
         GenericSRS::<Bls12>::read(&mut Cursor::new(&new_buffer))
             .expect_err("this should have failed");
     }
+
+    #[test]
+    fn test_prover_srs_specialize() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let srs = setup_fake_srs::<Bls12, _>(&mut rng, 16);
+        let pk = srs.specialize_prover(16);
+        for num_proofs in [2, 3, 8] {
+            let expected = srs.specialize_prover(num_proofs);
+            let specialized = pk.specialize(num_proofs);
+            assert_eq!(specialized.n, expected.n);
+            assert!(specialized.g_alpha_powers_table == expected.g_alpha_powers_table);
+            assert!(specialized.g_beta_powers_table == expected.g_beta_powers_table);
+            assert!(specialized.h_alpha_powers_table == expected.h_alpha_powers_table);
+            assert!(specialized.h_beta_powers_table == expected.h_beta_powers_table);
+            assert_eq!(specialized.vkey.a, expected.vkey.a);
+            assert_eq!(specialized.vkey.b, expected.vkey.b);
+            assert_eq!(specialized.wkey.a, expected.wkey.a);
+            assert_eq!(specialized.wkey.b, expected.wkey.b);
+            assert_eq!(specialized.generic_hash, srs.hash());
+        }
+    }
+
+    #[test]
+    fn test_specialized_srs_write_read() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let srs = setup_fake_srs::<Bls12, _>(&mut rng, 16);
+        let hash = srs.hash();
+        let (pk, vk) = srs.specialize(5);
+
+        let mut buffer = Vec::new();
+        pk.write(&mut buffer).unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&buffer).unwrap();
+        let mmap = unsafe { Mmap::map(file.as_file()).unwrap() };
+        let mapped_pk = ProverSRS::<Bls12>::read_mmap(mmap, &hash, 8, true).unwrap();
+        for read_pk in [
+            ProverSRS::<Bls12>::read(&mut Cursor::new(&buffer), &hash, 5, true).unwrap(),
+            mapped_pk.specialize(8),
+            mapped_pk,
+        ] {
+            assert_eq!(read_pk.n, pk.n);
+            assert!(read_pk.g_alpha_powers_table == pk.g_alpha_powers_table);
+            assert!(read_pk.g_beta_powers_table == pk.g_beta_powers_table);
+            assert!(read_pk.h_alpha_powers_table == pk.h_alpha_powers_table);
+            assert!(read_pk.h_beta_powers_table == pk.h_beta_powers_table);
+            assert_eq!(read_pk.vkey.a, pk.vkey.a);
+            assert_eq!(read_pk.vkey.b, pk.vkey.b);
+            assert_eq!(read_pk.wkey.a, pk.wkey.a);
+            assert_eq!(read_pk.wkey.b, pk.wkey.b);
+            assert_eq!(read_pk.generic_hash, hash);
+        }
+        // wrong generic srs, wrong size, wrong kind and truncated data
+        let mut other_hash = hash.clone();
+        other_hash[0] ^= 1;
+        ProverSRS::<Bls12>::read(&mut Cursor::new(&buffer), &other_hash, 8, true).unwrap_err();
+        ProverSRS::<Bls12>::read(&mut Cursor::new(&buffer), &hash, 16, true).unwrap_err();
+        VerifierSRS::<Bls12>::read(&mut Cursor::new(&buffer), &hash, 8, true).unwrap_err();
+        ProverSRS::<Bls12>::read(
+            &mut Cursor::new(&buffer[..buffer.len() - 1]),
+            &hash,
+            8,
+            true,
+        )
+        .unwrap_err();
+        // different point representation
+        let layout_offset = SPECIALIZED_SRS_MAGIC.len() + 4 + 1 + 4 + hash.len() + 4 + 4;
+        for byte in [0, 4, 8] {
+            let mut invalid = buffer.clone();
+            invalid[layout_offset + byte] ^= 1;
+            let err =
+                ProverSRS::<Bls12>::read(&mut Cursor::new(&invalid), &hash, 8, false).unwrap_err();
+            assert!(err.to_string().contains("different point representation"));
+        }
+        // invalid point in a table
+        let mut invalid = buffer.clone();
+        let last = invalid.len() - 1;
+        invalid[last] ^= 1;
+        ProverSRS::<Bls12>::read(&mut Cursor::new(&invalid), &hash, 8, true).unwrap_err();
+
+        let mut buffer = Vec::new();
+        vk.write(&mut buffer).unwrap();
+        let read_vk =
+            VerifierSRS::<Bls12>::read(&mut Cursor::new(&buffer), &hash, 8, true).unwrap();
+        assert!(read_vk == vk);
+        VerifierSRS::<Bls12>::read(&mut Cursor::new(&buffer), &other_hash, 8, true).unwrap_err();
+
+        let (pk, _) = srs.specialize_input_aggregation(4);
+        let mut buffer = Vec::new();
+        pk.write(&mut buffer).unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&buffer).unwrap();
+        let mmap = unsafe { Mmap::map(file.as_file()).unwrap() };
+        for read_pk in [
+            ProverSRSInputAggregation::<Bls12>::read(&mut Cursor::new(&buffer), &hash, 4, true)
+                .unwrap(),
+            ProverSRSInputAggregation::<Bls12>::read_mmap(mmap, &hash, 4, false).unwrap(),
+        ] {
+            assert!(read_pk.prover_srs.g_alpha_powers_table == pk.prover_srs.g_alpha_powers_table);
+            assert!(read_pk.g_alpha_powers_end_table == pk.g_alpha_powers_end_table);
+            assert_eq!(read_pk.prover_srs.vkey.a, pk.prover_srs.vkey.a);
+            assert_eq!(read_pk.prover_srs.wkey.b, pk.prover_srs.wkey.b);
+        }
+    }
 }
@@ -1,20 +1,83 @@
 use std::marker::PhantomData;
 
+use digest::Digest;
 use ff::{Field, PrimeField};
 use serde::Serialize;
-use sha2::{Digest, Sha256};
+use sha2::Sha256;
+use sha3::Keccak256;
 
 use pairing::Engine;
 
 const PREFIX: &str = "snarkpack-v1";
 
+/// The Fiat-Shamir transcript used by the SnarkPack prover and verifier. A transcript is created
+/// for a single challenge: the values are absorbed in order and the transcript is then consumed
+/// into a challenge or into raw bytes. The prover and verifier must use the same implementation.
+///
+/// The values are absorbed with their type, so that an implementation can absorb them natively,
+/// e.g. as field elements, instead of hashing a byte encoding of them. A sequence of values is
+/// absorbed as its length followed by its elements.
+pub trait TranscriptProtocol<E: Engine>: Sized {
+    /// Creates a new transcript, domain separated by the `application_tag`.
+    fn new(application_tag: &str) -> Self;
+
+    /// Absorbs a byte string, such as a digest or a transcript inclusion.
+    fn append_bytes(self, bytes: &[u8]) -> Self;
+
+    /// Absorbs an integer, such as a number of proofs or the length of a sequence.
+    fn append_u64(self, n: u64) -> Self;
+
+    /// Absorbs a scalar.
+    fn append_scalar(self, s: &E::Fr) -> Self;
+
+    /// Absorbs a point of the first source group.
+    fn append_g1(self, p: &E::G1Affine) -> Self;
+
+    /// Absorbs a point of the second source group.
+    fn append_g2(self, p: &E::G2Affine) -> Self;
+
+    /// Absorbs an element of the target group.
+    fn append_gt(self, gt: &E::Gt) -> Self;
+
+    /// Absorbs the scalars, prefixed by their number.
+    fn append_scalars(self, scalars: &[E::Fr]) -> Self {
+        scalars
+            .iter()
+            .fold(self.append_u64(scalars.len() as u64), |t, s| {
+                t.append_scalar(s)
+            })
+    }
+
+    /// Absorbs the points of the first source group, prefixed by their number.
+    fn append_g1s(self, points: &[E::G1Affine]) -> Self {
+        points
+            .iter()
+            .fold(self.append_u64(points.len() as u64), |t, p| t.append_g1(p))
+    }
+
+    /// Returns the digest of all values absorbed so far.
+    fn into_bytes(self) -> Vec<u8>;
+
+    /// Generates a challenge from the transcript. The challenge is never zero or one.
+    fn into_challenge(self) -> Challenge<E>;
+}
+
+/// A transcript hashing the bincode serialization of the values with the hash function `D`,
+/// whose output must be as large as the scalar field representation. SHA-256 is used by default.
+/// Points are serialized compressed, and a byte string is prefixed by its length.
 #[derive(Debug)]
-pub struct Transcript<E: Engine> {
-    hasher: Sha256,
+pub struct Transcript<E: Engine, D = Sha256> {
+    hasher: D,
     buffer: Vec<u8>,
     _e: PhantomData<E>,
 }
 
+/// The default SnarkPack transcript.
+pub type Sha256Transcript<E> = Transcript<E, Sha256>;
+
+/// A transcript using Keccak-256, which is cheap to verify on the EVM.
+pub type Keccak256Transcript<E> = Transcript<E, Keccak256>;
+
 /// A challenge derived from the transcript.
 #[derive(Debug, Clone)]
 pub struct Challenge<E: Engine>(E::Fr);
@@ -49,9 +112,33 @@ where
     }
 }
 
-impl<E: Engine> Transcript<E> {
-    pub fn new(application_tag: &str) -> Self {
-        let mut hasher = sha2::Sha256::new();
+impl<E: Engine> Challenge<E> {
+    /// Wraps a scalar derived by a transcript.
+    pub fn new(c: E::Fr) -> Self {
+        Challenge(c)
+    }
+}
+
+impl<E: Engine, D: Digest> Transcript<E, D> {
+    fn absorb<S: Serialize + ?Sized>(mut self, el: &S) -> Self {
+        bincode::serialize_into(&mut self.buffer, el).expect("vec");
+        self.hasher.update(&self.buffer);
+        self.buffer.clear();
+        self
+    }
+}
+
+impl<E, D> TranscriptProtocol<E> for Transcript<E, D>
+where
+    E: Engine,
+    E::Fr: Serialize,
+    E::G1Affine: Serialize,
+    E::G2Affine: Serialize,
+    E::Gt: Serialize,
+    D: Digest + Clone,
+{
+    fn new(application_tag: &str) -> Self {
+        let mut hasher = D::new();
         hasher.update(PREFIX);
         hasher.update(application_tag);
 
@@ -62,19 +149,35 @@ impl<E: Engine> Transcript<E> {
         }
     }
 
-    pub fn write<S: Serialize>(mut self, el: &S) -> Self {
-        bincode::serialize_into(&mut self.buffer, el).expect("vec");
-        self.hasher.update(&self.buffer);
-        self.buffer.clear();
-        self
+    fn append_bytes(self, bytes: &[u8]) -> Self {
+        self.absorb(bytes)
+    }
+
+    fn append_u64(self, n: u64) -> Self {
+        self.absorb(&n)
+    }
+
+    fn append_scalar(self, s: &E::Fr) -> Self {
+        self.absorb(s)
+    }
+
+    fn append_g1(self, p: &E::G1Affine) -> Self {
+        self.absorb(p)
+    }
+
+    fn append_g2(self, p: &E::G2Affine) -> Self {
+        self.absorb(p)
+    }
+
+    fn append_gt(self, gt: &E::Gt) -> Self {
+        self.absorb(gt)
     }
 
-    pub fn into_bytes(self) -> Vec<u8> {
+    fn into_bytes(self) -> Vec<u8> {
         self.hasher.finalize().to_vec()
     }
 
-    /// Generate a challenge from the transcript.
-    pub fn into_challenge(mut self) -> Challenge<E> {
+    fn into_challenge(mut self) -> Challenge<E> {
         let repr_bits = <<E as Engine>::Fr as PrimeField>::Repr::default()
             .as_ref()
             .len()
@@ -90,7 +193,8 @@ impl<E: Engine> Transcript<E> {
             let digest = curr_state.finalize();
 
             let mut repr = <<E as Engine>::Fr as PrimeField>::Repr::default();
-            repr.as_mut().copy_from_slice(&digest);
+            let len = repr.as_ref().len();
+            repr.as_mut().copy_from_slice(&digest[..len]);
 
             // Mask away the digest bits which exceed the field size.
             *repr.as_mut().last_mut().unwrap() &= 0xff >> shave_bits;
@@ -115,6 +219,7 @@ mod test {
     use blstrs::{Bls12, G1Affine, G2Affine, Scalar as Fr};
     use ff::Field;
     use group::prime::PrimeCurveAffine;
+    use hex_literal::hex;
     use pairing::{MillerLoopResult, MultiMillerLoop};
 
     #[test]
@@ -125,17 +230,71 @@ mod test {
         let gt = <Bls12 as MultiMillerLoop>::multi_miller_loop(&[(&g1, &g2.into())])
             .final_exponentiation();
 
-        t = t.write(&g1).write(&g2).write(&gt).write(&Fr::ONE);
+        t = t
+            .append_g1(&g1)
+            .append_g2(&g2)
+            .append_gt(&gt)
+            .append_scalar(&Fr::ONE);
 
         let c1 = t.into_challenge();
 
-        let t2 = Transcript::new("test")
-            .write(&g1)
-            .write(&g2)
-            .write(&gt)
-            .write(&Fr::ONE);
+        let t2 = Transcript::<Bls12>::new("test")
+            .append_g1(&g1)
+            .append_g2(&g2)
+            .append_gt(&gt)
+            .append_scalar(&Fr::ONE);
 
         let c12 = t2.into_challenge();
         assert_eq!(c1, c12);
     }
+
+    #[test]
+    fn test_transcript_vectors() {
+        let t = Sha256Transcript::<Bls12>::new("test");
+        assert_eq!(
+            t.into_bytes(),
+            hex!("c63829f6019c576a9445543ef78640f6afc96b6d93fe2124bac9502a55544820")
+        );
+        let t = Sha256Transcript::<Bls12>::new("test")
+            .append_u64(1)
+            .append_u64(u64::MAX);
+        assert_eq!(
+            t.into_bytes(),
+            hex!("58facd3adaea7d185aa5ef24cdb53838d85654b7db2b0eb94b7d93b2617898fb")
+        );
+        let t = Sha256Transcript::<Bls12>::new("test")
+            .append_u64(1)
+            .append_u64(u64::MAX);
+        assert_eq!(
+            t.into_challenge().to_repr(),
+            hex!("3dc800b68ee7edee9b7109cbfb3211980448465331f47ff80a52e8ffb30db82c")
+        );
+
+        // byte strings are prefixed by their length
+        let t = Sha256Transcript::<Bls12>::new("test").append_bytes(b"abc");
+        assert_eq!(
+            t.into_bytes(),
+            hex!("cb7db83e7899966d7fee924688f82b17888eb6e317c2a3ee18e8c9b00915dca3")
+        );
+
+        let t = Keccak256Transcript::<Bls12>::new("test");
+        assert_eq!(
+            t.into_bytes(),
+            hex!("bebcb4f4f184859562a7a57af2b6849a9f81df88ad96a60c5389f5b782847b21")
+        );
+        let t = Keccak256Transcript::<Bls12>::new("test")
+            .append_u64(1)
+            .append_u64(u64::MAX);
+        assert_eq!(
+            t.into_bytes(),
+            hex!("4dcf8ad18c3a733d3d77ac188a28fdd17b86adf76eaaa65b901288b95f4cc3d6")
+        );
+        let t = Keccak256Transcript::<Bls12>::new("test")
+            .append_u64(1)
+            .append_u64(u64::MAX);
+        assert_eq!(
+            t.into_challenge().to_repr(),
+            hex!("53fa3270a9043becd847a7bb7ccf50dc24b66e3aa21501f20c2be4e73d4bfd36")
+        );
+    }
 }
@@ -10,10 +10,18 @@ use serde::Serialize;
 use super::{
     accumulator::PairingChecks,
     inner_product,
-    prove::polynomial_evaluation_product_form_from_transcript,
+    link::LinkPlan,
+    prove::{
+        instance_transcript_include, linked_instance_transcript_include,
+        multi_vk_transcript_include, polynomial_evaluation_product_form_from_transcript,
+        sharded_challenge_r, sharded_challenge_s, statement_transcript_include,
+        LinkedInputCommitments,
+    },
     structured_scalar_power,
-    transcript::{Challenge, Transcript},
-    AggregateProof, AggregateProofAndInstance, KZGOpening, VerifierSRS,
+    transcript::{Challenge, Sha256Transcript, TranscriptProtocol},
+    AggregateProof, AggregateProofAndInstance, AggregateProofAndLinkedInstances, InstanceLink,
+    KZGOpening, MippProof, MultiAggregateProof, ShardCommitment, ShardProof, ShardedAggregateProof,
+    VerifierSRS,
 };
 use crate::groth16::{
     aggregate::AggregateVersion,
@@ -24,12 +32,34 @@ use bellpepper_core::SynthesisError;
 
 use std::default::Default;
 use std::ops::{AddAssign, MulAssign, SubAssign};
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
 
+/// `std::time::Instant` panics on `wasm32-unknown-unknown`. The timings are only used for
+/// logging, hence they are reported as zero there.
+#[cfg(target_arch = "wasm32")]
+struct Instant;
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    fn now() -> Self {
+        Instant
+    }
+
+    fn elapsed(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
+
 /// Verifies the aggregated proofs thanks to the Groth16 verifying key, the
 /// verifier SRS from the aggregation scheme, all the public inputs of the
 /// proofs and the aggregated proof.
 ///
+/// Only the public inputs of the real proofs are passed in, if the proofs were
+/// padded to a power of two during aggregation, the public inputs of the
+/// padding proofs are derived from the last ones. The verifier SRS must be
+/// specialized for the padded number of proofs.
+///
 /// WARNING: transcript_include represents everything that should be included in
 /// the transcript from outside the boundary of this function. This is especially
 /// relevant for ALL public inputs of ALL individual proofs. In the regular case,
@@ -57,8 +87,539 @@ where
     E::G1Affine: Serialize,
     E::G2Affine: Serialize,
     R: rand_core::RngCore + Send,
+{
+    verify_aggregate_proof_with_transcript::<E, R, Sha256Transcript<E>>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        public_inputs,
+        proof,
+        transcript_include,
+        version,
+    )
+}
+
+/// Verifies the aggregated proofs like `verify_aggregate_proof`, deriving the
+/// challenges with the transcript `T`. The proofs must have been aggregated
+/// with the same transcript, see `aggregate_proofs_with_transcript`.
+pub fn verify_aggregate_proof_with_transcript<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    version.check_unbound()?;
+    verify_aggregate_proof_inner::<E, R, T>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        public_inputs,
+        proof,
+        transcript_include,
+        version,
+    )
+}
+
+/// Verifies the aggregated proofs like `verify_aggregate_proof_with_transcript`,
+/// for any version.
+fn verify_aggregate_proof_inner<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
 {
     info!("verify_aggregate_proof");
+    check_aggregate_proof(pvk, public_inputs, proof)?;
+
+    let pairing_checks = PairingChecks::new(rng);
+    merge_aggregate_proof_checks::<E, R, T>(
+        ip_verifier_srs,
+        pvk,
+        public_inputs,
+        proof,
+        transcript_include,
+        version,
+        &pairing_checks,
+        false,
+    );
+
+    let res = pairing_checks.verify();
+    info!("aggregate verify done");
+    res
+}
+
+/// Verifies the aggregated proofs created by `aggregate_proofs_with_statement`.
+/// With `AggregateVersion::V3`, the transcript absorbs the digest of the
+/// verifying key `pvk` was prepared from, the hash of the generic SRS the
+/// verifier SRS was specialized from (see `VerifierSRS::generic_hash`), the
+/// number of proofs, the number of public inputs and the `public_inputs` of all
+/// proofs, so that the aggregated proof only verifies for the statement it was
+/// created for. With the other versions, this is the same as
+/// `verify_aggregate_proof`.
+pub fn verify_aggregate_proof_with_statement<E, R>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize,
+    E::G2Affine: Serialize,
+    R: rand_core::RngCore + Send,
+{
+    verify_aggregate_proof_with_statement_and_transcript::<E, R, Sha256Transcript<E>>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        public_inputs,
+        proof,
+        transcript_include,
+        version,
+    )
+}
+
+/// Verifies the aggregated proofs like `verify_aggregate_proof_with_statement`,
+/// deriving the challenges with the transcript `T`. The proofs must have been
+/// aggregated with the same transcript, see
+/// `aggregate_proofs_with_statement_and_transcript`.
+pub fn verify_aggregate_proof_with_statement_and_transcript<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    match version {
+        AggregateVersion::V1 | AggregateVersion::V2 => verify_aggregate_proof_inner::<E, R, T>(
+            ip_verifier_srs,
+            pvk,
+            rng,
+            public_inputs,
+            proof,
+            transcript_include,
+            version,
+        ),
+        AggregateVersion::V3 => {
+            let statement_include = statement_transcript_include::<E, T>(
+                &pvk.digest(),
+                &ip_verifier_srs.generic_hash,
+                pvk.ic.len() - 1,
+                public_inputs,
+                transcript_include,
+            );
+            verify_aggregate_proof_inner::<E, R, T>(
+                ip_verifier_srs,
+                pvk,
+                rng,
+                public_inputs,
+                proof,
+                &statement_include,
+                version,
+            )
+        }
+    }
+}
+
+/// Verifies the aggregated proofs of different circuits, created by
+/// `aggregate_proofs_multi_vk`. `items` contains the verifying key and the
+/// public inputs of every proof, in the order in which the proofs were
+/// aggregated. The verifying keys are compared by `PreparedVerifyingKey::digest`.
+/// The verifier SRS must be specialized for the largest group of proofs.
+///
+/// With `AggregateVersion::V3`, the proofs must have been aggregated by
+/// `aggregate_proofs_multi_vk_with_statement`: the aggregated proof of each
+/// circuit only verifies for its verifying key, the generic SRS and the public
+/// inputs of its proofs, see `verify_aggregate_proof_with_statement`.
+///
+/// WARNING: the same assumptions on transcript_include as for `verify_aggregate_proof` apply.
+#[allow(clippy::type_complexity)]
+pub fn verify_aggregate_proof_multi_vk<E, R>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    rng: R,
+    items: &[(&PreparedVerifyingKey<E>, &[E::Fr])],
+    proof: &MultiAggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize,
+    E::G2Affine: Serialize,
+    R: rand_core::RngCore + Send,
+{
+    verify_aggregate_proof_multi_vk_with_transcript::<E, R, Sha256Transcript<E>>(
+        ip_verifier_srs,
+        rng,
+        items,
+        proof,
+        transcript_include,
+        version,
+    )
+}
+
+/// Verifies the aggregated proofs of different circuits like
+/// `verify_aggregate_proof_multi_vk`, deriving the challenges with the
+/// transcript `T`, see `aggregate_proofs_multi_vk_with_transcript`.
+#[allow(clippy::type_complexity)]
+pub fn verify_aggregate_proof_multi_vk_with_transcript<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    rng: R,
+    items: &[(&PreparedVerifyingKey<E>, &[E::Fr])],
+    proof: &MultiAggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    info!("verify_aggregate_proof_multi_vk");
+    proof.parsing_check()?;
+
+    // Group the public inputs by verifying key, the same way the proofs were grouped.
+    let mut groups: Vec<(&PreparedVerifyingKey<E>, Vec<Vec<E::Fr>>)> = Vec::new();
+    for (pvk, public_inputs) in items {
+        match groups.iter_mut().find(|(g, _)| g.digest() == pvk.digest()) {
+            Some((_, group)) => group.push(public_inputs.to_vec()),
+            None => groups.push((pvk, vec![public_inputs.to_vec()])),
+        }
+    }
+    if groups.len() != proof.groups.len() {
+        return Err(SynthesisError::MalformedProofs(
+            "number of verifying keys does not match the number of aggregated proofs".to_string(),
+        ));
+    }
+
+    // A single proof was aggregated with a copy of it.
+    for (_, public_inputs) in groups.iter_mut() {
+        if public_inputs.len() == 1 {
+            public_inputs.push(public_inputs[0].clone());
+        }
+    }
+
+    let num_groups = groups.len();
+    let include = |i: usize| {
+        let group_include = multi_vk_transcript_include::<E, T>(transcript_include, i, num_groups);
+        match version {
+            AggregateVersion::V1 | AggregateVersion::V2 => group_include,
+            AggregateVersion::V3 => {
+                let (pvk, public_inputs) = &groups[i];
+                statement_transcript_include::<E, T>(
+                    &pvk.digest(),
+                    &ip_verifier_srs.generic_hash,
+                    pvk.ic.len() - 1,
+                    public_inputs,
+                    &group_include,
+                )
+            }
+        }
+    };
+    verify_aggregate_proof_groups::<E, R, T>(
+        ip_verifier_srs,
+        rng,
+        &groups,
+        &proof.groups,
+        include,
+        version,
+    )
+}
+
+/// Verifies aggregated proofs with a single final pairing check. `groups`
+/// contains the verifying key and the public inputs of the proofs aggregated in
+/// each of `proofs`, the `i`-th aggregated proof is verified with the transcript
+/// inclusion `include(i)`. The verifier SRS must be specialized for the largest
+/// group.
+#[allow(clippy::type_complexity)]
+fn verify_aggregate_proof_groups<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    rng: R,
+    groups: &[(&PreparedVerifyingKey<E>, Vec<Vec<E::Fr>>)],
+    proofs: &[AggregateProof<E>],
+    include: impl Fn(usize) -> Vec<u8> + Sync,
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    for ((pvk, public_inputs), group_proof) in groups.iter().zip(proofs.iter()) {
+        check_aggregate_proof(pvk, public_inputs, group_proof)?;
+        if public_inputs.len().next_power_of_two() > ip_verifier_srs.n {
+            return Err(SynthesisError::MalformedSrs);
+        }
+    }
+
+    let pairing_checks = PairingChecks::new(rng);
+    groups
+        .par_iter()
+        .zip(proofs.par_iter())
+        .enumerate()
+        .for_each(|(i, ((pvk, public_inputs), group_proof))| {
+            // Only `n` depends on the number of proofs, `h_alpha_d` is not used
+            // for the verification of aggregated proofs.
+            let group_srs = VerifierSRS {
+                n: public_inputs.len().next_power_of_two(),
+                ..ip_verifier_srs.clone()
+            };
+            // Only one of the aggregated Groth16 equations can be merged without randomization.
+            merge_aggregate_proof_checks::<E, R, T>(
+                &group_srs,
+                pvk,
+                public_inputs,
+                group_proof,
+                &include(i),
+                version,
+                &pairing_checks,
+                i > 0,
+            );
+        });
+
+    let res = pairing_checks.verify();
+    info!("aggregate verify done");
+    res
+}
+
+/// Verifies many aggregated proofs at once, each given by its verifier SRS,
+/// verifying key, public inputs, aggregated proof and transcript inclusion.
+/// All pairing checks are randomized and merged, so that a single final
+/// exponentiation is needed for the whole batch.
+///
+/// Returns the indices of all aggregated proofs that fail verification, an
+/// empty list means that all of them are valid. Malformed proofs, or proofs
+/// that don't match their public inputs, are reported as invalid. If the
+/// batch check fails, the set is recursively bisected to find the invalid
+/// proofs, see `verify_proofs_batch_with_culprits`.
+///
+/// With `AggregateVersion::V3`, each aggregated proof is bound to its statement
+/// like with `verify_aggregate_proof_with_statement`: its transcript absorbs
+/// the digest of its verifying key, the hash of the generic SRS its verifier
+/// SRS was specialized from and its public inputs.
+///
+/// WARNING: the same assumptions on transcript_include as for `verify_aggregate_proof` apply.
+#[allow(clippy::type_complexity)]
+pub fn verify_aggregate_proofs_batch<E, R>(
+    items: &[(
+        &VerifierSRS<E>,
+        &PreparedVerifyingKey<E>,
+        &[Vec<E::Fr>],
+        &AggregateProof<E>,
+        &[u8],
+    )],
+    rng: &mut R,
+    version: AggregateVersion,
+) -> Result<Vec<usize>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize,
+    E::G2Affine: Serialize,
+    R: rand_core::RngCore + Send,
+{
+    verify_aggregate_proofs_batch_with_transcript::<E, R, Sha256Transcript<E>>(items, rng, version)
+}
+
+/// Verifies many aggregated proofs at once like `verify_aggregate_proofs_batch`,
+/// deriving the challenges with the transcript `T`. All proofs must have been
+/// aggregated with the same transcript, see `aggregate_proofs_with_transcript`
+/// and `aggregate_proofs_with_statement_and_transcript`.
+#[allow(clippy::type_complexity)]
+pub fn verify_aggregate_proofs_batch_with_transcript<E, R, T>(
+    items: &[(
+        &VerifierSRS<E>,
+        &PreparedVerifyingKey<E>,
+        &[Vec<E::Fr>],
+        &AggregateProof<E>,
+        &[u8],
+    )],
+    rng: &mut R,
+    version: AggregateVersion,
+) -> Result<Vec<usize>, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    info!("verify_aggregate_proofs_batch");
+    let statement_includes: Vec<Vec<u8>> = match version {
+        AggregateVersion::V1 | AggregateVersion::V2 => Vec::new(),
+        AggregateVersion::V3 => items
+            .par_iter()
+            .map(|(srs, pvk, public_inputs, _, transcript_include)| {
+                statement_transcript_include::<E, T>(
+                    &pvk.digest(),
+                    &srs.generic_hash,
+                    pvk.ic.len() - 1,
+                    public_inputs,
+                    transcript_include,
+                )
+            })
+            .collect(),
+    };
+    let bound_items;
+    let items = if statement_includes.is_empty() {
+        items
+    } else {
+        bound_items = items
+            .iter()
+            .zip(statement_includes.iter())
+            .map(|(&(srs, pvk, public_inputs, proof, _), include)| {
+                (srs, pvk, public_inputs, proof, &include[..])
+            })
+            .collect::<Vec<_>>();
+        &bound_items[..]
+    };
+
+    let mut culprits = Vec::new();
+    let mut indices = Vec::with_capacity(items.len());
+    for (i, (_, pvk, public_inputs, proof, _)) in items.iter().enumerate() {
+        match check_aggregate_proof(pvk, public_inputs, proof) {
+            Ok(()) => indices.push(i),
+            Err(_) => culprits.push(i),
+        }
+    }
+
+    if !indices.is_empty()
+        && !verify_aggregate_proofs_subset::<E, R, T>(items, &indices, rng, version)?
+    {
+        find_aggregate_culprits::<E, R, T>(items, &indices, rng, version, &mut culprits)?;
+        culprits.sort_unstable();
+    }
+    info!("aggregate batch verify done");
+    Ok(culprits)
+}
+
+/// Verifies the aggregated proofs at the given `indices` of `items` with a
+/// single randomized pairing check.
+#[allow(clippy::type_complexity)]
+fn verify_aggregate_proofs_subset<E, R, T>(
+    items: &[(
+        &VerifierSRS<E>,
+        &PreparedVerifyingKey<E>,
+        &[Vec<E::Fr>],
+        &AggregateProof<E>,
+        &[u8],
+    )],
+    indices: &[usize],
+    rng: &mut R,
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    let pairing_checks = PairingChecks::new(rng);
+    indices.par_iter().for_each(|&i| {
+        let (srs, pvk, public_inputs, proof, transcript_include) = items[i];
+        merge_aggregate_proof_checks::<E, _, T>(
+            srs,
+            pvk,
+            public_inputs,
+            proof,
+            transcript_include,
+            version,
+            &pairing_checks,
+            true,
+        );
+    });
+    pairing_checks.verify()
+}
+
+/// Bisects a set of aggregated proofs that is known to fail the batch check.
+#[allow(clippy::type_complexity)]
+fn find_aggregate_culprits<E, R, T>(
+    items: &[(
+        &VerifierSRS<E>,
+        &PreparedVerifyingKey<E>,
+        &[Vec<E::Fr>],
+        &AggregateProof<E>,
+        &[u8],
+    )],
+    indices: &[usize],
+    rng: &mut R,
+    version: AggregateVersion,
+    culprits: &mut Vec<usize>,
+) -> Result<(), SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    if indices.len() == 1 {
+        culprits.push(indices[0]);
+        return Ok(());
+    }
+
+    let (left, right) = indices.split_at(indices.len() / 2);
+    let left_valid = verify_aggregate_proofs_subset::<E, R, T>(items, left, rng, version)?;
+    if !left_valid {
+        find_aggregate_culprits::<E, R, T>(items, left, rng, version, culprits)?;
+    }
+
+    // If the left half is valid, the right half must contain the invalid proof, hence it is not
+    // checked as a whole again.
+    if !left_valid && verify_aggregate_proofs_subset::<E, R, T>(items, right, rng, version)? {
+        return Ok(());
+    }
+    find_aggregate_culprits::<E, R, T>(items, right, rng, version, culprits)
+}
+
+/// Checks that the aggregated proof is well formed and that it matches the
+/// public inputs and the verifying key.
+fn check_aggregate_proof<E>(
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+) -> Result<(), SynthesisError>
+where
+    E: MultiMillerLoop,
+    <E as Engine>::Gt: Compress,
+{
     proof.parsing_check()?;
     for pub_input in public_inputs {
         if (pub_input.len() + 1) != pvk.ic.len() {
@@ -71,30 +632,57 @@ where
             "public inputs length does not match nproofs".to_string(),
         ));
     }
+    Ok(())
+}
 
-    let hcom = Transcript::<E>::new("hcom")
-        .write(&proof.com_ab)
-        .write(&proof.com_c)
-        .into_challenge();
+/// Merges all the pairing checks of an aggregated proof into `pairing_checks`.
+/// The aggregated Groth16 equation is only randomized if `randomize` is set,
+/// which is needed if more than one aggregated proof is merged.
+#[allow(clippy::too_many_arguments)]
+fn merge_aggregate_proof_checks<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+    pairing_checks: &PairingChecks<E, R>,
+    randomize: bool,
+) where
+    E: MultiMillerLoop + std::fmt::Debug,
+    T: TranscriptProtocol<E>,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+{
+    let num_proofs = public_inputs.len();
+    let num_padded_proofs = num_proofs.next_power_of_two();
+
+    let mut hcom_transcript = T::new("hcom")
+        .append_gt(&proof.com_ab.0)
+        .append_gt(&proof.com_ab.1)
+        .append_gt(&proof.com_c.0)
+        .append_gt(&proof.com_c.1);
+    // The number of real proofs is only bound if padding was applied.
+    if num_proofs != num_padded_proofs {
+        hcom_transcript = hcom_transcript.append_u64(num_proofs as u64);
+    }
+    let hcom = hcom_transcript.into_challenge();
 
     // Random linear combination of proofs
-    let r = Transcript::<E>::new("random-r")
-        .write(&hcom)
-        .write(&transcript_include)
+    let r = T::new("random-r")
+        .append_scalar(&hcom)
+        .append_bytes(transcript_include)
         .into_challenge();
 
-    let pairing_checks = PairingChecks::new(rng);
-    let pairing_checks_copy = &pairing_checks;
-
     // 1.Check TIPA proof ab
     // 2.Check TIPA proof c
     //        s.spawn(move |_| {
     let now = Instant::now();
-    verify_tipp_mipp::<E, R>(
+    verify_tipp_mipp::<E, R, T>(
         ip_verifier_srs,
         proof,
         &r, // we give the extra r as it's not part of the proof itself - it is simply used on top for the groth16 aggregation
-        pairing_checks_copy,
+        pairing_checks,
         &hcom,
         version,
     );
@@ -105,7 +693,7 @@ where
     // SUM a^i = (1 - a^n) / (1 - a) = -(1-a^n)/-(1-a)
     // = (a^n - 1) / (a - 1)
     info!("checking aggregate pairing");
-    let mut r_sum = r.pow_vartime([public_inputs.len() as u64]);
+    let mut r_sum = r.pow_vartime([num_padded_proofs as u64]);
     r_sum.sub_assign(&E::Fr::ONE);
     let b = (*r - E::Fr::ONE).invert().unwrap();
     r_sum.mul_assign(&b);
@@ -121,31 +709,25 @@ where
 
     let now = Instant::now();
     r_vec_sender
-        .send(structured_scalar_power(public_inputs.len(), &*r))
+        .send(structured_scalar_power(num_padded_proofs, &*r))
         .unwrap();
     let elapsed = now.elapsed().as_millis();
     debug!("generation of r vector: {}ms", elapsed);
 
     par! {
         // 3. Compute left part of the final pairing equation
-        let left = {
+        let alpha_g1_r_sum = {
             let mut alpha_g1_r_sum = pvk.alpha_g1;
             alpha_g1_r_sum.mul_assign(r_sum);
-
-            E::multi_miller_loop(&[(&alpha_g1_r_sum.to_affine(), &pvk.beta_g2)])
+            alpha_g1_r_sum.to_affine()
         },
         // 4. Compute right part of the final pairing equation
-        let right = {
-            E::multi_miller_loop(&[(
-                // e(c^r vector form, h^delta)
-                // let agg_c = inner_product::multiexponentiation::<E::G1Affine>(&c, r_vec)
-                &proof.agg_c.to_affine(),
-                &pvk.delta_g2,
-            )])
-        },
+        // e(c^r vector form, h^delta)
+        // let agg_c = inner_product::multiexponentiation::<E::G1Affine>(&c, r_vec)
+        let agg_c = proof.agg_c.to_affine(),
         // 5. compute the middle part of the final pairing equation, the one
         //    with the public inputs
-        let middle = {
+        let g_ic = {
             // We want to compute MUL(i:0 -> l) S_i ^ (SUM(j:0 -> n) ai,j * r^j)
             // this table keeps tracks of incremental computation of each i-th
             // exponent to later multiply with S_i
@@ -159,17 +741,25 @@ where
             g_ic.mul_assign(r_sum);
 
             let powers = r_vec_receiver.recv().unwrap();
+            // The padding proofs are copies of the last proof, hence their public inputs are
+            // accumulated with the sum of their powers.
+            let padding_power: E::Fr = powers[num_proofs..].iter().sum();
 
             let now = Instant::now();
             // now we do the multi exponentiation
             let getter = |i: usize| -> <E::Fr as PrimeField>::Repr {
                 // i denotes the column of the public input, and j denotes which public input
                 let mut c = public_inputs[0][i];
-                for j in 1..public_inputs.len() {
+                for j in 1..num_proofs {
                     let mut ai = public_inputs[j][i];
                     ai.mul_assign(&powers[j]);
                     c.add_assign(&ai);
                 }
+                if num_proofs != num_padded_proofs {
+                    let mut ai = public_inputs[num_proofs - 1][i];
+                    ai.mul_assign(&padding_power);
+                    c.add_assign(&ai);
+                }
                 c.to_repr()
             };
 
@@ -180,40 +770,146 @@ where
             );
 
             g_ic.add_assign(&totsi);
-
-            let ml = E::multi_miller_loop(&[(&g_ic.to_affine(), &pvk.gamma_g2)]);
             let elapsed = now.elapsed().as_millis();
             debug!("table generation: {}ms", elapsed);
 
-            ml
+            g_ic.to_affine()
         }
     };
 
-    pairing_checks_copy.merge_nonrandom(
-        vec![left, middle, right],
-        // final value ip_ab is what we want to compare in the groth16
-        // aggregated equation A * B
-        proof.ip_ab,
-    );
-
-    let res = pairing_checks.verify();
-    info!("aggregate verify done");
-    res
+    // final value ip_ab is what we want to compare in the groth16
+    // aggregated equation A * B
+    let pairs = [
+        (&alpha_g1_r_sum, &pvk.beta_g2),
+        (&g_ic, &pvk.gamma_g2),
+        (&agg_c, &pvk.delta_g2),
+    ];
+    if randomize {
+        pairing_checks.merge_miller_inputs_prepared(&pairs, &proof.ip_ab);
+    } else {
+        let left = pairs
+            .par_iter()
+            .map(|(a, b)| E::multi_miller_loop(&[(*a, *b)]))
+            .collect();
+        pairing_checks.merge_nonrandom(left, proof.ip_ab);
+    }
 }
 
 /// verification of related instances i.e. when instances are given by
 /// [a1, ... , an, b1, ... , bn], [b1, ... , bn, c1, ..., cn], [c1, ..., cn, d1, ..., dn] etc
+/// The aggregated proof is created by `aggregate_proofs_and_instances`, see
+/// `verify_aggregate_proof_and_linked_instances` for the general relations.
+///
+/// These aggregations don't support `AggregateVersion::V3`, binding the
+/// aggregated proof to the verifying key and to the generic SRS is left to the
+/// caller, see `aggregate_proofs_and_linked_instances`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_aggregate_proof_and_aggregate_instances<
+    E: Engine + std::fmt::Debug,
+    R: rand::RngCore + Send,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[E::Fr],
+    public_outputs: &[E::Fr],
+    aggregate_proof_and_instance: &AggregateProofAndInstance<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize,
+    E::G2Affine: Serialize,
+    R: rand_core::RngCore + Send,
+{
+    verify_aggregate_proof_and_aggregate_instances_with_transcript::<E, R, Sha256Transcript<E>>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        public_inputs,
+        public_outputs,
+        aggregate_proof_and_instance,
+        transcript_include,
+        version,
+    )
+}
+
+/// Verifies the aggregation of proofs with chained statements like
+/// `verify_aggregate_proof_and_aggregate_instances`, deriving the challenges
+/// with the transcript `T`, see `aggregate_proofs_and_instances_with_transcript`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_aggregate_proof_and_aggregate_instances_with_transcript<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[E::Fr],
+    public_outputs: &[E::Fr],
+    aggregate_proof_and_instance: &AggregateProofAndInstance<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    info!("verify_aggregate_proof");
+    version.check_unbound()?;
+    aggregate_proof_and_instance.parsing_check()?;
+
+    let num_inputs = pvk.ic.len() - 1;
+    if public_inputs.len() + public_outputs.len() != num_inputs
+        || public_inputs.len() != public_outputs.len()
+    {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let links = InstanceLink::chain(num_inputs);
+    let boundary = [public_inputs, public_outputs].concat();
+    let proof = AggregateProofAndLinkedInstances {
+        pi_agg: aggregate_proof_and_instance.pi_agg.clone(),
+        com_inputs: aggregate_proof_and_instance.com_f.clone(),
+        com_shifts: aggregate_proof_and_instance.com_w0.clone(),
+        com_bounds: aggregate_proof_and_instance.com_wd.clone(),
+        input_evals: aggregate_proof_and_instance.f_eval.clone(),
+        input_eval_proofs: aggregate_proof_and_instance.f_eval_proof.clone(),
+    };
+    verify_linked_inputs::<E, R, T>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        &boundary,
+        &links,
+        &proof,
+        version,
+        |com| instance_transcript_include::<E, T>(com, transcript_include),
+    )
+}
+
+/// Verifies an aggregation of proofs whose public inputs are related by the
+/// `links`, created by `aggregate_proofs_and_linked_instances`. `boundary`
+/// contains the values of the public inputs returned by
+/// `linked_instance_boundary`, in that order. The verifier SRS must be
+/// specialized for the number of proofs.
+///
+/// These aggregations don't support `AggregateVersion::V3`, binding the
+/// aggregated proof to the verifying key and to the generic SRS is left to the
+/// caller, see `aggregate_proofs_and_linked_instances`.
+///
+/// WARNING: the same assumptions on transcript_include as for `verify_aggregate_proof` apply.
 #[allow(clippy::too_many_arguments)]
-pub fn verify_aggregate_proof_and_aggregate_instances<
-    E: Engine + std::fmt::Debug,
-    R: rand::RngCore + Send,
->(
+pub fn verify_aggregate_proof_and_linked_instances<E, R>(
     ip_verifier_srs: &VerifierSRS<E>,
     pvk: &PreparedVerifyingKey<E>,
     rng: R,
-    public_inputs: &[E::Fr],
-    public_outputs: &[E::Fr],
-    aggregate_proof_and_instance: &AggregateProofAndInstance<E>,
+    boundary: &[E::Fr],
+    links: &[InstanceLink],
+    proof: &AggregateProofAndLinkedInstances<E>,
     transcript_include: &[u8],
     version: AggregateVersion,
 ) -> Result<bool, SynthesisError>
@@ -226,105 +922,208 @@ where
     E::G2Affine: Serialize,
     R: rand_core::RngCore + Send,
 {
-    info!("verify_aggregate_proof");
-    aggregate_proof_and_instance.parsing_check()?;
-    let proof = &aggregate_proof_and_instance.pi_agg;
+    verify_aggregate_proof_and_linked_instances_with_transcript::<E, R, Sha256Transcript<E>>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        boundary,
+        links,
+        proof,
+        transcript_include,
+        version,
+    )
+}
 
-    if (public_inputs.len() + public_outputs.len() + 1) != pvk.ic.len() {
-        return Err(SynthesisError::MalformedVerifyingKey);
+/// Verifies the aggregation of proofs with linked public inputs like
+/// `verify_aggregate_proof_and_linked_instances`, deriving the challenges with
+/// the transcript `T`, see `aggregate_proofs_and_linked_instances_with_transcript`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_aggregate_proof_and_linked_instances_with_transcript<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    boundary: &[E::Fr],
+    links: &[InstanceLink],
+    proof: &AggregateProofAndLinkedInstances<E>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    info!("verify_aggregate_proof_and_linked_instances");
+    version.check_unbound()?;
+    proof.parsing_check()?;
+    verify_linked_inputs::<E, R, T>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        boundary,
+        links,
+        proof,
+        version,
+        |com| linked_instance_transcript_include::<E, T>(links, com, transcript_include),
+    )
+}
+
+/// Verifies the aggregated proof and the commitments to the polynomials of the
+/// public inputs following the `LinkPlan` of the `links`. `transcript_include`
+/// returns the transcript inclusion the proof was aggregated with, see
+/// `aggregate_linked_inputs`.
+#[allow(clippy::too_many_arguments)]
+fn verify_linked_inputs<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    boundary: &[E::Fr],
+    links: &[InstanceLink],
+    proof: &AggregateProofAndLinkedInstances<E>,
+    version: AggregateVersion,
+    transcript_include: impl FnOnce(&LinkedInputCommitments<'_, E>) -> Vec<u8>,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    let pi_agg = &proof.pi_agg;
+    let num_proofs = pi_agg.tmipp.gipa.nproofs as usize;
+    if ip_verifier_srs.n != num_proofs {
+        return Err(SynthesisError::MalformedSrs);
+    }
+
+    let num_inputs = pvk.ic.len() - 1;
+    let plan = LinkPlan::new(links, num_inputs, num_proofs)?;
+    if proof.com_inputs.len() != plan.committed.len() {
+        return Err(SynthesisError::MalformedProofs(format!(
+            "com_inputs must be equal to {}",
+            plan.committed.len()
+        )));
+    }
+    let num_shifts: usize = plan.shifts.iter().sum();
+    if proof.com_shifts.len() != num_shifts {
+        return Err(SynthesisError::MalformedProofs(format!(
+            "com_shifts must be equal to {}",
+            num_shifts
+        )));
+    }
+    if boundary.len() != plan.boundary.len() {
+        return Err(SynthesisError::MalformedProofs(format!(
+            "boundary must be equal to {}",
+            plan.boundary.len()
+        )));
     }
 
-    let transcript_new = Transcript::<E>::new("transcript-with-coms")
-        .write(&aggregate_proof_and_instance.com_f)
-        .write(&aggregate_proof_and_instance.com_w0)
-        .write(&aggregate_proof_and_instance.com_wd)
-        .write(&transcript_include)
-        .into_bytes();
+    let transcript_new = transcript_include(&LinkedInputCommitments {
+        boundary,
+        com_inputs: &proof.com_inputs,
+        com_shifts: &proof.com_shifts,
+        com_bounds: &proof.com_bounds,
+    });
 
-    let hcom = Transcript::<E>::new("hcom")
-        .write(&proof.com_ab)
-        .write(&proof.com_c)
+    let hcom = T::new("hcom")
+        .append_gt(&pi_agg.com_ab.0)
+        .append_gt(&pi_agg.com_ab.1)
+        .append_gt(&pi_agg.com_c.0)
+        .append_gt(&pi_agg.com_c.1)
         .into_challenge();
 
     // Random linear combination of proofs
-    let r = Transcript::<E>::new("random-r")
-        .write(&hcom)
-        .write(&transcript_new)
+    let r = T::new("random-r")
+        .append_scalar(&hcom)
+        .append_bytes(&transcript_new)
         .into_challenge();
 
-    let r_f = (*r).pow_vartime([1u64]);
+    // The evaluations at r of the polynomials of all public inputs.
+    let mut evals: Vec<Option<E::Fr>> = vec![None; num_inputs];
+    for (&j, eval) in plan.committed.iter().zip(proof.input_evals.iter()) {
+        evals[j] = Some(*eval);
+    }
+    let value = |i: usize, j: usize| {
+        let idx = plan
+            .boundary
+            .binary_search(&(i, j))
+            .expect("boundary input");
+        boundary[idx]
+    };
+    for link in &plan.derived {
+        let to_eval = evals[link.to].expect("derived from a committed input");
+        evals[link.from] = Some(link.eval_from(to_eval, &r, num_proofs, value));
+    }
+    let evals: Vec<E::Fr> = evals
+        .into_iter()
+        .map(|eval| eval.expect("all inputs are known"))
+        .collect();
+    for link in &plan.checked {
+        if link.eval_to(evals[link.from], &r, num_proofs, value) != evals[link.to] {
+            return Ok(false);
+        }
+    }
 
-    //    let pairing_checks_instance: PairingChecks<E,R> = PairingChecks::new(rng2);
     let pairing_checks: PairingChecks<E, R> = PairingChecks::new(rng);
     let pairing_checks_copy = &pairing_checks;
 
-    for (i, public_input) in public_inputs.iter().enumerate() {
-        // check com_f has a_0 as zero'th coefficient: com_f - a0 * g
-        let d = (aggregate_proof_and_instance.com_f[i] - (ip_verifier_srs.g * public_input))
-            .to_affine();
-
-        pairing_checks_copy.merge_miller_inputs(
-            &[
-                (&d, &ip_verifier_srs.h.to_affine()),
-                (
-                    &aggregate_proof_and_instance.com_w0[i].to_affine(),
-                    &ip_verifier_srs.h_alpha.to_affine(),
-                ),
-            ],
-            &<E as Engine>::Gt::generator(),
-        );
-
-        // check com_f has bounded degree
-        pairing_checks_copy.merge_miller_inputs(
-            &[
-                (
-                    &aggregate_proof_and_instance.com_f[i].to_affine(),
-                    &ip_verifier_srs.h_alpha_d.to_affine(),
-                ),
-                (
-                    &aggregate_proof_and_instance.com_wd[i].to_affine(),
-                    &ip_verifier_srs.h.to_affine(),
-                ),
-            ],
-            &<E as Engine>::Gt::generator(),
-        );
-
-        // check com_f evaluates to d2 at r d2 = F g^(-eval)
-        let d2 = (aggregate_proof_and_instance.com_f[i]
-            - (ip_verifier_srs.g * aggregate_proof_and_instance.f_eval[i]))
-            .to_affine();
-
-        let d2 = -d2;
-
-        let d3 = (ip_verifier_srs.h_alpha - (ip_verifier_srs.h * r_f)).to_affine();
+    let g = ip_verifier_srs.g;
+    let h = ip_verifier_srs.h.to_affine();
+    let h_alpha = ip_verifier_srs.h_alpha.to_affine();
+    let h_alpha_d = ip_verifier_srs.h_alpha_d.to_affine();
+    let mut com_shifts = proof.com_shifts.iter();
+    for ((&j, &k), (com, com_bound)) in plan
+        .committed
+        .iter()
+        .zip(plan.shifts.iter())
+        .zip(proof.com_inputs.iter().zip(proof.com_bounds.iter()))
+    {
+        // check the first k coefficients of the polynomial F, one at a time:
+        // e(W_t - F_t g, h) = e(W_(t+1), h^alpha), with W_0 = F
+        let mut w = *com;
+        for t in 0..k {
+            let com_shift = com_shifts.next().expect("number of shifts is checked");
+            let d = (w - (g * value(t, j))).to_affine();
+            pairing_checks_copy.merge_miller_inputs(
+                &[(&d, &h), (&com_shift.to_affine(), &h_alpha)],
+                &<E as Engine>::Gt::generator(),
+            );
+            w = -*com_shift;
+        }
 
+        // check F has bounded degree: e(F, h^alpha^d) = e(F^alpha^d, h)
         pairing_checks_copy.merge_miller_inputs(
-            &[
-                (&d2, &ip_verifier_srs.h.to_affine()),
-                (
-                    &aggregate_proof_and_instance.f_eval_proof[i].to_affine(),
-                    &d3,
-                ),
-            ],
+            &[(&com.to_affine(), &h_alpha_d), (&com_bound.to_affine(), &h)],
             &<E as Engine>::Gt::generator(),
         );
     }
 
-    rayon::scope(move |_s| {
-        // 1.Check TIPA proof ab
-        // 2.Check TIPA proof c
-        //        s.spawn(move |_| {
+    // check that the commitments open to the evaluations at r:
+    // e(com - g^eval, h) = e(proof, h^alpha - h^r)
+    let h_alpha_r = (ip_verifier_srs.h_alpha - (ip_verifier_srs.h * *r)).to_affine();
+    proof
+        .com_inputs
+        .par_iter()
+        .zip(proof.input_evals.par_iter())
+        .zip(proof.input_eval_proofs.par_iter())
+        .for_each(|((com, eval), eval_proof)| {
+            let d = (ip_verifier_srs.g * eval - com).to_affine();
+            pairing_checks_copy.merge_miller_inputs(
+                &[(&d, &h), (&eval_proof.to_affine(), &h_alpha_r)],
+                &<E as Engine>::Gt::generator(),
+            );
+        });
 
+    rayon::scope(move |_s| {
         let now = Instant::now();
-        verify_tipp_mipp::<E, R>(
+        verify_tipp_mipp::<E, R, T>(
             ip_verifier_srs,
-            proof,
+            pi_agg,
             &r, // we give the extra r as it's not part of the proof itself - it is simply used on top for the groth16 aggregation
             pairing_checks_copy,
             &hcom,
             version,
         );
-
         debug!("TIPP took {} ms", now.elapsed().as_millis(),);
 
         // Check aggregate pairing product equation
@@ -332,19 +1131,11 @@ where
         // SUM a^i = (1 - a^n) / (1 - a) = -(1-a^n)/-(1-a)
         // = (a^n - 1) / (a - 1)
         info!("checking aggregate pairing");
-        let mut r_sum = r.pow_vartime([ip_verifier_srs.n as u64]);
+        let mut r_sum = r.pow_vartime([num_proofs as u64]);
         r_sum.sub_assign(&E::Fr::ONE);
         let b = (*r - E::Fr::ONE).invert().unwrap();
         r_sum.mul_assign(&b);
 
-        // The following parts 3 4 5 are independently computing the parts of the Groth16
-        // verification equation
-        // NOTE From this point on, we are only checking *one* pairing check (the Groth16
-        // verification equation) so we don't need to randomize as all other checks are being
-        // randomized already. When merging all pairing checks together, this will be the only one
-        // non-randomized.
-        //
-
         par! {
             // 3. Compute left part of the final pairing equation
             let left = {
@@ -356,33 +1147,19 @@ where
             let middle = {
                 // first public input is 1 for all circuits.
                 let mut g_ic = pvk.ic_projective[0] * r_sum;
-
-                for i in 0..public_inputs.len() {
-                    // g_ic = prod_i Si^(f_i(r)) S_(i + n)^( 1/r( f_i(r) - a0) + a_n r^(n-1))
-                    g_ic += pvk.ic[1 + i] * aggregate_proof_and_instance.f_eval[i];
-
-                    // d = f(r) - a0
-                    let mut d = aggregate_proof_and_instance.f_eval[i] - public_inputs[i];
-                    // d = (1/r) (f(r) - a0)
-                    d *= &r.invert().unwrap();
-                    // d = (1/r) (f(r) - a0 ) + r^(n-1) an
-                    let n_neg_one = (ip_verifier_srs.n - 1) as u64;
-                    d += public_outputs[i] * r.pow_vartime([n_neg_one]) ;
-
-                    // pk_ic_in = S_(i + m + 1)^d
-                    let pk_ic_in = pvk.ic[1 + i + public_inputs.len()] * d;
-                    g_ic += pk_ic_in;
+                // g_ic = prod_j S_j^(f_j(r)) where f_j is the polynomial of input j
+                for (ic, eval) in pvk.ic[1..].iter().zip(evals.iter()) {
+                    g_ic += *ic * eval;
                 }
 
-                E::multi_miller_loop(&[(&g_ic.to_affine() , &pvk.gamma_g2)])
+                E::multi_miller_loop(&[(&g_ic.to_affine(), &pvk.gamma_g2)])
             },
 
             // 4. Compute right part of the final pairing equation
             let right = {
                 E::multi_miller_loop(&[(
                     // e(c^r vector form, h^delta)
-                    // let agg_c = inner_product::multiexponentiation::<E::G1Affine>(&c, r_vec)
-                    &proof.agg_c.to_affine(),
+                    &pi_agg.agg_c.to_affine(),
                     &pvk.delta_g2,
                 )])
             }
@@ -392,7 +1169,7 @@ where
             vec![left, middle, right],
             // final value ip_ab is what we want to compare in the groth16
             // aggregated equation A * B
-            proof.ip_ab,
+            pi_agg.ip_ab,
         );
     });
 
@@ -401,10 +1178,406 @@ where
     res
 }
 
+/// Verifies a sharded aggregated proof created by `aggregate_proofs_sharded`.
+/// `public_inputs` are the public inputs of all proofs, shard after shard, and
+/// `ip_verifier_srs` must be specialized for the number of proofs the shards are
+/// padded to, see `ShardedAggregateProof::shard_size`.
+///
+/// The verifier checks the TIPP proof of every shard, the single MIPP proof over
+/// the combination of the C of all shards, and one Groth16 equation for all
+/// proofs. All pairing checks are merged into a single final exponentiation, but
+/// the work still grows with the number of shards.
+///
+/// WARNING: the same assumptions on transcript_include as for `verify_aggregate_proof` apply.
+pub fn verify_sharded_aggregate_proof<E, R>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &ShardedAggregateProof<E>,
+    transcript_include: &[u8],
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    E::Fr: Serialize,
+    <E as Engine>::Gt: Compress + Serialize,
+    E::G1: Serialize,
+    E::G1Affine: Serialize,
+    E::G2Affine: Serialize,
+    R: rand_core::RngCore + Send,
+{
+    verify_sharded_aggregate_proof_with_transcript::<E, R, Sha256Transcript<E>>(
+        ip_verifier_srs,
+        pvk,
+        rng,
+        public_inputs,
+        proof,
+        transcript_include,
+    )
+}
+
+/// Verifies a sharded aggregated proof like `verify_sharded_aggregate_proof`,
+/// deriving the challenges with the transcript `T`. The proof must have been
+/// created with the same transcript, see
+/// `aggregate_proofs_sharded_with_transcript`.
+pub fn verify_sharded_aggregate_proof_with_transcript<E, R, T>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    rng: R,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &ShardedAggregateProof<E>,
+    transcript_include: &[u8],
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop + std::fmt::Debug,
+    <E as Engine>::Gt: Compress,
+    R: rand_core::RngCore + Send,
+    T: TranscriptProtocol<E>,
+{
+    info!("verify_sharded_aggregate_proof");
+    proof.parsing_check()?;
+    if proof.shard_size() != ip_verifier_srs.n {
+        return Err(SynthesisError::MalformedSrs);
+    }
+    for pub_input in public_inputs {
+        if (pub_input.len() + 1) != pvk.ic.len() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+    }
+    if public_inputs.len() != proof.nproofs() {
+        return Err(SynthesisError::MalformedProofs(
+            "public inputs length does not match nproofs".to_string(),
+        ));
+    }
+
+    let r = sharded_challenge_r::<E, T>(
+        &ip_verifier_srs.generic_hash,
+        ip_verifier_srs.n,
+        &proof.commitments,
+        transcript_include,
+    );
+    let s = sharded_challenge_s::<E, T>(&r, &proof.shards);
+    // 1, s, s^2, ... for the shards
+    let s_vec: Vec<E::Fr> = structured_scalar_power(proof.shards.len(), &*s);
+
+    let pairing_checks = PairingChecks::new(rng);
+
+    // 1. Check the TIPP proof of every shard
+    let now = Instant::now();
+    proof
+        .commitments
+        .par_iter()
+        .zip(proof.shards.par_iter())
+        .enumerate()
+        .for_each(|(i, (commitment, shard))| {
+            verify_shard_tipp::<E, R, T>(ip_verifier_srs, commitment, shard, &r, i, &pairing_checks)
+        });
+    debug!("TIPP of the shards took {} ms", now.elapsed().as_millis());
+
+    // 2. Check the MIPP proof of the C of all shards, combined with s. Their
+    //    commitment is the combination of the commitments of the shards.
+    let com_c = proof.commitments.iter().zip(s_vec.iter()).fold(
+        (<E as Engine>::Gt::identity(), <E as Engine>::Gt::identity()),
+        |(t, u), (com, sj)| (t + com.com_c.0 * sj, u + com.com_c.1 * sj),
+    );
+    let now = Instant::now();
+    verify_mipp::<E, R, T>(
+        ip_verifier_srs,
+        &com_c,
+        &proof.agg_c,
+        &proof.mipp,
+        &r,
+        &s,
+        &pairing_checks,
+    );
+    debug!("MIPP took {} ms", now.elapsed().as_millis());
+
+    // 3. Check the Groth16 equations of all shards, combined with s:
+    //    \prod_j ip_ab_j^{s^j} = e(alpha, beta)^{\sum_j s^j \sum_i r^i}
+    //    e(\sum_j s^j P_j, gamma) e(agg_c, delta)
+    info!("checking aggregate pairing");
+    let r_vec: Vec<E::Fr> = structured_scalar_power(ip_verifier_srs.n, &*r);
+    let r_sum: E::Fr = r_vec.iter().sum();
+    let s_sum: E::Fr = s_vec.iter().sum();
+    let ip_ab = proof
+        .shards
+        .iter()
+        .zip(s_vec.iter())
+        .fold(<E as Engine>::Gt::identity(), |acc, (shard, sj)| {
+            acc + shard.ip_ab * sj
+        });
+
+    // The weight of the public inputs of every proof in the combined equation.
+    // The padding proofs of a shard are copies of its last proof, hence their
+    // powers are accumulated on it.
+    let mut weights = Vec::with_capacity(public_inputs.len());
+    for (commitment, sj) in proof.commitments.iter().zip(s_vec.iter()) {
+        let num_proofs = commitment.nproofs as usize;
+        weights.extend(r_vec[..num_proofs].iter().map(|ri| *ri * sj));
+        let padding_power: E::Fr = r_vec[num_proofs..].iter().sum();
+        *weights.last_mut().expect("non empty shard") += padding_power * sj;
+    }
+
+    let mut rs_sum = r_sum;
+    rs_sum.mul_assign(&s_sum);
+    par! {
+        let alpha_g1_rs_sum = (pvk.alpha_g1 * rs_sum).to_affine(),
+        let agg_c = proof.agg_c.to_affine(),
+        let g_ic = {
+            let mut g_ic = pvk.ic_projective[0];
+            g_ic.mul_assign(rs_sum);
+
+            let getter = |i: usize| -> <E::Fr as PrimeField>::Repr {
+                public_inputs
+                    .iter()
+                    .zip(weights.iter())
+                    .fold(E::Fr::ZERO, |acc, (inputs, w)| acc + inputs[i] * w)
+                    .to_repr()
+            };
+            let totsi = par_multiscalar::<_, E::G1Affine>(
+                &ScalarList::Getter(getter, pvk.ic.len() - 1),
+                &pvk.multiscalar.at_point(1),
+                std::mem::size_of::<<E::Fr as PrimeField>::Repr>() * 8,
+            );
+            g_ic.add_assign(&totsi);
+            g_ic.to_affine()
+        }
+    };
+
+    let pairs = [
+        (&alpha_g1_rs_sum, &pvk.beta_g2),
+        (&g_ic, &pvk.gamma_g2),
+        (&agg_c, &pvk.delta_g2),
+    ];
+    let left = pairs
+        .par_iter()
+        .map(|(a, b)| E::multi_miller_loop(&[(*a, *b)]))
+        .collect();
+    pairing_checks.merge_nonrandom(left, ip_ab);
+
+    let res = pairing_checks.verify();
+    info!("sharded aggregate verify done");
+    res
+}
+
+/// Merges the pairing checks of the TIPP proof of the `shard`-th shard of a
+/// sharded aggregation into `pairing_checks`, see `prove_tipp`.
+fn verify_shard_tipp<E, R, T>(
+    v_srs: &VerifierSRS<E>,
+    commitment: &ShardCommitment<E>,
+    proof: &ShardProof<E>,
+    r_shift: &E::Fr,
+    shard: usize,
+    pairing_checks: &PairingChecks<E, R>,
+) where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+    R: rand_core::RngCore + Send,
+{
+    let tipp = &proof.tipp;
+    let mut challenges = Vec::with_capacity(tipp.comms_ab.len());
+    let mut challenges_inv = Vec::with_capacity(tipp.comms_ab.len());
+
+    let mut c_inv: E::Fr = *T::new("sharded-tipp")
+        .append_scalar(r_shift)
+        .append_u64(shard as u64)
+        .append_gt(&proof.ip_ab)
+        .into_challenge();
+    for (i, ((tab_l, tab_r), (zab_l, zab_r))) in
+        tipp.comms_ab.iter().zip(tipp.z_ab.iter()).enumerate()
+    {
+        c_inv = *T::new(&format!("sharded-tipp-{}", i))
+            .append_scalar(&c_inv)
+            .append_gt(zab_l)
+            .append_gt(zab_r)
+            .append_gt(&tab_l.0)
+            .append_gt(&tab_l.1)
+            .append_gt(&tab_r.0)
+            .append_gt(&tab_r.1)
+            .into_challenge();
+        challenges.push(c_inv.invert().unwrap());
+        challenges_inv.push(c_inv);
+    }
+
+    // KZG challenge point
+    let z = T::new("sharded-tipp-z")
+        .append_scalar(&c_inv)
+        .append_g1(&tipp.final_a)
+        .append_g2(&tipp.final_b)
+        .append_g2(&tipp.final_vkey.0)
+        .append_g2(&tipp.final_vkey.1)
+        .append_g1(&tipp.final_wkey.0)
+        .append_g1(&tipp.final_wkey.1)
+        .into_challenge();
+
+    // T, U and Z of the final A and B: we multiply the left side by x and the
+    // right side by x^-1
+    let identity = <E as Engine>::Gt::identity;
+    let (t, u, zab) = tipp
+        .comms_ab
+        .par_iter()
+        .zip(tipp.z_ab.par_iter())
+        .zip(challenges.par_iter().zip(challenges_inv.par_iter()))
+        .map(|(((tab_l, tab_r), (zab_l, zab_r)), (c, c_inv))| {
+            (
+                tab_l.0 * c + tab_r.0 * c_inv,
+                tab_l.1 * c + tab_r.1 * c_inv,
+                *zab_l * c + *zab_r * c_inv,
+            )
+        })
+        .reduce(
+            || (identity(), identity(), identity()),
+            |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2),
+        );
+    let final_tab = commitment.com_ab.0 + t;
+    let final_uab = commitment.com_ab.1 + u;
+    let final_zab = proof.ip_ab + zab;
+
+    // the polynomials of the commitment keys take the challenges in reverse order
+    challenges.reverse();
+    challenges_inv.reverse();
+
+    let fvkey = &tipp.final_vkey;
+    let fwkey = &tipp.final_wkey;
+    let final_a = &tipp.final_a;
+    let final_b = &tipp.final_b;
+    let (final_tab, final_uab, final_zab) = (&final_tab, &final_uab, &final_zab);
+    par! {
+        // check the opening proof for v
+        let _vtuple = verify_kzg_v(
+            v_srs,
+            fvkey,
+            &tipp.vkey_opening,
+            &challenges_inv,
+            &z,
+            pairing_checks,
+        ),
+        // check the opening proof for w - note that w has been rescaled by $r^{-1}$
+        let _wtuple = verify_kzg_w(
+            v_srs,
+            fwkey,
+            &tipp.wkey_opening,
+            &challenges,
+            &r_shift.invert().unwrap(),
+            &z,
+            pairing_checks,
+        ),
+        // z = e(A,B)
+        let _check_z = pairing_checks.merge_miller_inputs(&[(final_a, final_b)], final_zab),
+        // T = e(A,v1)e(w1,B)
+        let _check_ab0 = pairing_checks.merge_miller_inputs(&[(final_a, &fvkey.0), (&fwkey.0, final_b)], final_tab),
+        // U = e(A,v2)e(w2,B)
+        let _check_ab1 = pairing_checks.merge_miller_inputs(&[(final_a, &fvkey.1), (&fwkey.1, final_b)], final_uab)
+    };
+}
+
+/// Merges the pairing checks of the MIPP proof of a sharded aggregation into
+/// `pairing_checks`, see `prove_mipp`. `com_c` is the commitment to the combined
+/// C of all shards and `agg_c` the claimed C^r.
+fn verify_mipp<E, R, T>(
+    v_srs: &VerifierSRS<E>,
+    com_c: &(<E as Engine>::Gt, <E as Engine>::Gt),
+    agg_c: &E::G1,
+    mipp: &MippProof<E>,
+    r_shift: &E::Fr,
+    s: &E::Fr,
+    pairing_checks: &PairingChecks<E, R>,
+) where
+    E: MultiMillerLoop,
+    T: TranscriptProtocol<E>,
+    R: rand_core::RngCore + Send,
+{
+    let mut challenges = Vec::with_capacity(mipp.comms_c.len());
+    let mut challenges_inv = Vec::with_capacity(mipp.comms_c.len());
+
+    let mut c_inv: E::Fr = *T::new("sharded-mipp")
+        .append_scalar(s)
+        .append_g1(&agg_c.to_affine())
+        .into_challenge();
+    for (i, ((tc_l, tc_r), (zc_l, zc_r))) in mipp.comms_c.iter().zip(mipp.z_c.iter()).enumerate() {
+        c_inv = *T::new(&format!("sharded-mipp-{}", i))
+            .append_scalar(&c_inv)
+            .append_g1(&zc_l.to_affine())
+            .append_g1(&zc_r.to_affine())
+            .append_gt(&tc_l.0)
+            .append_gt(&tc_l.1)
+            .append_gt(&tc_r.0)
+            .append_gt(&tc_r.1)
+            .into_challenge();
+        challenges.push(c_inv.invert().unwrap());
+        challenges_inv.push(c_inv);
+    }
+
+    // KZG challenge point
+    let z = T::new("sharded-mipp-z")
+        .append_scalar(&c_inv)
+        .append_g1(&mipp.final_c)
+        .append_g2(&mipp.final_vkey.0)
+        .append_g2(&mipp.final_vkey.1)
+        .into_challenge();
+
+    // T, U and Z of the final C: we multiply the left side by x and the right
+    // side by x^-1
+    let (t, u, zc) = mipp
+        .comms_c
+        .par_iter()
+        .zip(mipp.z_c.par_iter())
+        .zip(challenges.par_iter().zip(challenges_inv.par_iter()))
+        .map(|(((tc_l, tc_r), (zc_l, zc_r)), (c, c_inv))| {
+            (
+                tc_l.0 * c + tc_r.0 * c_inv,
+                tc_l.1 * c + tc_r.1 * c_inv,
+                *zc_l * c + *zc_r * c_inv,
+            )
+        })
+        .reduce(
+            || {
+                (
+                    <E as Engine>::Gt::identity(),
+                    <E as Engine>::Gt::identity(),
+                    E::G1::identity(),
+                )
+            },
+            |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2),
+        );
+    let final_tc = com_c.0 + t;
+    let final_uc = com_c.1 + u;
+    let final_zc = *agg_c + zc;
+
+    // the polynomial of the commitment key takes the challenges in reverse order
+    challenges_inv.reverse();
+    let final_r =
+        polynomial_evaluation_product_form_from_transcript(&challenges_inv, r_shift, &E::Fr::ONE);
+
+    let fvkey = &mipp.final_vkey;
+    let final_c = &mipp.final_c;
+    let (final_tc, final_uc) = (&final_tc, &final_uc);
+    par! {
+        // check the opening proof for v
+        let _vtuple = verify_kzg_v(
+            v_srs,
+            fvkey,
+            &mipp.vkey_opening,
+            &challenges_inv,
+            &z,
+            pairing_checks,
+        ),
+        // T = e(C,v1)
+        let _check_t = pairing_checks.merge_miller_inputs(&[(final_c, &fvkey.0)], final_tc),
+        // U = e(C,v2)
+        let _check_u = pairing_checks.merge_miller_inputs(&[(final_c, &fvkey.1)], final_uc)
+    };
+
+    // Z == C^r, the only check that doesn't require a pairing
+    if *final_c * final_r != final_zc {
+        pairing_checks.invalidate();
+    }
+}
+
 /// verify_tipp_mipp returns a pairing equation to check the tipp proof.  $r$ is
 /// the randomness used to produce a random linear combination of A and B and
 /// used in the MIPP part with C
-fn verify_tipp_mipp<E, R>(
+fn verify_tipp_mipp<E, R, T>(
     v_srs: &VerifierSRS<E>,
     proof: &AggregateProof<E>,
     r_shift: &E::Fr,
@@ -413,18 +1586,15 @@ fn verify_tipp_mipp<E, R>(
     version: AggregateVersion,
 ) where
     E: MultiMillerLoop,
-    E::Fr: Serialize,
-    <E as Engine>::Gt: Compress + Serialize,
-    E::G1: Serialize,
-    E::G1Affine: Serialize,
-    E::G2Affine: Serialize,
+    T: TranscriptProtocol<E>,
+    <E as Engine>::Gt: Compress,
     R: rand_core::RngCore + Send,
 {
     info!("verify with srs shift");
     let now = Instant::now();
     // (T,U), Z for TIPP and MIPP  and all challenges
     let (final_res, final_r, challenges, challenges_inv, extra_challenge) =
-        gipa_verify_tipp_mipp(proof, r_shift, hcom, version);
+        gipa_verify_tipp_mipp::<E, T>(proof, r_shift, hcom, version);
     debug!(
         "TIPP verify: gipa verify tipp {}ms",
         now.elapsed().as_millis()
@@ -446,22 +1616,22 @@ fn verify_tipp_mipp<E, R>(
 
     // KZG challenge point
     let c = match version {
-        AggregateVersion::V1 => Transcript::<E>::new("random-z")
-            .write(&challenges[0])
-            .write(&fvkey.0)
-            .write(&fvkey.1)
-            .write(&fwkey.0)
-            .write(&fwkey.1)
+        AggregateVersion::V1 => T::new("random-z")
+            .append_scalar(&challenges[0])
+            .append_g2(&fvkey.0)
+            .append_g2(&fvkey.1)
+            .append_g1(&fwkey.0)
+            .append_g1(&fwkey.1)
             .into_challenge(),
-        AggregateVersion::V2 => Transcript::<E>::new("random-z")
-            .write(&extra_challenge)
-            .write(&fvkey.0)
-            .write(&fvkey.1)
-            .write(&fwkey.0)
-            .write(&fwkey.1)
-            .write(final_a)
-            .write(final_b)
-            .write(final_c)
+        AggregateVersion::V2 | AggregateVersion::V3 => T::new("random-z")
+            .append_scalar(&extra_challenge)
+            .append_g2(&fvkey.0)
+            .append_g2(&fvkey.1)
+            .append_g1(&fwkey.0)
+            .append_g1(&fwkey.1)
+            .append_g1(final_a)
+            .append_g2(final_b)
+            .append_g1(final_c)
             .into_challenge(),
     };
 
@@ -538,7 +1708,7 @@ fn verify_tipp_mipp<E, R>(
 /// MIPP share the same challenges however, enabling to re-use common operations
 /// between them, such as the KZG proof for commitment keys.
 #[allow(clippy::type_complexity)]
-fn gipa_verify_tipp_mipp<E>(
+fn gipa_verify_tipp_mipp<E, T>(
     proof: &AggregateProof<E>,
     r_shift: &E::Fr,
     hcom: &E::Fr,
@@ -546,11 +1716,8 @@ fn gipa_verify_tipp_mipp<E>(
 ) -> (GipaTUZ<E>, E::Fr, Vec<E::Fr>, Vec<E::Fr>, E::Fr)
 where
     E: MultiMillerLoop,
-    E::Fr: Serialize,
-    <E as Engine>::Gt: Compress + Serialize,
-    E::G1: Serialize,
-    E::G1Affine: Serialize,
-    E::G2Affine: Serialize,
+    T: TranscriptProtocol<E>,
+    <E as Engine>::Gt: Compress,
 {
     info!("gipa verify TIPP [version {}]", version);
     let gipa = &proof.tmipp.gipa;
@@ -567,11 +1734,11 @@ where
     let mut challenges = Vec::new();
     let mut challenges_inv = Vec::new();
 
-    let mut c_inv: E::Fr = *Transcript::<E>::new("gipa-0")
-        .write(hcom)
-        .write(&proof.ip_ab)
-        .write(&proof.agg_c)
-        .write(&r_shift)
+    let mut c_inv: E::Fr = *T::new("gipa-0")
+        .append_scalar(hcom)
+        .append_gt(&proof.ip_ab)
+        .append_g1(&proof.agg_c.to_affine())
+        .append_scalar(r_shift)
         .into_challenge();
     let mut c = c_inv.invert().unwrap();
 
@@ -595,41 +1762,41 @@ where
                 AggregateVersion::V1 => {
                     // already generated c_inv and c outside of the loop
                 }
-                AggregateVersion::V2 => {
+                AggregateVersion::V2 | AggregateVersion::V3 => {
                     // in this version we do fiat shamir with the first inputs
-                    c_inv = *Transcript::<E>::new("gipa-0")
-                        .write(&c_inv)
-                        .write(&zab_l)
-                        .write(&zab_r)
-                        .write(&zc_l)
-                        .write(&zc_r)
-                        .write(&tab_l.0)
-                        .write(&tab_l.1)
-                        .write(&tab_r.0)
-                        .write(&tab_r.1)
-                        .write(&tc_l.0)
-                        .write(&tc_l.1)
-                        .write(&tc_r.0)
-                        .write(&tc_r.1)
+                    c_inv = *T::new("gipa-0")
+                        .append_scalar(&c_inv)
+                        .append_gt(zab_l)
+                        .append_gt(zab_r)
+                        .append_g1(&zc_l.to_affine())
+                        .append_g1(&zc_r.to_affine())
+                        .append_gt(&tab_l.0)
+                        .append_gt(&tab_l.1)
+                        .append_gt(&tab_r.0)
+                        .append_gt(&tab_r.1)
+                        .append_gt(&tc_l.0)
+                        .append_gt(&tc_l.1)
+                        .append_gt(&tc_r.0)
+                        .append_gt(&tc_r.1)
                         .into_challenge();
                     c = c_inv.invert().unwrap();
                 }
             }
         } else {
-            c_inv = *Transcript::<E>::new(&format!("gipa-{}", i))
-                .write(&c_inv)
-                .write(&zab_l)
-                .write(&zab_r)
-                .write(&zc_l)
-                .write(&zc_r)
-                .write(&tab_l.0)
-                .write(&tab_l.1)
-                .write(&tab_r.0)
-                .write(&tab_r.1)
-                .write(&tc_l.0)
-                .write(&tc_l.1)
-                .write(&tc_r.0)
-                .write(&tc_r.1)
+            c_inv = *T::new(&format!("gipa-{}", i))
+                .append_scalar(&c_inv)
+                .append_gt(zab_l)
+                .append_gt(zab_r)
+                .append_g1(&zc_l.to_affine())
+                .append_g1(&zc_r.to_affine())
+                .append_gt(&tab_l.0)
+                .append_gt(&tab_l.1)
+                .append_gt(&tab_r.0)
+                .append_gt(&tab_r.1)
+                .append_gt(&tc_l.0)
+                .append_gt(&tc_l.1)
+                .append_gt(&tc_r.0)
+                .append_gt(&tc_r.1)
                 .into_challenge();
             c = c_inv.invert().unwrap();
         }
@@ -667,23 +1834,23 @@ where
 
     // This extra challenge is simply done to make the bridge between the
     // MIPP/TIPP proofs and the KZG proofs, but is not used in TIPP/MIPP.
-    let extra_challenge = *Transcript::<E>::new("gipa-extra-link")
-        .write(&challenges.last().unwrap())
-        .write(&proof.tmipp.gipa.final_a)
-        .write(&proof.tmipp.gipa.final_b)
-        .write(&proof.tmipp.gipa.final_c)
-        .write(&final_zab_l)
-        .write(&final_zab_r)
-        .write(&final_zc_l)
-        .write(&final_zc_r)
-        .write(&final_tab_l.0)
-        .write(&final_tab_l.1)
-        .write(&final_tab_r.0)
-        .write(&final_tab_r.1)
-        .write(&final_tuc_l.0)
-        .write(&final_tuc_l.1)
-        .write(&final_tuc_r.0)
-        .write(&final_tuc_r.1)
+    let extra_challenge = *T::new("gipa-extra-link")
+        .append_scalar(challenges.last().unwrap())
+        .append_g1(&proof.tmipp.gipa.final_a)
+        .append_g2(&proof.tmipp.gipa.final_b)
+        .append_g1(&proof.tmipp.gipa.final_c)
+        .append_gt(final_zab_l)
+        .append_gt(final_zab_r)
+        .append_g1(&final_zc_l.to_affine())
+        .append_g1(&final_zc_r.to_affine())
+        .append_gt(&final_tab_l.0)
+        .append_gt(&final_tab_l.1)
+        .append_gt(&final_tab_r.0)
+        .append_gt(&final_tab_r.1)
+        .append_gt(&final_tuc_l.0)
+        .append_gt(&final_tuc_l.1)
+        .append_gt(&final_tuc_r.0)
+        .append_gt(&final_tuc_r.1)
         .into_challenge();
 
     debug!("verify: extra challenge {:?}", extra_challenge);
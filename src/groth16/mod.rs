@@ -3,30 +3,35 @@
 //! [Groth16]: https://eprint.iacr.org/2016/260
 
 pub mod aggregate;
-#[cfg(not(feature = "cuda-supraseal"))]
+#[cfg(all(feature = "prover", not(feature = "cuda-supraseal")))]
 mod ext;
 #[cfg(feature = "cuda-supraseal")]
 mod ext_supraseal;
+#[cfg(feature = "prover")]
 mod generator;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "prover", not(target_arch = "wasm32")))]
 mod mapped_params;
+#[cfg(feature = "prover")]
 mod params;
 mod proof;
+#[cfg(feature = "prover")]
 mod prover;
 #[cfg(feature = "cuda-supraseal")]
 mod supraseal_params;
 mod verifier;
 mod verifying_key;
 
-mod multiscalar;
+pub(crate) mod multiscalar;
 
-#[cfg(not(feature = "cuda-supraseal"))]
+#[cfg(all(feature = "prover", not(feature = "cuda-supraseal")))]
 pub use self::ext::*;
 #[cfg(feature = "cuda-supraseal")]
 pub use self::ext_supraseal::*;
+#[cfg(feature = "prover")]
 pub use self::generator::*;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "prover", not(target_arch = "wasm32")))]
 pub use self::mapped_params::*;
+#[cfg(feature = "prover")]
 pub use self::params::*;
 pub use self::proof::*;
 #[cfg(feature = "cuda-supraseal")]
@@ -1,12 +1,22 @@
 use std::convert::TryInto;
 use std::ops::AddAssign;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
 
 use ff::PrimeField;
 use group::{prime::PrimeCurveAffine, Curve, Group};
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
 use rayon::prelude::*;
 
+use crate::glv::{Glv, GLV_BITS};
+
 pub const WINDOW_SIZE: usize = 8;
 
+/// Above this number of points, the chunks of `par_multiscalar` are large enough for the doublings
+/// saved by the GLV decomposition not to make up for deriving the entries of the tables.
+const GLV_MAX_POINTS: usize = 1024;
+
 /// Abstraction over either a slice or a getter to produce a fixed number of scalars.
 pub enum ScalarList<'a, G: PrimeCurveAffine, F: Fn(usize) -> <G::Scalar as PrimeField>::Repr> {
     Slice(&'a [<G::Scalar as PrimeField>::Repr]),
@@ -16,6 +26,7 @@ pub enum ScalarList<'a, G: PrimeCurveAffine, F: Fn(usize) -> <G::Scalar as Prime
 impl<'a, G: PrimeCurveAffine, F: Fn(usize) -> <G::Scalar as PrimeField>::Repr>
     ScalarList<'a, G, F>
 {
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         match self {
             ScalarList::Slice(s) => s.len(),
@@ -31,18 +42,134 @@ pub type Getter<G> =
 pub trait MultiscalarPrecomp<G: PrimeCurveAffine>: Send + Sync {
     fn window_size(&self) -> usize;
     fn window_mask(&self) -> u64;
-    fn tables(&self) -> &[Vec<G>];
+    /// Number of entries of the table of each point.
+    fn table_entries(&self) -> usize;
+    /// The tables of all points, one after the other.
+    fn tables(&self) -> &[G];
     fn at_point(&self, idx: usize) -> MultiscalarPrecompRef<'_, G>;
+
+    /// Returns the table of the `idx`-th point.
+    fn table(&self, idx: usize) -> &[G] {
+        let table_entries = self.table_entries();
+        &self.tables()[idx * table_entries..(idx + 1) * table_entries]
+    }
 }
 
-/// Owned variant of the multiscalar precomputations.
+/// Storage of the tables of `MultiscalarPrecompOwned`.
+#[derive(Clone, Debug)]
+enum Tables<G> {
+    Owned(Vec<G>),
+    /// `len` points starting at byte `offset` of the memory mapped file.
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped {
+        mmap: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl<G> Tables<G> {
+    fn as_slice(&self) -> &[G] {
+        match self {
+            Tables::Owned(tables) => tables,
+            #[cfg(not(target_arch = "wasm32"))]
+            Tables::Mapped { mmap, offset, len } => {
+                // Safety: the constructor `MultiscalarPrecompOwned::from_mapped` guarantees that
+                // the range is within the mapping, aligned and contains valid points.
+                unsafe { std::slice::from_raw_parts(mmap.as_ptr().add(*offset) as *const G, *len) }
+            }
+        }
+    }
+}
+
+/// Owned variant of the multiscalar precomputations. The tables are either in memory or in a
+/// memory mapped file that is kept alive by them.
 #[derive(Clone, Debug)]
 pub struct MultiscalarPrecompOwned<G: PrimeCurveAffine> {
     num_points: usize,
     window_size: usize,
     window_mask: u64,
     table_entries: usize,
-    tables: Vec<Vec<G>>,
+    tables: Tables<G>,
+}
+
+impl<G: PrimeCurveAffine> MultiscalarPrecompOwned<G> {
+    /// Returns the precomputations of the first `num_points` points. Tables in a memory mapped
+    /// file are not copied.
+    pub fn truncate(&self, num_points: usize) -> Self {
+        assert!(num_points <= self.num_points);
+        let len = num_points * self.table_entries;
+        let tables = match &self.tables {
+            Tables::Owned(tables) => Tables::Owned(tables[..len].to_vec()),
+            #[cfg(not(target_arch = "wasm32"))]
+            Tables::Mapped { mmap, offset, .. } => Tables::Mapped {
+                mmap: mmap.clone(),
+                offset: *offset,
+                len,
+            },
+        };
+        MultiscalarPrecompOwned {
+            num_points,
+            window_size: self.window_size,
+            window_mask: self.window_mask,
+            table_entries: self.table_entries,
+            tables,
+        }
+    }
+
+    /// Creates the precomputations from the tables of all points, as returned by `tables()`.
+    pub fn from_tables(window_size: usize, tables: Vec<G>) -> Self {
+        let table_entries = (1 << window_size) - 1;
+        assert_eq!(tables.len() % table_entries, 0);
+        MultiscalarPrecompOwned {
+            num_points: tables.len() / table_entries,
+            window_size,
+            window_mask: (1 << window_size) - 1,
+            table_entries,
+            tables: Tables::Owned(tables),
+        }
+    }
+
+    /// Creates the precomputations from the tables of `num_points` points, stored with the
+    /// in-memory representation of `G` at byte `offset` of the memory mapped file. The tables
+    /// are read from the mapping when they are used, they are not copied.
+    ///
+    /// # Safety
+    ///
+    /// The range must be aligned for `G` and every point in it must be a valid value of `G`,
+    /// e.g. written from the memory of a `[G]` on the same platform.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn from_mapped(
+        window_size: usize,
+        num_points: usize,
+        mmap: Arc<Mmap>,
+        offset: usize,
+    ) -> Self {
+        let table_entries = (1 << window_size) - 1;
+        let len = num_points * table_entries;
+        assert!(offset + len * std::mem::size_of::<G>() <= mmap.len());
+        assert_eq!(
+            (mmap.as_ptr() as usize + offset) % std::mem::align_of::<G>(),
+            0
+        );
+        MultiscalarPrecompOwned {
+            num_points,
+            window_size,
+            window_mask: (1 << window_size) - 1,
+            table_entries,
+            tables: Tables::Mapped { mmap, offset, len },
+        }
+    }
+
+    /// Returns the `idx`-th point the precomputations were created for.
+    pub fn point(&self, idx: usize) -> G {
+        self.tables.as_slice()[idx * self.table_entries]
+    }
+
+    /// Returns the number of points the precomputations were created for.
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
 }
 
 impl<G: PrimeCurveAffine> PartialEq for MultiscalarPrecompOwned<G> {
@@ -52,9 +179,9 @@ impl<G: PrimeCurveAffine> PartialEq for MultiscalarPrecompOwned<G> {
             && self.window_mask == other.window_mask
             && self.table_entries == other.table_entries
             && self
-                .tables
-                .par_iter()
-                .zip(other.tables.par_iter())
+                .tables()
+                .par_chunks(self.table_entries)
+                .zip(other.tables().par_chunks(other.table_entries))
                 .all(|(a, b)| a == b)
     }
 }
@@ -68,8 +195,12 @@ impl<G: PrimeCurveAffine> MultiscalarPrecomp<G> for MultiscalarPrecompOwned<G> {
         self.window_mask
     }
 
-    fn tables(&self) -> &[Vec<G>] {
-        &self.tables
+    fn table_entries(&self) -> usize {
+        self.table_entries
+    }
+
+    fn tables(&self) -> &[G] {
+        self.tables.as_slice()
     }
 
     fn at_point(&self, idx: usize) -> MultiscalarPrecompRef<'_, G> {
@@ -78,7 +209,7 @@ impl<G: PrimeCurveAffine> MultiscalarPrecomp<G> for MultiscalarPrecompOwned<G> {
             window_size: self.window_size,
             window_mask: self.window_mask,
             table_entries: self.table_entries,
-            tables: &self.tables[idx..],
+            tables: &self.tables()[idx * self.table_entries..],
         }
     }
 }
@@ -90,7 +221,7 @@ pub struct MultiscalarPrecompRef<'a, G: PrimeCurveAffine> {
     window_size: usize,
     window_mask: u64,
     table_entries: usize,
-    tables: &'a [Vec<G>],
+    tables: &'a [G],
 }
 
 impl<G: PrimeCurveAffine> MultiscalarPrecomp<G> for MultiscalarPrecompRef<'_, G> {
@@ -102,7 +233,11 @@ impl<G: PrimeCurveAffine> MultiscalarPrecomp<G> for MultiscalarPrecompRef<'_, G>
         self.window_mask
     }
 
-    fn tables(&self) -> &[Vec<G>] {
+    fn table_entries(&self) -> usize {
+        self.table_entries
+    }
+
+    fn tables(&self) -> &[G] {
         self.tables
     }
 
@@ -112,7 +247,7 @@ impl<G: PrimeCurveAffine> MultiscalarPrecomp<G> for MultiscalarPrecompRef<'_, G>
             window_size: self.window_size,
             window_mask: self.window_mask,
             table_entries: self.table_entries,
-            tables: &self.tables[idx..],
+            tables: &self.tables[idx * self.table_entries..],
         }
     }
 }
@@ -125,29 +260,27 @@ pub fn precompute_fixed_window<G: PrimeCurveAffine>(
     let table_entries = (1 << window_size) - 1;
     let num_points = points.len();
 
-    let tables = points
-        .into_par_iter()
-        .map(|point| {
-            let mut table = Vec::with_capacity(table_entries);
-            table.push(*point);
+    let mut tables = vec![G::identity(); num_points * table_entries];
+    tables
+        .par_chunks_mut(table_entries)
+        .zip(points.par_iter())
+        .for_each(|(table, point)| {
+            table[0] = *point;
 
             let mut cur_precomp_point = point.to_curve();
 
-            for _ in 1..table_entries {
+            for entry in table.iter_mut().skip(1) {
                 cur_precomp_point.add_assign(point);
-                table.push(cur_precomp_point.to_affine());
+                *entry = cur_precomp_point.to_affine();
             }
-
-            table
-        })
-        .collect();
+        });
 
     MultiscalarPrecompOwned {
         num_points,
         window_size,
         window_mask: (1 << window_size) - 1,
         table_entries,
-        tables,
+        tables: Tables::Owned(tables),
     }
 }
 
@@ -185,8 +318,8 @@ pub fn multiscalar<G: PrimeCurveAffine>(
             result = result.double();
         }
         let mut prev_idx = 0;
-        let mut prev_table: &Vec<G> = &precomp_table.tables()[0];
-        let mut table: &Vec<G> = &precomp_table.tables()[0];
+        let mut prev_table: &[G] = precomp_table.table(0);
+        let mut table: &[G] = precomp_table.table(0);
 
         for (m, point) in k.iter().enumerate() {
             let point_limb =
@@ -194,7 +327,7 @@ pub fn multiscalar<G: PrimeCurveAffine>(
             idx = point_limb >> (window_in_limb * precomp_table.window_size())
                 & precomp_table.window_mask();
             if idx > 0 {
-                table = &precomp_table.tables()[m];
+                table = precomp_table.table(m);
                 prefetch(&table[idx as usize - 1]);
             }
             if prev_idx > 0 && m > 0 {
@@ -213,7 +346,9 @@ pub fn multiscalar<G: PrimeCurveAffine>(
     result
 }
 
-/// Perform a threaded multiscalar multiplication and accumulation.
+/// Perform a threaded multiscalar multiplication and accumulation. On groups with an endomorphism,
+/// like BLS12-381 G1, full length scalars are split with the GLV decomposition, like in
+/// `par_multiscalar_glv`, if there are few enough of them for it to be faster.
 pub fn par_multiscalar<F, G: PrimeCurveAffine>(
     points: &ScalarList<'_, G, F>,
     precomp_table: &dyn MultiscalarPrecomp<G>,
@@ -221,6 +356,63 @@ pub fn par_multiscalar<F, G: PrimeCurveAffine>(
 ) -> G::Curve
 where
     F: Fn(usize) -> <G::Scalar as PrimeField>::Repr + Sync,
+{
+    match Glv::<G>::of() {
+        Some(glv) if nbits > GLV_BITS && points.len() <= GLV_MAX_POINTS => {
+            par_multiscalar_glv(&glv, points, precomp_table)
+        }
+        _ => par_multiscalar_generic(points, precomp_table, nbits),
+    }
+}
+
+/// Perform a threaded multiscalar multiplication and accumulation, for any group and without the
+/// GLV decomposition.
+pub fn par_multiscalar_generic<F, G: PrimeCurveAffine>(
+    points: &ScalarList<'_, G, F>,
+    precomp_table: &dyn MultiscalarPrecomp<G>,
+    nbits: usize,
+) -> G::Curve
+where
+    F: Fn(usize) -> <G::Scalar as PrimeField>::Repr + Sync,
+{
+    par_chunks(points, |start_idx, scalars| {
+        let subset = precomp_table.at_point(start_idx);
+        multiscalar(scalars, &subset, nbits)
+    })
+}
+
+/// Perform a threaded multiscalar multiplication and accumulation on a group with an endomorphism,
+/// like BLS12-381 G1, with the GLV decomposition of the scalars: the scalars are split into two
+/// halves of 128 bits, one for the point P and one for $\phi(P)$. The precomputed table of P is used
+/// for both, the entries for $\phi(P)$ are derived from it on the fly. This halves the number of
+/// doublings compared to `par_multiscalar_generic`.
+pub fn par_multiscalar_glv<F, G: PrimeCurveAffine>(
+    glv: &Glv<G>,
+    points: &ScalarList<'_, G, F>,
+    precomp_table: &dyn MultiscalarPrecomp<G>,
+) -> G::Curve
+where
+    F: Fn(usize) -> <G::Scalar as PrimeField>::Repr + Sync,
+{
+    let tables = precomp_table.tables();
+    let table_entries = precomp_table.table_entries();
+    let window_size = precomp_table.window_size();
+    par_chunks(points, |start_idx, scalars| {
+        let tables = tables[start_idx * table_entries..].chunks(table_entries);
+        multiscalar_glv(glv, scalars, tables, window_size)
+    })
+}
+
+/// Splits the multiscalar multiplication into chunks of points, computed in parallel by
+/// `multiscalar` with the index of the first point of the chunk and its scalars.
+fn par_chunks<F, G, C>(
+    points: &ScalarList<'_, G, F>,
+    multiscalar: impl Fn(usize, &[<G::Scalar as PrimeField>::Repr]) -> C + Sync + Send,
+) -> C
+where
+    F: Fn(usize) -> <G::Scalar as PrimeField>::Repr + Sync,
+    G: PrimeCurveAffine,
+    C: Group,
 {
     let num_points = points.len();
 
@@ -256,25 +448,59 @@ where
                 end_idx = num_points;
             }
 
-            let subset = precomp_table.at_point(start_idx);
             let scalars = match points {
                 ScalarList::Slice(s) => &s[start_idx..end_idx],
                 ScalarList::Getter(ref getter, _) => {
                     for i in start_idx..end_idx {
                         scalar_storage[i - start_idx] = getter(i);
                     }
-                    &scalar_storage
+                    &scalar_storage[..end_idx - start_idx]
                 }
             };
 
-            multiscalar(scalars, &subset, nbits)
+            multiscalar(start_idx, scalars)
         }) // Accumulate results
-        .reduce(G::Curve::identity, |mut acc, part| {
+        .reduce(C::identity, |mut acc, part| {
             acc.add_assign(&part);
             acc
         })
 }
 
+/// Multipoint scalar multiplication on a group with an endomorphism with the GLV decomposition of
+/// the scalars `k`, using the precomputed `tables` of the points.
+fn multiscalar_glv<'a, G: PrimeCurveAffine, R: AsRef<[u8]>>(
+    glv: &Glv<G>,
+    k: &[R],
+    tables: impl Iterator<Item = &'a [G]> + Clone,
+    window_size: usize,
+) -> G::Curve {
+    let k: Vec<(u128, u128)> = k.iter().map(|k| (glv.decompose)(k.as_ref())).collect();
+    let window_mask = (1u128 << window_size) - 1;
+    let num_windows = (GLV_BITS + window_size - 1) / window_size;
+
+    let mut result = G::Curve::identity();
+    for i in (0..num_windows).rev() {
+        for _ in 0..window_size {
+            result = result.double();
+        }
+
+        let shift = i * window_size;
+        for ((k1, k2), table) in k.iter().zip(tables.clone()) {
+            let idx = (k1 >> shift) & window_mask;
+            if idx > 0 {
+                result.add_assign(&table[idx as usize - 1]);
+            }
+            // $\phi(j P) = j \phi(P)$, the table of $\phi(P)$ is the image of the one of P.
+            let idx = (k2 >> shift) & window_mask;
+            if idx > 0 {
+                result.add_assign(&(glv.endomorphism)(&table[idx as usize - 1]));
+            }
+        }
+    }
+
+    result
+}
+
 #[cfg(target_arch = "x86_64")]
 fn prefetch<T>(p: *const T) {
     unsafe {
@@ -378,4 +604,42 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_multiscalar_glv() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let glv = Glv::<G1Affine>::of().unwrap();
+
+        for (num_inputs, window_size) in &[(1, 8), (8, 4), (20, 3), (100, 8), (1030, 8)] {
+            let points: Vec<G1Affine> = (0..*num_inputs)
+                .map(|_| G1Projective::random(&mut rng).to_affine())
+                .collect();
+
+            let mut scalars: Vec<<Fr as PrimeField>::Repr> = (0..*num_inputs)
+                .map(|_| Fr::random(&mut rng).to_repr())
+                .collect();
+            scalars[0] = (-Fr::ONE).to_repr();
+
+            let table = precompute_fixed_window::<G1Affine>(&points, *window_size);
+
+            let naive_result = multiscalar_naive(&points, &scalars);
+            let glv_result = par_multiscalar_glv::<&Getter<G1Affine>, _>(
+                &glv,
+                &ScalarList::Slice(&scalars),
+                &table,
+            );
+            assert_eq!(naive_result, glv_result);
+
+            let getter = |i: usize| scalars[i];
+            let result = par_multiscalar::<_, G1Affine>(
+                &ScalarList::Getter(getter, *num_inputs),
+                &table,
+                std::mem::size_of::<<Fr as PrimeField>::Repr>() * 8,
+            );
+            assert_eq!(naive_result, result);
+        }
+    }
 }
@@ -233,7 +233,7 @@ impl<E: Engine> Proof<E> {
 // As most tests are about the parameter serialization, hence it doesn't matter for SupraSeal, as
 // there the parameter handling is in their C++ code base.
 #[cfg(not(feature = "cuda-supraseal"))]
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test_with_bls12_381 {
     use std::ops::MulAssign;
 
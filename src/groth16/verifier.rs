@@ -31,6 +31,7 @@ where
         alpha_g1: vk.alpha_g1.to_curve(),
         beta_g2: vk.beta_g2.into(),
         ic_projective: vk.ic.par_iter().map(|i| i.to_curve()).collect(),
+        digest: vk.digest(),
     }
 }
 
@@ -140,24 +141,7 @@ where
     let mut accum_y = E::Fr::ZERO;
 
     for _ in 0..proof_num {
-        use rand::Rng;
-
-        let t: u128 = rng.gen();
-
-        let mut repr = E::Fr::ZERO.to_repr();
-        let mut repr_u64s = le_bytes_to_u64s(repr.as_ref());
-        assert!(repr_u64s.len() > 1);
-
-        repr_u64s[0] = (t & (-1i64 as u128) >> 64) as u64;
-        repr_u64s[1] = (t >> 64) as u64;
-
-        for (i, limb) in repr_u64s.iter().enumerate() {
-            let start = i * 8;
-            let stop = start + 8;
-            repr.as_mut()[start..stop].copy_from_slice(&limb.to_le_bytes());
-        }
-
-        let fr = E::Fr::from_repr(repr).unwrap();
+        let fr = random_batch_scalar::<E, _>(rng);
         let repr = fr.to_repr();
 
         // calculate sum
@@ -274,3 +258,223 @@ where
     let actual = ml_all.final_exponentiation();
     Ok(actual == y)
 }
+
+/// Randomized batch verification of proofs created for different circuits.
+///
+/// Every entry is a proof together with its public inputs and the prepared verifying key of the
+/// circuit it belongs to. All proofs are combined with random coefficients into a single
+/// multi-Miller loop and a single final exponentiation. The `gamma`, `delta` and `alpha * beta`
+/// terms of proofs sharing a verifying key are accumulated before the pairing, hence the check
+/// costs one Miller loop per proof plus two per distinct key.
+///
+/// Entries are grouped by the digest of their verifying key, see
+/// `PreparedVerifyingKey::digest`.
+#[allow(clippy::type_complexity)]
+pub fn verify_proofs_batch_multi_vk<E, R>(
+    items: &[(&PreparedVerifyingKey<E>, &Proof<E>, &[E::Fr])],
+    rng: &mut R,
+) -> Result<bool, SynthesisError>
+where
+    E: MultiMillerLoop,
+    <E::Fr as PrimeField>::Repr: Sync + Copy,
+    R: rand::RngCore,
+{
+    for (pvk, _, public_inputs) in items {
+        if (public_inputs.len() + 1) != pvk.ic.len() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+    }
+
+    match items.len() {
+        0 => return Ok(true),
+        1 => return verify_proof(items[0].0, items[0].1, items[0].2),
+        _ => {}
+    }
+
+    // Choose random coefficients for combining the proofs.
+    let rand_z: Vec<E::Fr> = (0..items.len())
+        .map(|_| random_batch_scalar::<E, _>(rng))
+        .collect();
+
+    // Group the proofs by verifying key.
+    let mut groups: Vec<(&PreparedVerifyingKey<E>, Vec<usize>)> = Vec::new();
+    for (i, (pvk, _, _)) in items.iter().enumerate() {
+        match groups.iter_mut().find(|(g, _)| g.digest() == pvk.digest()) {
+            Some((_, indices)) => indices.push(i),
+            None => groups.push((pvk, vec![i])),
+        }
+    }
+
+    let rand_z = &rand_z;
+
+    // Per key: (\sum Accum_Gamma, Accum_Delta, Accum_Y)
+    let accumulators: Vec<(E::G1Affine, E::G1Affine, E::Fr)> = groups
+        .par_iter()
+        .map(|(pvk, indices)| {
+            let num_inputs = pvk.ic.len() - 1;
+
+            let mut accum_y = E::Fr::ZERO;
+            for &j in indices {
+                accum_y.add_assign(&rand_z[j]);
+            }
+
+            // \sum(z_j * aj,i), with the first entry belonging to ic[0]
+            let mut scalars = vec![E::Fr::ZERO; num_inputs + 1];
+            scalars[0] = accum_y;
+            for &j in indices {
+                for (scalar, input) in scalars.iter_mut().skip(1).zip(items[j].2.iter()) {
+                    let mut tmp = rand_z[j];
+                    tmp.mul_assign(input);
+                    scalar.add_assign(&tmp);
+                }
+            }
+            let scalars_repr: Vec<_> = scalars.iter().map(PrimeField::to_repr).collect();
+
+            // \sum Accum_Gamma
+            let acc_g_psi = multiscalar::par_multiscalar::<&multiscalar::Getter<E::G1Affine>, _>(
+                &multiscalar::ScalarList::Slice(&scalars_repr),
+                &pvk.multiscalar,
+                256,
+            );
+
+            // Accum_Delta
+            let points: Vec<_> = indices.iter().map(|&j| items[j].1.c).collect();
+            let rand_z_repr: Vec<_> = indices.iter().map(|&j| rand_z[j].to_repr()).collect();
+            let acc_d: E::G1 = {
+                let pre = multiscalar::precompute_fixed_window::<E::G1Affine>(&points, 1);
+                multiscalar::multiscalar::<E::G1Affine>(
+                    &rand_z_repr,
+                    &pre,
+                    std::mem::size_of::<<E::Fr as PrimeField>::Repr>() * 8,
+                )
+            };
+
+            (acc_g_psi.to_affine(), acc_d.to_affine(), accum_y)
+        })
+        .collect();
+
+    // ([z_j] pi_j,A, -pi_j,B)
+    let ab_terms: Vec<(E::G1Affine, E::G2Prepared)> = items
+        .par_iter()
+        .zip(rand_z.par_iter())
+        .map(|((_, proof, _), rand)| {
+            let mul_a = proof.a.mul(*rand).to_affine();
+            let neg_b = -proof.b;
+            (mul_a, neg_b.into())
+        })
+        .collect();
+
+    // One multi-Miller loop over all terms, split into chunks to make use of all threads.
+    let mut terms: Vec<(&E::G1Affine, &E::G2Prepared)> =
+        ab_terms.iter().map(|(a, b)| (a, b)).collect();
+    for ((pvk, _), (acc_g, acc_d, _)) in groups.iter().zip(accumulators.iter()) {
+        terms.push((acc_g, &pvk.gamma_g2));
+        terms.push((acc_d, &pvk.delta_g2));
+    }
+    let chunk_size = (terms.len() / rayon::current_num_threads()).max(1);
+    let ml_all = terms
+        .par_chunks(chunk_size)
+        .map(|chunk| E::multi_miller_loop(chunk))
+        .reduce(<E as MultiMillerLoop>::Result::default, |acc, ml| acc + ml);
+
+    // \prod_k (alpha_k * beta_k)^-Accum_Y_k
+    let y = groups.iter().zip(accumulators.iter()).fold(
+        <E as Engine>::Gt::identity(),
+        |acc, ((pvk, _), (_, _, accum_y))| acc + pvk.alpha_g1_beta_g2 * (-*accum_y),
+    );
+
+    let actual = ml_all.final_exponentiation();
+    Ok(actual == y)
+}
+
+/// Sample a random 128-bit scalar used as coefficient in randomized batch verification.
+fn random_batch_scalar<E, R>(rng: &mut R) -> E::Fr
+where
+    E: Engine,
+    R: rand::RngCore,
+{
+    use rand::Rng;
+
+    let t: u128 = rng.gen();
+
+    let mut repr = E::Fr::ZERO.to_repr();
+    let mut repr_u64s = le_bytes_to_u64s(repr.as_ref());
+    assert!(repr_u64s.len() > 1);
+
+    repr_u64s[0] = (t & (-1i64 as u128) >> 64) as u64;
+    repr_u64s[1] = (t >> 64) as u64;
+
+    for (i, limb) in repr_u64s.iter().enumerate() {
+        let start = i * 8;
+        let stop = start + 8;
+        repr.as_mut()[start..stop].copy_from_slice(&limb.to_le_bytes());
+    }
+
+    E::Fr::from_repr(repr).unwrap()
+}
+
+/// Randomized batch verification that identifies the invalid proofs.
+///
+/// Returns the indices of all proofs that fail verification, an empty list means that all proofs
+/// are valid. If the batch check over all proofs fails, the set is recursively bisected and the
+/// randomized batch check is repeated on the halves. When the left half of a failing set is
+/// valid, the right half is known to contain an invalid proof and is split without checking it
+/// first. This results in roughly `O(k log n)` batch checks for `k` invalid proofs.
+pub fn verify_proofs_batch_with_culprits<E, R>(
+    pvk: &PreparedVerifyingKey<E>,
+    rng: &mut R,
+    proofs: &[&Proof<E>],
+    public_inputs: &[Vec<E::Fr>],
+) -> Result<Vec<usize>, SynthesisError>
+where
+    E: MultiMillerLoop,
+    <E::Fr as PrimeField>::Repr: Sync + Copy,
+    R: rand::RngCore,
+{
+    debug_assert_eq!(proofs.len(), public_inputs.len());
+
+    let mut culprits = Vec::new();
+    if proofs.is_empty() || verify_proofs_batch(pvk, rng, proofs, public_inputs)? {
+        return Ok(culprits);
+    }
+
+    find_culprits(pvk, rng, proofs, public_inputs, 0, &mut culprits)?;
+    Ok(culprits)
+}
+
+/// Bisect a set of proofs that is known to fail the batch check. `offset` is the index of the
+/// first proof within the original batch.
+fn find_culprits<E, R>(
+    pvk: &PreparedVerifyingKey<E>,
+    rng: &mut R,
+    proofs: &[&Proof<E>],
+    public_inputs: &[Vec<E::Fr>],
+    offset: usize,
+    culprits: &mut Vec<usize>,
+) -> Result<(), SynthesisError>
+where
+    E: MultiMillerLoop,
+    <E::Fr as PrimeField>::Repr: Sync + Copy,
+    R: rand::RngCore,
+{
+    if proofs.len() == 1 {
+        culprits.push(offset);
+        return Ok(());
+    }
+
+    let mid = proofs.len() / 2;
+    let (left_proofs, right_proofs) = proofs.split_at(mid);
+    let (left_inputs, right_inputs) = public_inputs.split_at(mid);
+
+    let left_valid = verify_proofs_batch(pvk, rng, left_proofs, left_inputs)?;
+    if !left_valid {
+        find_culprits(pvk, rng, left_proofs, left_inputs, offset, culprits)?;
+    }
+
+    // If the left half is valid, the right half must contain the invalid proof, hence it is not
+    // checked as a whole again.
+    if !left_valid && verify_proofs_batch(pvk, rng, right_proofs, right_inputs)? {
+        return Ok(());
+    }
+    find_culprits(pvk, rng, right_proofs, right_inputs, offset + mid, culprits)
+}
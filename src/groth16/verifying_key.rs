@@ -4,6 +4,7 @@ use pairing::{Engine, MultiMillerLoop};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 #[cfg(not(target_arch = "wasm32"))]
 use memmap2::Mmap;
+use sha2::{Digest, Sha256};
 use std::io::{self, Read, Write};
 #[cfg(not(target_arch = "wasm32"))]
 use std::mem;
@@ -56,6 +57,13 @@ fn read_uncompressed_point<C: UncompressedEncoding>(repr: &C::Uncompressed) -> i
 }
 
 impl<E: Engine + MultiMillerLoop> VerifyingKey<E> {
+    /// Returns the SHA-256 hash of the serialized verifying key, see `write`.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut v = Vec::new();
+        self.write(&mut v).expect("failed to compute digest");
+        Sha256::digest(&v).into()
+    }
+
     pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         writer.write_all(self.alpha_g1.to_uncompressed().as_ref())?;
         writer.write_all(self.beta_g1.to_uncompressed().as_ref())?;
@@ -220,4 +228,17 @@ where
     pub(crate) alpha_g1: E::G1,
     pub(crate) beta_g2: <E as MultiMillerLoop>::G2Prepared,
     pub(crate) ic_projective: Vec<E::G1>,
+
+    /// Digest of the `VerifyingKey`, see `VerifyingKey::digest`.
+    pub(crate) digest: [u8; 32],
+}
+
+impl<E> PreparedVerifyingKey<E>
+where
+    E: MultiMillerLoop,
+{
+    /// Returns the digest of the verifying key this key was prepared from.
+    pub fn digest(&self) -> [u8; 32] {
+        self.digest
+    }
 }
@@ -15,7 +15,7 @@
 //! - Expose `hash` as a public input using multiscalar packing.
 //!
 //! ```no_run
-//! # #[cfg(not(feature = "cuda-supraseal"))]
+//! # #[cfg(all(feature = "prover", not(feature = "cuda-supraseal")))]
 //! # {
 //! use bellperson::{
 //!     gadgets::{
@@ -144,14 +144,36 @@
 #[macro_use]
 extern crate hex_literal;
 
+#[cfg(all(target_arch = "wasm32", feature = "prover"))]
+compile_error!("the `prover` feature is not supported on wasm32");
+
+#[cfg(feature = "prover")]
 pub mod domain;
 pub mod gadgets;
+pub(crate) mod glv;
+#[cfg(feature = "prover")]
 pub mod gpu;
 #[cfg(feature = "groth16")]
 pub mod groth16;
+#[cfg(feature = "prover")]
 pub mod multiexp;
 pub mod util_cs;
 
+/// Internals used by the benchmarks, not part of the public API.
+#[cfg(feature = "__private_bench")]
+#[doc(hidden)]
+pub mod __private_bench {
+    pub use crate::glv::Glv;
+    #[cfg(feature = "groth16")]
+    pub use crate::groth16::multiscalar::{
+        par_multiscalar_generic, par_multiscalar_glv, precompute_fixed_window, Getter, ScalarList,
+        WINDOW_SIZE,
+    };
+    #[cfg(feature = "prover")]
+    pub use crate::multiexp::multiexp_cpu_glv;
+}
+
+#[cfg(feature = "prover")]
 pub(crate) mod lc;
 pub use bellpepper_core::{Circuit, ConstraintSystem, Namespace, SynthesisError};
 pub use bellpepper_core::{Index, LinearCombination, Variable};
@@ -3,12 +3,18 @@ use std::sync::Arc;
 use ec_gpu_gen::multiexp_cpu::{multiexp_cpu, QueryDensity, SourceBuilder};
 use ec_gpu_gen::threadpool::{Waiter, Worker};
 use ec_gpu_gen::EcError;
-use ff::PrimeField;
-use group::prime::PrimeCurveAffine;
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Group};
+use rayon::prelude::*;
 
+use crate::glv::{Glv, GLV_BITS};
 use crate::gpu;
 pub use ec_gpu_gen::multiexp_cpu::DensityTracker;
 
+/// Below this number of exponents, the CPU multi-exponentiation doesn't use the GLV
+/// decomposition, as the cost of splitting the exponents isn't made up for.
+const GLV_MIN_EXPONENTS: usize = 32;
+
 /// Perform multi-exponentiation. The caller is responsible for ensuring the
 /// query size is the same as the number of exponents.
 #[cfg(any(feature = "cuda", feature = "opencl"))]
@@ -37,7 +43,7 @@ where
     }
 
     // Fallback to the CPU in case the GPU run failed.
-    let result_cpu = multiexp_cpu(pool, bases, density_map, exponents);
+    let result_cpu = cpu_multiexp(pool, bases, density_map, exponents);
 
     // Do not give the control back to the caller till the multiexp is done. Once done the GPU
     // might again be free, so we can run subsequent calls on the GPU instead of the CPU again.
@@ -60,5 +66,239 @@ where
     G: PrimeCurveAffine,
     S: SourceBuilder<G>,
 {
-    multiexp_cpu(pool, bases, density_map, exponents)
+    cpu_multiexp(pool, bases, density_map, exponents)
+}
+
+/// Perform multi-exponentiation on the CPU. On groups with an endomorphism, like BLS12-381 G1, the
+/// exponents are split with the GLV decomposition, see `multiexp_cpu_glv`.
+fn cpu_multiexp<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
+) -> Waiter<Result<<G as PrimeCurveAffine>::Curve, EcError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: PrimeCurveAffine,
+    S: SourceBuilder<G>,
+{
+    let glv = match Glv::<G>::of() {
+        Some(glv) if exponents.len() >= GLV_MIN_EXPONENTS => glv,
+        _ => return multiexp_cpu(pool, bases, density_map, exponents),
+    };
+
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        // If the density map has a known query size, it should not be
+        // inconsistent with the number of exponents.
+        assert!(query_size == exponents.len());
+    }
+
+    pool.compute(move || {
+        let exps = density_map.as_ref().generate_exps::<G::Scalar>(exponents);
+        let (bases, skip) = bases.get();
+        let bases = bases
+            .get(skip..skip + exps.len())
+            .ok_or(EcError::Simple("Expected more bases from source."))?;
+
+        // Like `multiexp_cpu`, reject the identity as the base of a non-zero exponent.
+        let zero = G::Scalar::ZERO.to_repr();
+        if bases
+            .iter()
+            .zip(exps.iter())
+            .any(|(base, exp)| bool::from(base.is_identity()) && exp.as_ref() != zero.as_ref())
+        {
+            return Err(EcError::Simple(
+                "Encountered an identity element in the CRS.",
+            ));
+        }
+
+        Ok(multiexp_cpu_glv(&glv, bases, &exps))
+    })
+}
+
+/// Perform multi-exponentiation on a group with an endomorphism, like BLS12-381 G1, with the bucket
+/// method, on twice as many bases with exponents of half the length: each exponent $k$ is split
+/// into $k_1 + k_2 \lambda$ with the GLV decomposition, for the bases $P$ and
+/// $\phi(P) = \lambda P$ (see the `glv` module).
+pub fn multiexp_cpu_glv<G, R>(glv: &Glv<G>, bases: &[G], exponents: &[R]) -> G::Curve
+where
+    G: PrimeCurveAffine,
+    R: AsRef<[u8]> + Sync,
+{
+    let num_bases = exponents.len();
+    assert!(bases.len() >= num_bases, "not enough bases");
+    if num_bases == 0 {
+        return G::Curve::identity();
+    }
+
+    let exponents: Vec<(u128, u128)> = exponents
+        .par_iter()
+        .map(|exp| (glv.decompose)(exp.as_ref()))
+        .collect();
+    let bases = &bases[..num_bases];
+    let endomorphisms: Vec<G> = bases.par_iter().map(glv.endomorphism).collect();
+
+    // The same window size as `multiexp_cpu`, for the doubled number of bases.
+    let c = if 2 * num_bases < 32 {
+        3
+    } else {
+        (f64::from((2 * num_bases) as u32)).ln().ceil() as usize
+    };
+    let num_windows = (GLV_BITS + c - 1) / c;
+
+    // There are half as many windows as for full length exponents, the bases are split as well to
+    // keep all threads busy.
+    let num_chunks =
+        ((rayon::current_num_threads() + num_windows - 1) / num_windows).clamp(1, num_bases);
+    let chunk_size = (num_bases + num_chunks - 1) / num_chunks;
+
+    let window_sums: Vec<G::Curve> = (0..num_windows)
+        .into_par_iter()
+        .map(|window| {
+            let skip = window * c;
+            (0..num_bases)
+                .into_par_iter()
+                .step_by(chunk_size)
+                .map(|start| {
+                    let end = (start + chunk_size).min(num_bases);
+                    bucket_sum(
+                        &bases[start..end],
+                        &endomorphisms[start..end],
+                        &exponents[start..end],
+                        skip,
+                        c,
+                    )
+                })
+                .reduce(G::Curve::identity, |acc, part| acc + part)
+        })
+        .collect();
+
+    window_sums
+        .into_iter()
+        .rev()
+        .fold(G::Curve::identity(), |mut acc, part| {
+            for _ in 0..c {
+                acc = acc.double();
+            }
+            acc + part
+        })
+}
+
+/// Returns the sum of the bases weighted by the `c` bits of their exponents starting at `skip`.
+fn bucket_sum<G: PrimeCurveAffine>(
+    bases: &[G],
+    endomorphisms: &[G],
+    exponents: &[(u128, u128)],
+    skip: usize,
+    c: usize,
+) -> G::Curve {
+    let mask = (1u128 << c) - 1;
+    let mut buckets = vec![G::Curve::identity(); (1 << c) - 1];
+    for ((base, endomorphism), (k1, k2)) in bases.iter().zip(endomorphisms).zip(exponents) {
+        let digit = ((k1 >> skip) & mask) as usize;
+        if digit != 0 {
+            buckets[digit - 1] += base;
+        }
+        let digit = ((k2 >> skip) & mask) as usize;
+        if digit != 0 {
+            buckets[digit - 1] += endomorphism;
+        }
+    }
+
+    // Summation by parts
+    // e.g. 3a + 2b + 1c = a +
+    //                    (a) + b +
+    //                    ((a) + b) + c
+    let mut acc = G::Curve::identity();
+    let mut running_sum = G::Curve::identity();
+    for bucket in buckets.into_iter().rev() {
+        running_sum += bucket;
+        acc += running_sum;
+    }
+    acc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use blstrs::{G1Affine, G1Projective, G2Affine, G2Projective, Scalar as Fr};
+    use ec_gpu_gen::multiexp_cpu::FullDensity;
+    use group::Curve;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_multiexp_cpu_glv() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let pool = Worker::new();
+        let glv = Glv::<G1Affine>::of().unwrap();
+        for num_bases in [0, 1, 31, 32, 1000] {
+            let bases: Vec<G1Affine> = (0..num_bases + 2)
+                .map(|_| G1Projective::random(&mut rng).to_affine())
+                .collect();
+            let scalars: Vec<Fr> = (0..num_bases).map(|_| Fr::random(&mut rng)).collect();
+            let exponents: Vec<_> = scalars.iter().map(|s| s.to_repr()).collect();
+            let expected: G1Projective = bases[2..]
+                .iter()
+                .zip(scalars.iter())
+                .map(|(base, scalar)| base * scalar)
+                .sum();
+
+            assert_eq!(multiexp_cpu_glv(&glv, &bases[2..], &exponents), expected);
+
+            let bases = Arc::new(bases);
+            let result = cpu_multiexp(&pool, (bases, 2), FullDensity, Arc::new(exponents));
+            assert_eq!(result.wait().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_cpu_multiexp_density() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let pool = Worker::new();
+        const NUM_EXPONENTS: usize = 100;
+
+        let scalars: Vec<Fr> = (0..NUM_EXPONENTS).map(|_| Fr::random(&mut rng)).collect();
+        let exponents = Arc::new(scalars.iter().map(|s| s.to_repr()).collect::<Vec<_>>());
+        let mut density = DensityTracker::new();
+        for i in 0..NUM_EXPONENTS {
+            density.add_element();
+            if i % 3 != 0 {
+                density.inc(i);
+            }
+        }
+        let density = Arc::new(density);
+
+        let g1: Vec<G1Affine> = (0..density.get_total_density())
+            .map(|_| G1Projective::random(&mut rng).to_affine())
+            .collect();
+        let g2: Vec<G2Affine> = (0..density.get_total_density())
+            .map(|_| G2Projective::random(&mut rng).to_affine())
+            .collect();
+
+        let expected = multiexp_cpu(
+            &pool,
+            (Arc::new(g1.clone()), 0),
+            density.clone(),
+            exponents.clone(),
+        );
+        let result = cpu_multiexp(&pool, (Arc::new(g1), 0), density.clone(), exponents.clone());
+        assert_eq!(result.wait().unwrap(), expected.wait().unwrap());
+
+        let expected = multiexp_cpu(
+            &pool,
+            (Arc::new(g2.clone()), 0),
+            density.clone(),
+            exponents.clone(),
+        );
+        let result = cpu_multiexp(&pool, (Arc::new(g2), 0), density.clone(), exponents.clone());
+        assert_eq!(result.wait().unwrap(), expected.wait().unwrap());
+
+        // Not enough bases
+        let g1 = Arc::new(vec![G1Affine::generator(); 10]);
+        assert!(cpu_multiexp(&pool, (g1, 0), density, exponents)
+            .wait()
+            .is_err());
+    }
 }
@@ -377,7 +377,7 @@ impl ec_gpu::GpuName for Fr {
     }
 }
 
-#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+#[cfg(all(feature = "prover", not(any(feature = "cuda", feature = "opencl"))))]
 impl bellperson::gpu::GpuName for Fr {}
 
 impl MillerLoopResult for Fr {
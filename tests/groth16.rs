@@ -1,6 +1,6 @@
 // All those tests depend on the `DummyEngine`, SupraSeal is only implemented for BLS12, hence the
-// tests are disabled for SupraSeal.
-#![cfg(not(feature = "cuda-supraseal"))]
+// tests are disabled for SupraSeal. They also depend on the prover.
+#![cfg(all(feature = "prover", not(feature = "cuda-supraseal")))]
 
 use ff::{Field, PrimeField};
 use group::{Curve, Group};
@@ -643,6 +643,166 @@ fn test_verify_random_batch() {
     }
 }
 
+#[test]
+fn test_verify_random_batch_multi_vk() {
+    use bellperson::groth16::{
+        create_random_proof, generate_random_parameters, verify_proofs_batch_multi_vk,
+    };
+    use blstrs::{Bls12, Scalar as Fr};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let c = XorDemo::<Fr> {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+
+    // Two independent setups result in two different verifying keys.
+    let params_a = generate_random_parameters::<Bls12, _, _>(c.clone(), &mut rng).unwrap();
+    let params_b = generate_random_parameters::<Bls12, _, _>(c.clone(), &mut rng).unwrap();
+    let pvk_a = prepare_verifying_key(&params_a.vk);
+    let pvk_b = prepare_verifying_key(&params_b.vk);
+
+    let inputs = vec![Fr::ONE];
+    let wrong_inputs = vec![Fr::ZERO];
+
+    for _ in 0..10 {
+        let proof_a1 = create_random_proof(c.clone(), &params_a, &mut rng).unwrap();
+        let proof_a2 = create_random_proof(c.clone(), &params_a, &mut rng).unwrap();
+        let proof_b = create_random_proof(c.clone(), &params_b, &mut rng).unwrap();
+
+        // real proofs
+        assert!(verify_proofs_batch_multi_vk(
+            &[
+                (&pvk_a, &proof_a1, &inputs[..]),
+                (&pvk_b, &proof_b, &inputs[..]),
+                (&pvk_a, &proof_a2, &inputs[..]),
+            ],
+            &mut rng,
+        )
+        .unwrap());
+
+        // mess up the inputs
+        assert!(!verify_proofs_batch_multi_vk(
+            &[
+                (&pvk_a, &proof_a1, &inputs[..]),
+                (&pvk_b, &proof_b, &wrong_inputs[..]),
+                (&pvk_a, &proof_a2, &inputs[..]),
+            ],
+            &mut rng,
+        )
+        .unwrap());
+
+        // verify a proof against the wrong key
+        assert!(!verify_proofs_batch_multi_vk(
+            &[
+                (&pvk_a, &proof_a1, &inputs[..]),
+                (&pvk_a, &proof_b, &inputs[..]),
+                (&pvk_b, &proof_a2, &inputs[..]),
+            ],
+            &mut rng,
+        )
+        .unwrap());
+
+        // mess up the proof a little bit
+        {
+            let mut fake_proof = proof_b.clone();
+            fake_proof.c = fake_proof.c.mul(Fr::random(&mut rng)).to_affine();
+            assert!(!verify_proofs_batch_multi_vk(
+                &[
+                    (&pvk_a, &proof_a1, &inputs[..]),
+                    (&pvk_b, &fake_proof, &inputs[..]),
+                    (&pvk_a, &proof_a2, &inputs[..]),
+                ],
+                &mut rng,
+            )
+            .unwrap());
+        }
+    }
+
+    // Keys are grouped by content, not by reference
+    let pvk_a_copy = prepare_verifying_key(&params_a.vk);
+    let proof_a1 = create_random_proof(c.clone(), &params_a, &mut rng).unwrap();
+    let proof_a2 = create_random_proof(c.clone(), &params_a, &mut rng).unwrap();
+    let proof_b = create_random_proof(c.clone(), &params_b, &mut rng).unwrap();
+    assert!(verify_proofs_batch_multi_vk(
+        &[
+            (&pvk_a, &proof_a1, &inputs[..]),
+            (&pvk_b, &proof_b, &inputs[..]),
+            (&pvk_a_copy, &proof_a2, &inputs[..]),
+        ],
+        &mut rng,
+    )
+    .unwrap());
+
+    // wrong number of public inputs
+    let proof = create_random_proof(c, &params_a, &mut rng).unwrap();
+    assert!(verify_proofs_batch_multi_vk(
+        &[
+            (&pvk_a, &proof, &inputs[..]),
+            (&pvk_b, &proof, &[Fr::ONE, Fr::ONE][..]),
+        ],
+        &mut rng,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_verify_random_batch_with_culprits() {
+    use bellperson::groth16::{
+        create_random_proof_batch, generate_random_parameters, verify_proofs_batch_with_culprits,
+    };
+    use blstrs::{Bls12, Scalar as Fr};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let c = XorDemo::<Fr> {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+
+    let params = generate_random_parameters::<Bls12, _, _>(c.clone(), &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let num_proofs = 11;
+    let proofs = create_random_proof_batch(vec![c; num_proofs], &params, &mut rng).unwrap();
+    let inputs = vec![vec![Fr::ONE]; num_proofs];
+
+    // all proofs are valid
+    let proof_refs: Vec<_> = proofs.iter().collect();
+    assert!(
+        verify_proofs_batch_with_culprits(&pvk, &mut rng, &proof_refs, &inputs)
+            .unwrap()
+            .is_empty()
+    );
+
+    for bad in [vec![0], vec![10], vec![1, 5, 6], vec![2, 3, 4, 7, 8, 9]] {
+        let mut fake_proofs = proofs.clone();
+        let mut fake_inputs = inputs.clone();
+        for (i, &idx) in bad.iter().enumerate() {
+            // alternate between messing up the proof and the inputs
+            if i % 2 == 0 {
+                fake_proofs[idx].c = fake_proofs[idx].c.mul(Fr::random(&mut rng)).to_affine();
+            } else {
+                fake_inputs[idx] = vec![Fr::random(&mut rng)];
+            }
+        }
+
+        let proof_refs: Vec<_> = fake_proofs.iter().collect();
+        let culprits =
+            verify_proofs_batch_with_culprits(&pvk, &mut rng, &proof_refs, &fake_inputs).unwrap();
+        assert_eq!(culprits, bad);
+    }
+}
+
 struct MultWithZeroCoeffs<F> {
     a: Option<F>,
     b: Option<F>,
@@ -1,14 +1,27 @@
+// The tests depend on the prover.
+#![cfg(feature = "prover")]
+
 mod util;
 
 use bellpepper_core::num::AllocatedNum;
 use bellpepper_core::{Circuit, ConstraintSystem, SynthesisError};
 use bellperson::groth16::{
     aggregate::{
-        aggregate_proofs, setup_fake_srs, verify_aggregate_proof, AggregateProof, AggregateVersion,
-        GenericSRS,
+        aggregate_proofs, aggregate_proofs_and_linked_instances, aggregate_proofs_multi_vk,
+        aggregate_proofs_multi_vk_with_statement, aggregate_proofs_sharded,
+        aggregate_proofs_with_statement, aggregate_proofs_with_statement_and_transcript,
+        aggregate_proofs_with_transcript, aggregate_shards, commit_shard, linked_instance_boundary,
+        prove_shard, setup_fake_srs, verify_aggregate_proof,
+        verify_aggregate_proof_and_linked_instances, verify_aggregate_proof_multi_vk,
+        verify_aggregate_proof_with_statement,
+        verify_aggregate_proof_with_statement_and_transcript,
+        verify_aggregate_proof_with_transcript, verify_aggregate_proofs_batch,
+        verify_sharded_aggregate_proof, AggregateProof, AggregateProofAndLinkedInstances,
+        AggregateVersion, Aggregator, GenericSRS, InstanceLink, Keccak256Transcript,
+        MultiAggregateProof, ProverSRS, ShardedAggregateProof, VerifierSRS,
     },
     create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
-    verify_proofs_batch, Parameters, Proof,
+    verify_proofs_batch, Parameters, PreparedVerifyingKey, Proof,
 };
 use blstrs::{Bls12, Scalar as Fr};
 use ff::{Field, PrimeField};
@@ -305,7 +318,7 @@ fn test_groth16_bench_inner(version: AggregateVersion) {
     let pvk = prepare_verifying_key(&params.vk);
 
     let (proofs, statements): (Vec<Proof<Bls12>>, Vec<Vec<Fr>>) = (0..max)
-        .map(|_| generate_proof(public_inputs, &params, &mut rng))
+        .map(|_| generate_proof(&vec![Fr::from(4u64); public_inputs], &params, &mut rng))
         .unzip();
 
     let mut writer = csv::Writer::from_path("aggregation.csv").expect("unable to open csv writer");
@@ -316,9 +329,10 @@ fn test_groth16_bench_inner(version: AggregateVersion) {
     proofs[0].write(&mut buf).expect("buffer");
     let proof_size = buf.len();
     let inclusion = vec![1, 2, 3];
+    let generic_hash = generic.hash();
     for i in nb_proofs {
         let mut records = Vec::new();
-        let (pk, vk) = generic.specialize(i);
+        let (pk, vk) = generic.specialize_with_hash(i, &generic_hash);
         for _ in 0..n_average {
             println!("Proofs {}", i);
             // Aggregate proofs using inner product proofs
@@ -406,27 +420,21 @@ fn test_groth16_bench_inner(version: AggregateVersion) {
 }
 
 fn generate_proof<R: SeedableRng + RngCore>(
-    publics: usize,
+    inputs: &[Fr],
     p: &Parameters<Bls12>,
     mut rng: &mut R,
 ) -> (Proof<Bls12>, Vec<Fr>) {
-    // Generate random inputs to product together
-    let mut public_inputs = Vec::new();
-    let mut statement = Vec::new();
-    let mut prod = Fr::ONE;
-    for _i in 0..publics {
-        let x = Fr::from(4u64);
-        public_inputs.push(Some(x));
-        statement.push(x);
-        prod.mul_assign(&x);
-    }
+    // The public product is the witness times all the inputs
+    let mut statement = inputs.to_vec();
     let w = Fr::from(3);
     let mut product: Fr = w;
-    product.mul_assign(&prod);
+    for x in inputs {
+        product.mul_assign(x);
+    }
     statement.push(product);
 
     let c = TestCircuit {
-        public_inputs,
+        public_inputs: inputs.iter().copied().map(Some).collect(),
         public_product: Some(product),
         witness_input: Some(w),
     };
@@ -437,6 +445,34 @@ fn generate_proof<R: SeedableRng + RngCore>(
     (create_random_proof(c, p, &mut rng).unwrap(), statement)
 }
 
+type Setup = (
+    Parameters<Bls12>,
+    PreparedVerifyingKey<Bls12>,
+    Vec<Proof<Bls12>>,
+    Vec<Vec<Fr>>,
+);
+
+/// Creates the parameters of a circuit with `num_inputs` public inputs and
+/// `num_proofs` proofs of it, where all the inputs of proof `i` are `i + 2`.
+fn setup<R: SeedableRng + RngCore>(num_proofs: usize, num_inputs: usize, rng: &mut R) -> Setup {
+    let params = {
+        let c = TestCircuit::<Fr> {
+            public_inputs: vec![Default::default(); num_inputs],
+            public_product: Default::default(),
+            witness_input: Default::default(),
+        };
+
+        generate_random_parameters(c, &mut *rng).unwrap()
+    };
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let (proofs, statements) = (0..num_proofs)
+        .map(|i| generate_proof(&vec![Fr::from(i as u64 + 2); num_inputs], &params, rng))
+        .unzip();
+
+    (params, pvk, proofs, statements)
+}
+
 #[test]
 fn test_groth16_aggregation() {
     test_groth16_aggregation_inner(AggregateVersion::V1);
@@ -549,10 +585,20 @@ fn test_groth16_aggregation_inner(version: AggregateVersion) {
     )
     .unwrap());
 
-    // 2. Non power of two
-    let err = aggregate_proofs::<Bls12>(&pk, &to_include, &proofs[0..NUM_PROOFS - 1], version)
-        .unwrap_err();
-    assert!(matches!(err, SynthesisError::NonPowerOfTwo));
+    // 2. Non power of two, the proofs are padded
+    let padded_proof =
+        aggregate_proofs::<Bls12>(&pk, &to_include, &proofs[0..NUM_PROOFS - 1], version)
+            .expect("failed to aggregate proofs");
+    assert!(verify_aggregate_proof(
+        &vk,
+        &pvk,
+        &mut rng,
+        &statements[0..NUM_PROOFS - 1],
+        &padded_proof,
+        &to_include,
+        version,
+    )
+    .unwrap());
 
     // 3. aggregate invalid proof content (random A, B, and C)
     let old_a = proofs[0].a;
@@ -754,6 +800,824 @@ fn test_groth16_aggregation_mimc_inner(version: AggregateVersion) {
     println!("Proof batch verification time: {} ms", batch_verifier_time);
 }
 
+#[test]
+fn test_groth16_aggregation_padding() {
+    test_groth16_aggregation_padding_inner(AggregateVersion::V1);
+    test_groth16_aggregation_padding_inner(AggregateVersion::V2);
+}
+
+/// Aggregates a number of proofs that is not a power of two, each with distinct public inputs.
+fn test_groth16_aggregation_padding_inner(version: AggregateVersion) {
+    const NUM_PUBLIC_INPUTS: usize = 2;
+    const MAX_PROOFS: usize = 8;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, MAX_PROOFS);
+    let (_, pvk, proofs, statements) = setup(MAX_PROOFS, NUM_PUBLIC_INPUTS, &mut rng);
+
+    let to_include = vec![1, 2, 3];
+    let generic_hash = generic.hash();
+    for num_proofs in [3, 5, 6, 7] {
+        let (pk, vk) = generic.specialize_with_hash(num_proofs, &generic_hash);
+        let proofs = &proofs[..num_proofs];
+        let statements = &statements[..num_proofs];
+
+        let mut aggregate_proof = aggregate_proofs::<Bls12>(&pk, &to_include, proofs, version)
+            .expect("failed to aggregate proofs");
+        assert_eq!(aggregate_proof.tmipp.gipa.nproofs as usize, num_proofs);
+        assert!(verify_aggregate_proof(
+            &vk,
+            &pvk,
+            &mut rng,
+            statements,
+            &aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap());
+
+        // The public inputs of the padding proofs are derived from the last proof
+        let mut invalid_statements = statements.to_vec();
+        invalid_statements[num_proofs - 1][0] = Fr::ONE;
+        assert!(!verify_aggregate_proof(
+            &vk,
+            &pvk,
+            &mut rng,
+            &invalid_statements,
+            &aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap());
+
+        // The number of real proofs is bound to the transcript
+        let mut invalid_statements = statements.to_vec();
+        invalid_statements.push(statements[num_proofs - 1].clone());
+        aggregate_proof.tmipp.gipa.nproofs += 1;
+        assert!(!verify_aggregate_proof(
+            &vk,
+            &pvk,
+            &mut rng,
+            &invalid_statements,
+            &aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap());
+    }
+}
+
+#[test]
+fn test_groth16_aggregation_streaming() {
+    test_groth16_aggregation_streaming_inner(AggregateVersion::V1);
+    test_groth16_aggregation_streaming_inner(AggregateVersion::V2);
+}
+
+/// Aggregates proofs pushed one at a time and compares with the one-shot aggregation.
+fn test_groth16_aggregation_streaming_inner(version: AggregateVersion) {
+    const NUM_PUBLIC_INPUTS: usize = 2;
+    const MAX_PROOFS: usize = 8;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, MAX_PROOFS);
+    let (_, pvk, proofs, statements) = setup(MAX_PROOFS, NUM_PUBLIC_INPUTS, &mut rng);
+
+    let to_include = vec![1, 2, 3];
+    let generic_hash = generic.hash();
+    for num_proofs in [5, 8] {
+        let (pk, vk) = generic.specialize_with_hash(num_proofs, &generic_hash);
+        let proofs = &proofs[..num_proofs];
+        let statements = &statements[..num_proofs];
+
+        let expected = aggregate_proofs::<Bls12>(&pk, &to_include, proofs, version)
+            .expect("failed to aggregate proofs");
+        let mut expected_bytes = Vec::new();
+        expected.write(&mut expected_bytes).unwrap();
+
+        // The tables of a memory mapped SRS are used in place
+        let mut srs_file = tempfile::tempfile().unwrap();
+        pk.write(&mut srs_file).unwrap();
+        let mmap = unsafe { memmap2::Mmap::map(&srs_file).unwrap() };
+        let mapped_pk = ProverSRS::read_mmap(mmap, &generic_hash, num_proofs, false).unwrap();
+        let mapped = aggregate_proofs::<Bls12>(&mapped_pk, &to_include, proofs, version)
+            .expect("failed to aggregate proofs");
+        let mut bytes = Vec::new();
+        mapped.write(&mut bytes).unwrap();
+        assert_eq!(bytes, expected_bytes);
+
+        for chunk_size in [1, 3, 256] {
+            let mut aggregator = Aggregator::new(&pk, version).chunk_size(chunk_size);
+            for proof in proofs {
+                aggregator.push(proof).unwrap();
+            }
+            assert_eq!(aggregator.num_proofs(), num_proofs);
+            let aggregate_proof = aggregator
+                .finish(&to_include)
+                .expect("failed to aggregate proofs");
+
+            let mut bytes = Vec::new();
+            aggregate_proof.write(&mut bytes).unwrap();
+            assert_eq!(bytes, expected_bytes);
+            assert!(verify_aggregate_proof(
+                &vk,
+                &pvk,
+                &mut rng,
+                statements,
+                &aggregate_proof,
+                &to_include,
+                version,
+            )
+            .unwrap());
+        }
+    }
+
+    // More proofs than the SRS is specialized for
+    let pk = generic.specialize_prover_with_hash(4, &generic_hash);
+    let mut aggregator = Aggregator::new(&pk, version);
+    for proof in &proofs[..4] {
+        aggregator.push(proof).unwrap();
+    }
+    assert!(aggregator.push(&proofs[4]).is_err());
+
+    // Less proofs than the SRS is specialized for, after padding
+    let mut aggregator = Aggregator::new(&pk, version);
+    aggregator.push(&proofs[0]).unwrap();
+    aggregator.push(&proofs[1]).unwrap();
+    assert!(aggregator.finish(&to_include).is_err());
+}
+
+#[test]
+fn test_groth16_aggregation_multi_vk() {
+    test_groth16_aggregation_multi_vk_inner(AggregateVersion::V1);
+    test_groth16_aggregation_multi_vk_inner(AggregateVersion::V2);
+    test_groth16_aggregation_multi_vk_inner(AggregateVersion::V3);
+}
+
+/// Aggregates proofs of three circuits, one of them with a single proof.
+fn test_groth16_aggregation_multi_vk_inner(version: AggregateVersion) {
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, 8);
+    let (pk, vk) = generic.specialize(5);
+
+    // 5 proofs of the first circuit, 2 of the second and 1 of the third one.
+    // The circuits differ in their number of public inputs.
+    let circuits = [0, 1, 0, 0, 2, 1, 0, 0];
+    let setups: Vec<_> = (0..3)
+        .map(|circuit| {
+            let num_proofs = circuits.iter().filter(|&&c| c == circuit).count();
+            setup(num_proofs, circuit + 1, &mut rng)
+        })
+        .collect();
+    let vks: Vec<_> = setups
+        .iter()
+        .map(|(params, ..)| params.vk.clone())
+        .collect();
+    let pvks: Vec<_> = vks.iter().map(prepare_verifying_key).collect();
+
+    let mut per_circuit: Vec<_> = setups
+        .into_iter()
+        .map(|(_, _, proofs, statements)| proofs.into_iter().zip(statements))
+        .collect();
+    let (proofs, statements): (Vec<_>, Vec<_>) = circuits
+        .iter()
+        .map(|circuit| per_circuit[*circuit].next().unwrap())
+        .unzip();
+
+    let to_include = vec![1, 2, 3];
+    let to_aggregate: Vec<_> = circuits
+        .iter()
+        .zip(proofs.iter())
+        .map(|(circuit, proof)| (&vks[*circuit], proof))
+        .collect();
+    let aggregate_proof = match version {
+        AggregateVersion::V3 => aggregate_proofs_multi_vk_with_statement(
+            &pk,
+            &statements,
+            &to_include,
+            &to_aggregate,
+            version,
+        ),
+        _ => aggregate_proofs_multi_vk(&pk, &to_include, &to_aggregate, version),
+    }
+    .expect("failed to aggregate proofs");
+    assert_eq!(aggregate_proof.groups.len(), 3);
+
+    let mut buffer = Vec::new();
+    aggregate_proof.write(&mut buffer).unwrap();
+    let read_proof = MultiAggregateProof::<Bls12>::read(&buffer[..]).unwrap();
+    assert!(read_proof == aggregate_proof);
+
+    let items: Vec<_> = circuits
+        .iter()
+        .zip(statements.iter())
+        .map(|(circuit, statement)| (&pvks[*circuit], &statement[..]))
+        .collect();
+    assert!(verify_aggregate_proof_multi_vk(
+        &vk,
+        &mut rng,
+        &items,
+        &aggregate_proof,
+        &to_include,
+        version,
+    )
+    .unwrap());
+
+    // Invalid transcript inclusion
+    assert!(!verify_aggregate_proof_multi_vk(
+        &vk,
+        &mut rng,
+        &items,
+        &aggregate_proof,
+        &[4, 5, 6],
+        version,
+    )
+    .unwrap());
+
+    // Invalid public inputs of each circuit
+    for i in [3, 4, 5] {
+        let mut invalid_statement = statements[i].clone();
+        invalid_statement[0] = Fr::ONE;
+        let mut invalid_items = items.clone();
+        invalid_items[i].1 = &invalid_statement;
+        assert!(!verify_aggregate_proof_multi_vk(
+            &vk,
+            &mut rng,
+            &invalid_items,
+            &aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap());
+    }
+
+    // Proofs in a different order
+    let mut invalid_items = items.clone();
+    invalid_items.swap(0, 2);
+    assert!(!verify_aggregate_proof_multi_vk(
+        &vk,
+        &mut rng,
+        &invalid_items,
+        &aggregate_proof,
+        &to_include,
+        version,
+    )
+    .unwrap());
+
+    // Keys are grouped by content, not by reference
+    let cloned_vks: Vec<_> = circuits.iter().map(|c| vks[*c].clone()).collect();
+    let cloned_pvks: Vec<_> = cloned_vks.iter().map(prepare_verifying_key).collect();
+    let to_aggregate: Vec<_> = cloned_vks.iter().zip(proofs.iter()).collect();
+    let cloned_proof = aggregate_proofs_multi_vk_with_statement(
+        &pk,
+        &statements,
+        &to_include,
+        &to_aggregate,
+        version,
+    )
+    .expect("failed to aggregate proofs");
+    assert_eq!(cloned_proof.groups.len(), 3);
+    let cloned_items: Vec<_> = cloned_pvks
+        .iter()
+        .zip(statements.iter())
+        .map(|(pvk, statement)| (pvk, &statement[..]))
+        .collect();
+    for proof in [&aggregate_proof, &cloned_proof] {
+        assert!(verify_aggregate_proof_multi_vk(
+            &vk,
+            &mut rng,
+            &cloned_items,
+            proof,
+            &to_include,
+            version,
+        )
+        .unwrap());
+    }
+
+    // Missing the proof of the third circuit
+    let mut invalid_items = items.clone();
+    invalid_items.remove(4);
+    assert!(verify_aggregate_proof_multi_vk(
+        &vk,
+        &mut rng,
+        &invalid_items,
+        &aggregate_proof,
+        &to_include,
+        version,
+    )
+    .is_err());
+
+    if version == AggregateVersion::V3 {
+        // The aggregated proofs are bound to the SRS
+        let other_vk = VerifierSRS {
+            generic_hash: setup_fake_srs::<Bls12, _>(&mut rng, 8).hash(),
+            ..vk.clone()
+        };
+        assert!(!verify_aggregate_proof_multi_vk(
+            &other_vk,
+            &mut rng,
+            &items,
+            &aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap());
+
+        // V3 can't be used without the statements
+        let to_aggregate: Vec<_> = cloned_vks.iter().zip(proofs.iter()).collect();
+        assert!(aggregate_proofs_multi_vk(&pk, &to_include, &to_aggregate, version).is_err());
+    }
+}
+
+#[test]
+fn test_groth16_aggregation_statement() {
+    const NUM_PUBLIC_INPUTS: usize = 2;
+    const NUM_PROOFS: usize = 8;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, NUM_PROOFS);
+    let generic_hash = generic.hash();
+    let (pk, vk) = generic.specialize_with_hash(NUM_PROOFS, &generic_hash);
+    assert_eq!(pk.generic_hash, generic_hash);
+    assert_eq!(vk.generic_hash, generic_hash);
+    // The hash is streamed over the serialized generic SRS
+    let mut generic_bytes = Vec::new();
+    generic.write(&mut generic_bytes).unwrap();
+    assert_eq!(
+        generic_hash,
+        <sha2::Sha256 as sha2::Digest>::digest(&generic_bytes).to_vec()
+    );
+    let (params, pvk, proofs, statements) = setup(NUM_PROOFS, NUM_PUBLIC_INPUTS, &mut rng);
+    assert_eq!(pvk.digest(), params.vk.digest());
+
+    let to_include = vec![1, 2, 3];
+    let aggregate_proof = aggregate_proofs_with_statement::<Bls12>(
+        &pk,
+        &params.vk,
+        &statements,
+        &to_include,
+        &proofs,
+        AggregateVersion::V3,
+    )
+    .expect("failed to aggregate proofs");
+    assert!(verify_aggregate_proof_with_statement(
+        &vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &aggregate_proof,
+        &to_include,
+        AggregateVersion::V3,
+    )
+    .unwrap());
+
+    // The proof is bound to the SRS
+    let other_vk = VerifierSRS {
+        generic_hash: setup_fake_srs::<Bls12, _>(&mut rng, NUM_PROOFS).hash(),
+        ..vk.clone()
+    };
+    assert!(!verify_aggregate_proof_with_statement(
+        &other_vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &aggregate_proof,
+        &to_include,
+        AggregateVersion::V3,
+    )
+    .unwrap());
+
+    // Invalid public inputs
+    let mut invalid_statements = statements.clone();
+    invalid_statements[3][0] = Fr::ONE;
+    assert!(!verify_aggregate_proof_with_statement(
+        &vk,
+        &pvk,
+        &mut rng,
+        &invalid_statements,
+        &aggregate_proof,
+        &to_include,
+        AggregateVersion::V3,
+    )
+    .unwrap());
+
+    // The statement is bound with the transcript of the aggregation
+    let keccak_proof =
+        aggregate_proofs_with_statement_and_transcript::<Bls12, Keccak256Transcript<Bls12>>(
+            &pk,
+            &params.vk,
+            &statements,
+            &to_include,
+            &proofs,
+            AggregateVersion::V3,
+        )
+        .expect("failed to aggregate proofs");
+    assert!(
+        verify_aggregate_proof_with_statement_and_transcript::<_, _, Keccak256Transcript<Bls12>>(
+            &vk,
+            &pvk,
+            &mut rng,
+            &statements,
+            &keccak_proof,
+            &to_include,
+            AggregateVersion::V3,
+        )
+        .unwrap()
+    );
+    assert!(!verify_aggregate_proof_with_statement(
+        &vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &keccak_proof,
+        &to_include,
+        AggregateVersion::V3,
+    )
+    .unwrap());
+
+    // V3 can't be used without the statement
+    assert!(aggregate_proofs::<Bls12>(&pk, &to_include, &proofs, AggregateVersion::V3).is_err());
+    assert!(verify_aggregate_proof(
+        &vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &aggregate_proof,
+        &to_include,
+        AggregateVersion::V3,
+    )
+    .is_err());
+
+    // The statement is ignored by the previous versions
+    for version in [AggregateVersion::V1, AggregateVersion::V2] {
+        let bound = aggregate_proofs_with_statement::<Bls12>(
+            &pk,
+            &params.vk,
+            &statements,
+            &to_include,
+            &proofs,
+            version,
+        )
+        .unwrap();
+        let unbound = aggregate_proofs::<Bls12>(&pk, &to_include, &proofs, version).unwrap();
+        let (mut bound_bytes, mut unbound_bytes) = (Vec::new(), Vec::new());
+        bound.write(&mut bound_bytes).unwrap();
+        unbound.write(&mut unbound_bytes).unwrap();
+        assert_eq!(bound_bytes, unbound_bytes);
+        assert!(verify_aggregate_proof_with_statement(
+            &other_vk,
+            &pvk,
+            &mut rng,
+            &statements,
+            &bound,
+            &to_include,
+            version,
+        )
+        .unwrap());
+    }
+}
+
+#[test]
+fn test_groth16_aggregation_keccak_transcript() {
+    test_groth16_aggregation_keccak_transcript_inner(AggregateVersion::V1);
+    test_groth16_aggregation_keccak_transcript_inner(AggregateVersion::V2);
+}
+
+fn test_groth16_aggregation_keccak_transcript_inner(version: AggregateVersion) {
+    const NUM_PUBLIC_INPUTS: usize = 2;
+    const NUM_PROOFS: usize = 4;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, NUM_PROOFS);
+    let (pk, vk) = generic.specialize(NUM_PROOFS);
+    let (_, pvk, proofs, statements) = setup(NUM_PROOFS, NUM_PUBLIC_INPUTS, &mut rng);
+
+    let to_include = vec![1, 2, 3];
+    let aggregate_proof = aggregate_proofs_with_transcript::<Bls12, Keccak256Transcript<Bls12>>(
+        &pk,
+        &to_include,
+        &proofs,
+        version,
+    )
+    .expect("failed to aggregate proofs");
+    assert!(
+        verify_aggregate_proof_with_transcript::<_, _, Keccak256Transcript<Bls12>>(
+            &vk,
+            &pvk,
+            &mut rng,
+            &statements,
+            &aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap()
+    );
+
+    // The challenges of the default transcript differ
+    assert!(!verify_aggregate_proof(
+        &vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &aggregate_proof,
+        &to_include,
+        version,
+    )
+    .unwrap());
+}
+
+#[test]
+fn test_groth16_aggregation_batch() {
+    test_groth16_aggregation_batch_inner(AggregateVersion::V1);
+    test_groth16_aggregation_batch_inner(AggregateVersion::V2);
+    test_groth16_aggregation_batch_inner(AggregateVersion::V3);
+}
+
+/// Verifies aggregated proofs of different sizes together and identifies the invalid ones.
+fn test_groth16_aggregation_batch_inner(version: AggregateVersion) {
+    const NUM_PUBLIC_INPUTS: usize = 2;
+    const MAX_PROOFS: usize = 4;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, MAX_PROOFS);
+    let (params, pvk, proofs, statements) = setup(MAX_PROOFS, NUM_PUBLIC_INPUTS, &mut rng);
+
+    let sizes = [2, 4, 3, 2, 4];
+    let includes: Vec<Vec<u8>> = (0..sizes.len() as u8).map(|i| vec![i; 3]).collect();
+    let generic_hash = generic.hash();
+    let vks: Vec<_> = sizes
+        .iter()
+        .map(|&n| generic.specialize_vk_with_hash(n, &generic_hash))
+        .collect();
+    let aggregate_proofs: Vec<_> = sizes
+        .iter()
+        .zip(includes.iter())
+        .map(|(&n, include)| {
+            let pk = generic.specialize_prover_with_hash(n, &generic_hash);
+            aggregate_proofs_with_statement::<Bls12>(
+                &pk,
+                &params.vk,
+                &statements[..n],
+                include,
+                &proofs[..n],
+                version,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    let verify = |rng: &mut rand_chacha::ChaChaRng,
+                  vks: &[VerifierSRS<Bls12>],
+                  statements: &[Vec<Vec<Fr>>]| {
+        let items: Vec<_> = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| {
+                (
+                    &vks[i],
+                    &pvk,
+                    &statements[i][..n],
+                    &aggregate_proofs[i],
+                    &includes[i][..],
+                )
+            })
+            .collect();
+        verify_aggregate_proofs_batch(&items, rng, version).unwrap()
+    };
+
+    let all_statements = vec![statements; sizes.len()];
+    assert!(verify(&mut rng, &vks, &all_statements).is_empty());
+
+    for bad in [vec![0], vec![4], vec![1, 2], vec![0, 2, 3, 4]] {
+        let mut invalid_statements = all_statements.clone();
+        for &i in &bad {
+            invalid_statements[i][1][0] = Fr::ONE;
+        }
+        assert_eq!(verify(&mut rng, &vks, &invalid_statements), bad);
+    }
+
+    // A proof that does not match its public inputs is reported as well
+    let mut invalid_statements = all_statements.clone();
+    invalid_statements[3][0].push(Fr::ONE);
+    assert_eq!(verify(&mut rng, &vks, &invalid_statements), vec![3]);
+
+    if version == AggregateVersion::V3 {
+        // The aggregated proofs are bound to the SRS
+        let mut other_vks = vks;
+        other_vks[1].generic_hash = setup_fake_srs::<Bls12, _>(&mut rng, MAX_PROOFS).hash();
+        assert_eq!(verify(&mut rng, &other_vks, &all_statements), vec![1]);
+    }
+}
+
+#[test]
+fn test_groth16_aggregation_linked_instances() {
+    test_groth16_aggregation_linked_instances_inner(AggregateVersion::V1);
+    test_groth16_aggregation_linked_instances_inner(AggregateVersion::V2);
+}
+
+/// Aggregates proofs with statements [a, b, c, product], where a is shared by all
+/// proofs and c of proof i + 2 is b of proof i.
+fn test_groth16_aggregation_linked_instances_inner(version: AggregateVersion) {
+    const NUM_PROOFS: usize = 8;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, NUM_PROOFS);
+    let (pk, vk) = generic.specialize_input_aggregation(NUM_PROOFS);
+    let (params, pvk, _, _) = setup(0, 3, &mut rng);
+
+    let a = Fr::from(5);
+    let b: Vec<Fr> = (0..NUM_PROOFS).map(|_| Fr::random(&mut rng)).collect();
+    let mut c: Vec<Fr> = (0..NUM_PROOFS).map(|_| Fr::random(&mut rng)).collect();
+    c[2..].copy_from_slice(&b[..NUM_PROOFS - 2]);
+
+    let (proofs, statements): (Vec<_>, Vec<_>) = (0..NUM_PROOFS)
+        .map(|i| generate_proof(&[a, b[i], c[i]], &params, &mut rng))
+        .unzip();
+
+    let links = [InstanceLink::shared(0), InstanceLink::new(1, 2, 2)];
+    let to_include = vec![1, 2, 3];
+    let aggregate_proof = aggregate_proofs_and_linked_instances::<Bls12>(
+        &pk,
+        &to_include,
+        &statements,
+        &proofs,
+        &links,
+        version,
+    )
+    .expect("failed to aggregate proofs");
+    // The inputs b are derived from c, whose first two coefficients are
+    // proven, the other inputs are committed.
+    assert_eq!(aggregate_proof.com_inputs.len(), 3);
+    assert_eq!(aggregate_proof.com_shifts.len(), 2);
+
+    let mut buffer = Vec::new();
+    aggregate_proof.write(&mut buffer).unwrap();
+    let read_proof = AggregateProofAndLinkedInstances::read(&buffer[..]).unwrap();
+    assert_eq!(read_proof, aggregate_proof);
+
+    let cells = linked_instance_boundary(&links, NUM_PROOFS);
+    assert_eq!(cells, vec![(0, 0), (0, 2), (1, 2), (6, 1), (7, 0), (7, 1)]);
+    let boundary: Vec<Fr> = cells.iter().map(|&(i, j)| statements[i][j]).collect();
+    let verify = |rng: &mut rand_chacha::ChaChaRng,
+                  boundary: &[Fr],
+                  aggregate_proof: &AggregateProofAndLinkedInstances<Bls12>| {
+        verify_aggregate_proof_and_linked_instances(
+            &vk,
+            &pvk,
+            rng,
+            boundary,
+            &links,
+            aggregate_proof,
+            &to_include,
+            version,
+        )
+        .unwrap()
+    };
+    assert!(verify(&mut rng, &boundary, &aggregate_proof));
+
+    for i in 0..boundary.len() {
+        let mut invalid_boundary = boundary.clone();
+        invalid_boundary[i] += Fr::ONE;
+        assert!(!verify(&mut rng, &invalid_boundary, &aggregate_proof));
+    }
+
+    let mut invalid_proof = aggregate_proof.clone();
+    invalid_proof.input_evals[0] += Fr::ONE;
+    assert!(!verify(&mut rng, &boundary, &invalid_proof));
+
+    for i in 0..aggregate_proof.com_shifts.len() {
+        let mut invalid_proof = aggregate_proof.clone();
+        invalid_proof.com_shifts[i] = invalid_proof.com_shifts[i].double();
+        assert!(!verify(&mut rng, &boundary, &invalid_proof));
+    }
+
+    let mut invalid_proof = aggregate_proof.clone();
+    invalid_proof.com_bounds[1] = invalid_proof.com_bounds[1].double();
+    assert!(!verify(&mut rng, &boundary, &invalid_proof));
+
+    // The statements must satisfy the links
+    let mut invalid_statements = statements;
+    invalid_statements[3][0] = Fr::ONE;
+    assert!(aggregate_proofs_and_linked_instances::<Bls12>(
+        &pk,
+        &to_include,
+        &invalid_statements,
+        &proofs,
+        &links,
+        version,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_groth16_aggregation_sharded() {
+    const NUM_PUBLIC_INPUTS: usize = 2;
+    const SHARD_SIZE: usize = 4;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+
+    let generic = setup_fake_srs(&mut rng, SHARD_SIZE);
+    let (pk, vk) = generic.specialize(SHARD_SIZE);
+    let (_, pvk, proofs, statements) = setup(12, NUM_PUBLIC_INPUTS, &mut rng);
+
+    // Full shards, a single proof shard and a partial shard
+    let sizes = [4, 1, 3, 4];
+    let mut rest = &proofs[..];
+    let shards: Vec<&[Proof<Bls12>]> = sizes
+        .iter()
+        .map(|&n| {
+            let (shard, tail) = rest.split_at(n);
+            rest = tail;
+            shard
+        })
+        .collect();
+    let to_include = vec![1, 2, 3];
+
+    let aggregate_proof = aggregate_proofs_sharded(&pk, &to_include, &shards).unwrap();
+    assert_eq!(aggregate_proof.nproofs(), proofs.len());
+    assert_eq!(aggregate_proof.shard_size(), SHARD_SIZE);
+    assert!(verify_sharded_aggregate_proof(
+        &vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &aggregate_proof,
+        &to_include,
+    )
+    .unwrap());
+
+    // The shards can be committed to and proven separately
+    let commitments: Vec<_> = shards
+        .iter()
+        .map(|shard| commit_shard(&pk, shard).unwrap())
+        .collect();
+    let shard_proofs: Vec<_> = shards
+        .iter()
+        .enumerate()
+        .map(|(i, shard)| prove_shard(&pk, &to_include, &commitments, i, shard).unwrap())
+        .collect();
+    let stepwise_proof =
+        aggregate_shards(&pk, &to_include, commitments, shard_proofs, &shards).unwrap();
+    assert!(stepwise_proof == aggregate_proof);
+
+    let mut buffer = Vec::new();
+    aggregate_proof.write(&mut buffer).unwrap();
+    let read_proof = ShardedAggregateProof::<Bls12>::read(&buffer[..]).unwrap();
+    assert!(read_proof == aggregate_proof);
+
+    let verify = |rng: &mut rand_chacha::ChaChaRng,
+                  statements: &[Vec<Fr>],
+                  proof: &ShardedAggregateProof<Bls12>,
+                  include: &[u8]| {
+        verify_sharded_aggregate_proof(&vk, &pvk, rng, statements, proof, include)
+    };
+
+    // Invalid public inputs, in a full and in a partial shard
+    for i in [0, 7] {
+        let mut invalid_statements = statements.clone();
+        invalid_statements[i][0] = Fr::ONE;
+        assert!(!verify(&mut rng, &invalid_statements, &aggregate_proof, &to_include).unwrap());
+    }
+
+    // Another transcript inclusion
+    assert!(!verify(&mut rng, &statements, &aggregate_proof, &[4, 5, 6]).unwrap());
+
+    // Swapped shards of the same size
+    let mut swapped_proof = aggregate_proof.clone();
+    swapped_proof.commitments.swap(0, 3);
+    swapped_proof.shards.swap(0, 3);
+    assert!(!verify(&mut rng, &statements, &swapped_proof, &to_include).unwrap());
+
+    // A shard proof from another aggregation
+    let other_proof = aggregate_proofs_sharded(&pk, &[4, 5, 6], &shards).unwrap();
+    let mut mixed_proof = aggregate_proof.clone();
+    mixed_proof.shards[2] = other_proof.shards[2].clone();
+    assert!(!verify(&mut rng, &statements, &mixed_proof, &to_include).unwrap());
+
+    // The public inputs must match the number of proofs
+    assert!(verify(&mut rng, &statements[..11], &aggregate_proof, &to_include).is_err());
+
+    // Every shard needs its commitment and its proof
+    let mut truncated_proof = aggregate_proof.clone();
+    truncated_proof.shards.pop();
+    assert!(verify(&mut rng, &statements, &truncated_proof, &to_include).is_err());
+
+    // The verifier SRS must match the size of the shards
+    let (_, other_vk) = generic.specialize(SHARD_SIZE / 2);
+    assert!(verify_sharded_aggregate_proof(
+        &other_vk,
+        &pvk,
+        &mut rng,
+        &statements,
+        &aggregate_proof,
+        &to_include,
+    )
+    .is_err());
+
+    // Shards must hold between one and SHARD_SIZE proofs
+    assert!(aggregate_proofs_sharded(&pk, &to_include, &[&proofs[..5]]).is_err());
+    assert!(aggregate_proofs_sharded(&pk, &to_include, &[&proofs[..2], &[]]).is_err());
+}
+
 #[test]
 fn test_groth16_aggregate_versions() {
     let _ = env_logger::try_init();
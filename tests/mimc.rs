@@ -1,3 +1,6 @@
+// The tests depend on the prover.
+#![cfg(feature = "prover")]
+
 mod util;
 
 // For randomness (during paramgen and proof generation)
@@ -1,3 +1,6 @@
+// The tests depend on the prover.
+#![cfg(feature = "prover")]
+
 extern crate ff;
 extern crate rand;
 
@@ -219,8 +222,9 @@ fn minroot_test() {
 }
 
 use bellperson::groth16::aggregate::{
-    aggregate_proofs_and_instances, setup_fake_srs, verify_aggregate_proof_and_aggregate_instances,
-    GenericSRS,
+    aggregate_proofs_and_instances, aggregate_proofs_and_linked_instances,
+    linked_instance_boundary, setup_fake_srs, verify_aggregate_proof_and_aggregate_instances,
+    verify_aggregate_proof_and_linked_instances, GenericSRS, InstanceLink,
 };
 use blstrs::Scalar as Fr;
 use rand_core::SeedableRng;
@@ -307,6 +311,37 @@ fn minroot_aggregate_proof_inner(version: AggregateVersion) {
     .unwrap();
 
     assert!(verified, "failed to verify aggregate proof");
+
+    // The same relation expressed as instance links
+    let links = InstanceLink::chain(4);
+    let aggregate_proof_and_instances = aggregate_proofs_and_linked_instances::<Bls12>(
+        &pk,
+        &inclusion,
+        &statements,
+        &proofs,
+        &links,
+        version,
+    )
+    .expect("failed to aggregate proofs");
+
+    assert_eq!(
+        linked_instance_boundary(&links, nb_proofs),
+        vec![(0, 0), (0, 1), (nb_proofs - 1, 2), (nb_proofs - 1, 3)]
+    );
+    let boundary = [public_inputs, public_outputs].concat();
+    let verified = verify_aggregate_proof_and_linked_instances(
+        &vk,
+        &pvk,
+        &mut rng,
+        &boundary,
+        &links,
+        &aggregate_proof_and_instances,
+        &inclusion,
+        version,
+    )
+    .unwrap();
+
+    assert!(verified, "failed to verify aggregate proof");
 }
 
 fn generate_proof(
@@ -0,0 +1,207 @@
+//! Tests for the verifiers that do not depend on the prover. The proofs are read from fixtures, so
+//! that the tests also run without the `prover` feature and in a WebAssembly runtime, e.g.
+//! via `wasm-pack test --node -- --no-default-features --features groth16`.
+//!
+//! The fixtures can be regenerated with
+//! `cargo test --release --test verifier_only -- --ignored generate_fixtures`.
+
+// SupraSeal does not support the proof generation of the fixture generation.
+#![cfg(not(feature = "cuda-supraseal"))]
+
+use bellperson::groth16::{
+    aggregate::{verify_aggregate_proof, AggregateProof, AggregateVersion, GenericSRS},
+    prepare_verifying_key, verify_proof, verify_proofs_batch, Proof, VerifyingKey,
+};
+use blstrs::{Bls12, Scalar as Fr};
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Curve, Group};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+const NUM_PROOFS: usize = 8;
+const TRANSCRIPT_INCLUDE: &[u8] = b"verifier-only";
+
+const VK: &[u8] = include_bytes!("fixtures/verifier_only/vk.bin");
+const PROOFS: &[u8] = include_bytes!("fixtures/verifier_only/proofs.bin");
+const SRS: &[u8] = include_bytes!("fixtures/verifier_only/srs.bin");
+const AGGREGATE_PROOF: &[u8] = include_bytes!("fixtures/verifier_only/aggregate_proof.bin");
+
+/// The witness of the i-th proof, the public input is its square.
+fn witness(i: usize) -> Fr {
+    Fr::from(i as u64 + 2)
+}
+
+fn public_inputs() -> Vec<Vec<Fr>> {
+    (0..NUM_PROOFS).map(|i| vec![witness(i).square()]).collect()
+}
+
+fn proofs() -> Vec<Proof<Bls12>> {
+    Proof::read_many(PROOFS, NUM_PROOFS).expect("failed to read proofs")
+}
+
+#[test]
+fn test_verify_proof() {
+    let vk = VerifyingKey::<Bls12>::read(VK).unwrap();
+    let pvk = prepare_verifying_key(&vk);
+    let inputs = public_inputs();
+
+    for (proof, input) in proofs().iter().zip(inputs.iter()) {
+        assert!(verify_proof(&pvk, proof, input).unwrap());
+        assert!(!verify_proof(&pvk, proof, &[Fr::ONE]).unwrap());
+    }
+}
+
+#[test]
+fn test_verify_proofs_batch() {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let vk = VerifyingKey::<Bls12>::read(VK).unwrap();
+    let pvk = prepare_verifying_key(&vk);
+    let inputs = public_inputs();
+    let mut proofs = proofs();
+
+    let proof_refs: Vec<_> = proofs.iter().collect();
+    assert!(verify_proofs_batch(&pvk, &mut rng, &proof_refs, &inputs).unwrap());
+
+    proofs[3].c = (proofs[3].c.to_curve() + blstrs::G1Projective::generator()).to_affine();
+    let proof_refs: Vec<_> = proofs.iter().collect();
+    assert!(!verify_proofs_batch(&pvk, &mut rng, &proof_refs, &inputs).unwrap());
+}
+
+#[test]
+fn test_verify_aggregate_proof() {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let vk = VerifyingKey::<Bls12>::read(VK).unwrap();
+    let pvk = prepare_verifying_key(&vk);
+    let srs = GenericSRS::<Bls12>::read(&mut &SRS[..]).unwrap();
+    let verifier_srs = srs.specialize_vk(NUM_PROOFS);
+    let aggregate_proof = AggregateProof::<Bls12>::read(AGGREGATE_PROOF).unwrap();
+    let mut inputs = public_inputs();
+
+    assert!(verify_aggregate_proof(
+        &verifier_srs,
+        &pvk,
+        &mut rng,
+        &inputs,
+        &aggregate_proof,
+        TRANSCRIPT_INCLUDE,
+        AggregateVersion::V2,
+    )
+    .unwrap());
+
+    assert!(!verify_aggregate_proof(
+        &verifier_srs,
+        &pvk,
+        &mut rng,
+        &inputs,
+        &aggregate_proof,
+        b"wrong transcript",
+        AggregateVersion::V2,
+    )
+    .unwrap());
+
+    inputs[5][0] = Fr::ONE;
+    assert!(!verify_aggregate_proof(
+        &verifier_srs,
+        &pvk,
+        &mut rng,
+        &inputs,
+        &aggregate_proof,
+        TRANSCRIPT_INCLUDE,
+        AggregateVersion::V2,
+    )
+    .unwrap());
+}
+
+#[cfg(feature = "prover")]
+mod fixtures {
+    use std::fs::File;
+    use std::path::Path;
+
+    use bellperson::groth16::{
+        aggregate::{aggregate_proofs, setup_fake_srs},
+        create_random_proof, generate_random_parameters,
+    };
+    use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+    use ff::PrimeField;
+
+    use super::*;
+
+    /// Proves knowledge of the square root of the public input.
+    struct SquareRoot<Scalar: PrimeField> {
+        root: Option<Scalar>,
+    }
+
+    impl<Scalar: PrimeField> Circuit<Scalar> for SquareRoot<Scalar> {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let root = cs.alloc(
+                || "root",
+                || self.root.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            let square = cs.alloc_input(
+                || "square",
+                || {
+                    self.root
+                        .map(|root| root.square())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?;
+            cs.enforce(
+                || "root * root = square",
+                |lc| lc + root,
+                |lc| lc + root,
+                |lc| lc + square,
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn generate_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/verifier_only");
+        let mut rng = ChaChaRng::seed_from_u64(0);
+
+        let params =
+            generate_random_parameters::<Bls12, _, _>(SquareRoot::<Fr> { root: None }, &mut rng)
+                .unwrap();
+        params
+            .vk
+            .write(File::create(dir.join("vk.bin")).unwrap())
+            .unwrap();
+
+        let proofs: Vec<_> = (0..NUM_PROOFS)
+            .map(|i| {
+                let circuit = SquareRoot {
+                    root: Some(witness(i)),
+                };
+                create_random_proof(circuit, &params, &mut rng).unwrap()
+            })
+            .collect();
+        let mut proofs_file = File::create(dir.join("proofs.bin")).unwrap();
+        for proof in &proofs {
+            proof.write(&mut proofs_file).unwrap();
+        }
+
+        let srs = setup_fake_srs::<Bls12, _>(&mut rng, NUM_PROOFS);
+        srs.write(&mut File::create(dir.join("srs.bin")).unwrap())
+            .unwrap();
+
+        let (prover_srs, _) = srs.specialize(NUM_PROOFS);
+        let aggregate_proof = aggregate_proofs(
+            &prover_srs,
+            TRANSCRIPT_INCLUDE,
+            &proofs,
+            AggregateVersion::V2,
+        )
+        .unwrap();
+        aggregate_proof
+            .write(File::create(dir.join("aggregate_proof.bin")).unwrap())
+            .unwrap();
+    }
+}